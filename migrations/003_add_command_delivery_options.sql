@@ -0,0 +1,5 @@
+-- Add per-command delivery options
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS reply_mode TEXT NOT NULL DEFAULT 'reply';
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS target_channel_id BIGINT;
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS delete_trigger BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS delete_after INTEGER;
@@ -0,0 +1,4 @@
+-- Add options for publishing custom commands as guild slash commands
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS slash BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS description TEXT;
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS arguments TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,8 @@
+-- Create guild_registrations table (last registered command set and its result per guild)
+CREATE TABLE IF NOT EXISTS guild_registrations (
+    guild_id BIGINT PRIMARY KEY,
+    command_hash TEXT,
+    status TEXT NOT NULL,
+    error TEXT,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
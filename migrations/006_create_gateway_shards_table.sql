@@ -0,0 +1,9 @@
+-- Create gateway_shards table (latest status reported by the bot process running each shard)
+CREATE TABLE IF NOT EXISTS gateway_shards (
+    shard_id BIGINT PRIMARY KEY,
+    shard_total BIGINT NOT NULL,
+    stage TEXT NOT NULL,
+    latency_ms BIGINT,
+    guilds BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
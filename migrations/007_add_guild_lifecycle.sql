@@ -0,0 +1,10 @@
+-- Track who added/last updated each command (for per-user data export and deletion)
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS created_by BIGINT;
+ALTER TABLE commands ADD COLUMN IF NOT EXISTS updated_by BIGINT;
+
+-- Create guild_departures table (guilds the bot was removed from and when their data will be purged, in unix seconds)
+CREATE TABLE IF NOT EXISTS guild_departures (
+    guild_id BIGINT PRIMARY KEY,
+    left_at BIGINT NOT NULL,
+    purge_at BIGINT NOT NULL
+);
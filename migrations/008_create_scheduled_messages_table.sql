@@ -0,0 +1,18 @@
+-- Create scheduled_messages table (one-shot when cron is NULL; times are unix seconds)
+CREATE TABLE IF NOT EXISTS scheduled_messages (
+    id BIGSERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    cron TEXT,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    content TEXT,
+    command_name TEXT,
+    next_run_at BIGINT NOT NULL,
+    last_run_at BIGINT,
+    last_error TEXT,
+    created_by BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduled_messages_next_run_at ON scheduled_messages(next_run_at);
+CREATE INDEX IF NOT EXISTS idx_scheduled_messages_guild_id ON scheduled_messages(guild_id);
@@ -0,0 +1,12 @@
+-- Create command_scopes table (per-command channel/category/role allow & deny lists)
+CREATE TABLE IF NOT EXISTS command_scopes (
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    allow BOOLEAN NOT NULL,
+    PRIMARY KEY (guild_id, name, kind, target_id),
+    FOREIGN KEY (guild_id, name) REFERENCES commands(guild_id, name) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_command_scopes_guild_id ON command_scopes(guild_id);
@@ -0,0 +1,5 @@
+-- Add per-command delivery options
+ALTER TABLE commands ADD COLUMN reply_mode TEXT NOT NULL DEFAULT 'reply';
+ALTER TABLE commands ADD COLUMN target_channel_id INTEGER;
+ALTER TABLE commands ADD COLUMN delete_trigger BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE commands ADD COLUMN delete_after INTEGER;
@@ -0,0 +1,5 @@
+-- Add options for publishing custom commands as guild slash commands
+-- (SQLite has no array type, so arguments is stored as a JSON array)
+ALTER TABLE commands ADD COLUMN slash BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE commands ADD COLUMN description TEXT;
+ALTER TABLE commands ADD COLUMN arguments TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,9 @@
+-- Create gateway_shards table (latest status reported by the bot process running each shard)
+CREATE TABLE IF NOT EXISTS gateway_shards (
+    shard_id INTEGER PRIMARY KEY,
+    shard_total INTEGER NOT NULL,
+    stage TEXT NOT NULL,
+    latency_ms INTEGER,
+    guilds INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,10 @@
+-- Track who added/last updated each command (for per-user data export and deletion)
+ALTER TABLE commands ADD COLUMN created_by INTEGER;
+ALTER TABLE commands ADD COLUMN updated_by INTEGER;
+
+-- Create guild_departures table (guilds the bot was removed from and when their data will be purged, in unix seconds)
+CREATE TABLE IF NOT EXISTS guild_departures (
+    guild_id INTEGER PRIMARY KEY,
+    left_at INTEGER NOT NULL,
+    purge_at INTEGER NOT NULL
+);
@@ -0,0 +1,13 @@
+-- Create greetings table (per-guild welcome and farewell messages)
+CREATE TABLE IF NOT EXISTS greetings (
+    guild_id INTEGER PRIMARY KEY,
+    welcome_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    welcome_channel_id INTEGER,
+    welcome_message TEXT NOT NULL DEFAULT '',
+    welcome_dm BOOLEAN NOT NULL DEFAULT FALSE,
+    auto_role_id INTEGER,
+    farewell_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    farewell_channel_id INTEGER,
+    farewell_message TEXT NOT NULL DEFAULT '',
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
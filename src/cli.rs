@@ -0,0 +1,193 @@
+// コマンドライン引数とメンテナンス用のサブコマンド
+// serve/bot-only/web-only 以外は Discord に接続せずに DB を直接操作する
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand};
+use serenity::http::Http;
+use serenity::model::id::GuildId;
+
+use crate::config::{Config, Uses};
+use crate::events::{Event, EventBus};
+use crate::export;
+use crate::list::ListSort;
+use crate::registration::{self, Registration};
+use crate::store::{self, CommandStore};
+
+#[derive(Parser)]
+#[command(name = "nkmzbot", version, about = "Discord のカスタムコマンド Bot と管理用 Web UI")]
+pub struct Cli {
+    /// 設定ファイル (省略時は NKMZBOT_CONFIG、nkmzbot.toml の順に探す)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// 設定を検証して表示し、終了する
+    #[arg(long, global = true)]
+    pub check_config: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Bot と Web UI を起動する (既定)
+    Serve,
+    /// Bot だけを起動する (Web は /healthz・/readyz・/metrics のみ)
+    BotOnly,
+    /// Web UI だけを起動する (Gateway には接続しない)
+    WebOnly,
+    /// マイグレーションを実行して終了する
+    Migrate,
+    /// ギルドのコマンド一式を JSON で書き出す
+    Export {
+        #[arg(long)]
+        guild: i64,
+        /// 書き出し先 (省略時は標準出力)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// export で書き出した JSON をギルドに読み込む
+    Import {
+        #[arg(long)]
+        guild: i64,
+        file: PathBuf,
+        /// 既に存在するコマンドも上書きする
+        #[arg(long)]
+        replace: bool,
+    },
+    /// ギルドのコマンドを一覧表示する
+    List {
+        #[arg(long)]
+        guild: i64,
+    },
+    /// ギルドのスラッシュコマンドを登録し直す
+    RegisterCommands {
+        /// 省略時はコマンドのある全ギルド
+        #[arg(long)]
+        guild: Option<i64>,
+        /// 前回から変更がなくても登録する
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+impl Command {
+    // 設定の検証で必須にする項目
+    pub fn uses(&self) -> Uses {
+        match self {
+            Command::Serve | Command::WebOnly => Uses::ALL,
+            Command::BotOnly | Command::RegisterCommands { .. } => Uses { discord: true, web: false },
+            Command::Migrate | Command::Export { .. } | Command::Import { .. } | Command::List { .. } => Uses { discord: false, web: false },
+        }
+    }
+}
+
+// Gateway に接続せずに API を呼ぶ Http (アプリケーション ID は API から取得する)
+pub async fn discord_http(token: &str) -> Result<Arc<Http>, String> {
+    let http = Http::new(token);
+    let info = http.get_current_application_info().await.map_err(|e| format!("アプリケーション情報の取得に失敗しました: {}", e))?;
+    http.set_application_id(info.id.0);
+    Ok(Arc::new(http))
+}
+
+// メンテナンス用のサブコマンドを実行し、終了コードを返す
+pub async fn run(command: Command, config: &Config) -> i32 {
+    let store = match store::connect(&config.database.url).await {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let result = match command {
+        Command::Migrate => {
+            // マイグレーションは store::connect で実行済み
+            println!("マイグレーションが完了しました。");
+            Ok(())
+        }
+        Command::Export { guild, output } => export(store.as_ref(), guild, output).await,
+        Command::Import { guild, file, replace } => import(store.as_ref(), config, guild, file, replace).await,
+        Command::List { guild } => list(store.as_ref(), guild).await,
+        Command::RegisterCommands { guild, force } => register_commands(store.as_ref(), config, guild, force).await,
+        Command::Serve | Command::BotOnly | Command::WebOnly => unreachable!("server modes are handled in main"),
+    };
+    store.close().await;
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+async fn export(store: &dyn CommandStore, guild: i64, output: Option<PathBuf>) -> Result<(), String> {
+    let data = export::export_guild(store, guild).await.map_err(|e| e.report())?;
+    let json = serde_json::to_string_pretty(&data).map_err(|e| e.to_string())?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, json + "\n").map_err(|e| format!("{}: {}", path.display(), e))?;
+            eprintln!("{} 件のコマンドを {} に書き出しました。", data.commands.len(), path.display());
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+async fn import(store: &dyn CommandStore, config: &Config, guild: i64, file: PathBuf, replace: bool) -> Result<(), String> {
+    let text = std::fs::read_to_string(&file).map_err(|e| format!("{}: {}", file.display(), e))?;
+    let data: export::GuildExport = serde_json::from_str(&text).map_err(|e| format!("{}: {}", file.display(), e))?;
+    if data.version > export::FORMAT_VERSION {
+        return Err(format!("{}: 対応していない形式のバージョンです ({})", file.display(), data.version));
+    }
+    let summary = export::import_guild(store, guild, &data, replace).await.map_err(|e| e.to_string())?;
+    for warning in &summary.warnings {
+        eprintln!("警告: {}", warning);
+    }
+    println!("追加 {} 件、上書き {} 件、スキップ {} 件", summary.added, summary.updated, summary.skipped);
+    if summary.updated > 0 || summary.added > 0 {
+        // Postgres なら起動中の Bot に知らせてスラッシュコマンドを同期させる
+        let events = EventBus::connect(&config.database.url).await?;
+        if events.is_distributed() {
+            events.publish(Event::CommandsChanged { guild_id: guild }).await;
+            events.close().await;
+        } else {
+            println!("スラッシュコマンドの変更を反映するには register-commands --guild {} を実行してください。", guild);
+        }
+    }
+    Ok(())
+}
+
+async fn list(store: &dyn CommandStore, guild: i64) -> Result<(), String> {
+    let commands = store.list_commands(guild, None, ListSort::Name).await.map_err(|e| e.report())?;
+    for c in &commands {
+        let slash = if c.slash { " [/]" } else { "" };
+        // 複数行の返答は 1 行目だけ表示する
+        let first_line = c.response.lines().next().unwrap_or("");
+        println!("{}{}\t{}", c.name, slash, first_line);
+    }
+    eprintln!("{} 件", commands.len());
+    Ok(())
+}
+
+async fn register_commands(store: &dyn CommandStore, config: &Config, guild: Option<i64>, force: bool) -> Result<(), String> {
+    let http = discord_http(&config.discord.token).await?;
+    let guilds = match guild {
+        Some(guild) => vec![guild],
+        None => store.list_guild_ids().await.map_err(|e| e.report())?,
+    };
+    let mut failed = 0;
+    for guild_id in guilds {
+        match registration::sync_guild_commands(&http, store, GuildId(guild_id as u64), force).await {
+            Ok(Registration::Registered) => println!("{}: 登録しました", guild_id),
+            Ok(Registration::Unchanged) => println!("{}: 変更なし", guild_id),
+            Err(e) => {
+                println!("{}: {}", guild_id, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} 件のギルドで登録に失敗しました。", failed));
+    }
+    Ok(())
+}
@@ -1,47 +1,153 @@
-use sqlx::{PgPool, FromRow};
+use std::fmt;
+
+use sqlx::FromRow;
+
+// コマンド操作の失敗理由
+#[derive(Debug)]
+pub enum CommandError {
+    // 同じ名前のコマンドが既に存在する
+    AlreadyExists,
+    // 指定された名前のコマンドが存在しない
+    NotFound,
+    // DB エラー
+    Database(sqlx::Error),
+}
+
+// 利用者向けのメッセージ (スラッシュコマンドの返答や Web のエラー表示に使う)
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::AlreadyExists => write!(f, "同じ名前のコマンドが既に存在します。"),
+            CommandError::NotFound => write!(f, "そのコマンドは存在しません。"),
+            CommandError::Database(_) => write!(f, "データベースエラーが発生しました。時間をおいて再度お試しください。"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommandError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// 変換では記録しない (ストアは Err を返すだけなので、受け取った呼び出し元で log / report する)
+impl From<sqlx::Error> for CommandError {
+    fn from(e: sqlx::Error) -> Self {
+        CommandError::Database(e)
+    }
+}
+
+impl CommandError {
+    // DB エラーを記録する (ほかは利用者の操作によるものなので記録しない)
+    pub fn log(&self) {
+        if let CommandError::Database(e) = self {
+            tracing::error!(error = ?e, "database error");
+        }
+    }
+
+    // 記録したうえで利用者向けのメッセージにする
+    pub fn report(&self) -> String {
+        self.log();
+        self.to_string()
+    }
+}
 
 #[derive(FromRow, Debug, Clone)]
 pub struct Command {
     pub guild_id: i64,
     pub name: String,
     pub response: String,
+    pub reply_mode: String,
+    pub target_channel_id: Option<i64>,
+    pub delete_trigger: bool,
+    pub delete_after: Option<i32>,
+    pub slash: bool,
+    pub description: Option<String>,
+    pub arguments: Vec<String>,
+}
+
+impl Command {
+    pub fn reply_mode(&self) -> ReplyMode {
+        ReplyMode::parse(&self.reply_mode).unwrap_or(ReplyMode::Reply)
+    }
+
+    // 返答中の {引数名} を与えられた値で置き換える
+    pub fn render_response(&self, args: &[(String, String)]) -> String {
+        let mut out = self.response.clone();
+        for (name, value) in args {
+            out = out.replace(&format!("{{{}}}", name), value);
+        }
+        out
+    }
+
+    // 現在のスラッシュコマンドとしての公開設定
+    pub fn slash_options(&self) -> SlashOptions {
+        SlashOptions { enabled: self.slash, description: self.description.clone(), arguments: self.arguments.clone() }
+    }
+}
+
+// 返答の送り方 (DB の reply_mode カラムに文字列で保存)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyMode {
+    // 返信 (メンションなし)
+    Reply,
+    // 返信 (メンションあり)
+    ReplyPing,
+    // 返信にせず同じチャンネルに送信
+    Send,
+    // 実行者に DM
+    Dm,
+    // 指定チャンネルに送信
+    Channel,
+}
+
+impl ReplyMode {
+    pub const ALL: [ReplyMode; 5] = [ReplyMode::Reply, ReplyMode::ReplyPing, ReplyMode::Send, ReplyMode::Dm, ReplyMode::Channel];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplyMode::Reply => "reply",
+            ReplyMode::ReplyPing => "reply_ping",
+            ReplyMode::Send => "send",
+            ReplyMode::Dm => "dm",
+            ReplyMode::Channel => "channel",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReplyMode::Reply => "返信 (メンションなし)",
+            ReplyMode::ReplyPing => "返信 (メンションあり)",
+            ReplyMode::Send => "チャンネルに送信",
+            ReplyMode::Dm => "実行者に DM",
+            ReplyMode::Channel => "指定チャンネルに送信",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<ReplyMode> {
+        ReplyMode::ALL.into_iter().find(|m| m.as_str() == s)
+    }
+}
+
+// 返答を自動削除するまでの秒数の上限 (1 日)
+pub const MAX_DELETE_AFTER_SECS: i32 = 86400;
+
+// 返答の送り方に関する設定
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub reply_mode: ReplyMode,
+    pub target_channel_id: Option<i64>,
+    pub delete_trigger: bool,
+    pub delete_after: Option<i32>,
 }
 
-pub async fn get_command(pool: &PgPool, guild_id: i64, name: &str) -> Option<Command> {
-    sqlx::query_as::<_, Command>("SELECT guild_id, name, response FROM commands WHERE guild_id = $1 AND name = $2")
-        .bind(guild_id)
-        .bind(name)
-        .fetch_optional(pool)
-        .await
-        .ok()
-        .flatten()
-}
-
-pub async fn add_command(pool: &PgPool, guild_id: i64, name: &str, response: &str) -> bool {
-    sqlx::query("INSERT INTO commands (guild_id, name, response) VALUES ($1, $2, $3) ON CONFLICT (guild_id, name) DO NOTHING")
-        .bind(guild_id)
-        .bind(name)
-        .bind(response)
-        .execute(pool)
-        .await
-        .is_ok()
-}
-
-pub async fn update_command(pool: &PgPool, guild_id: i64, name: &str, response: &str) -> bool {
-    sqlx::query("UPDATE commands SET response = $3 WHERE guild_id = $1 AND name = $2")
-        .bind(guild_id)
-        .bind(name)
-        .bind(response)
-        .execute(pool)
-        .await
-        .is_ok()
-}
-
-pub async fn remove_command(pool: &PgPool, guild_id: i64, name: &str) -> bool {
-    sqlx::query("DELETE FROM commands WHERE guild_id = $1 AND name = $2")
-        .bind(guild_id)
-        .bind(name)
-        .execute(pool)
-        .await
-        .is_ok()
+// スラッシュコマンドとしての公開設定
+#[derive(Debug, Clone)]
+pub struct SlashOptions {
+    pub enabled: bool,
+    pub description: Option<String>,
+    pub arguments: Vec<String>,
 }
@@ -0,0 +1,106 @@
+// 設定ファイルと環境変数の読み込み・検証を確認するテスト
+// 環境変数はプロセス全体で共有されるため、apply_env には参照用の関数を渡す
+
+use std::collections::HashMap;
+
+use super::*;
+
+const FILE: &str = r#"
+[discord]
+token = "file-token"
+shards = "4"
+
+[database]
+url = "sqlite://file.db"
+
+[web]
+bind = "127.0.0.1:3000"
+session_secret = "0123456789abcdef0123456789abcdef"
+
+[oauth]
+client_id = "client"
+client_secret = "secret"
+
+[retention]
+guild_days = 7
+"#;
+
+fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    move |key| vars.get(key).cloned()
+}
+
+fn config() -> Config {
+    Config::parse(FILE).unwrap()
+}
+
+#[test]
+fn env_overrides_file() {
+    let mut config = config();
+    config.validate(Uses::ALL).unwrap();
+    config
+        .apply_env(env(&[("DISCORD_TOKEN", "env-token"), ("DATABASE_URL", "postgres://db"), ("GUILD_RETENTION_DAYS", "0"), ("BOT_OWNER_IDS", "1, 2")]))
+        .unwrap();
+    assert_eq!(config.discord.token, "env-token");
+    assert_eq!(config.database.url, "postgres://db");
+    assert_eq!(config.retention.guild_days, 0);
+    assert_eq!(config.admin.owner_ids, [1, 2]);
+    // 環境変数にないものは設定ファイルの値を残す
+    assert_eq!(config.web.bind, "127.0.0.1:3000");
+    assert_eq!(config.discord.shards, "4");
+
+    // 空の値は未設定として扱う
+    config.apply_env(env(&[("METRICS_BIND", "")])).unwrap();
+    assert_eq!(config.web.metrics_bind, None);
+
+    let errors = config.apply_env(env(&[("SHUTDOWN_GRACE_SECS", "soon"), ("BOT_OWNER_IDS", "owner")])).unwrap_err();
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn rejects_weak_session_secrets() {
+    let with_secret = |secret: &str| {
+        let mut config = config();
+        config.web.session_secret = secret.to_string();
+        config
+    };
+    let secret_errors = |config: &Config| config.validate(Uses::ALL).err().unwrap_or_default().into_iter().filter(|e| e.contains("session_secret")).count();
+
+    assert_eq!(secret_errors(&with_secret("")), 1);
+    assert_eq!(secret_errors(&with_secret(DEV_SESSION_SECRET)), 1);
+    assert_eq!(secret_errors(&with_secret(&"x".repeat(MIN_SESSION_SECRET_LEN - 1))), 1);
+    assert_eq!(secret_errors(&with_secret(&"x".repeat(MIN_SESSION_SECRET_LEN))), 0);
+
+    // dev モードでは未設定なら開発用の値を使う
+    let mut dev = with_secret("");
+    dev.dev = true;
+    dev.validate(Uses::ALL).unwrap();
+    assert_eq!(dev.session_secret(), DEV_SESSION_SECRET);
+    // Web を使わない起動モードでは問わない
+    with_secret("").validate(Uses { discord: true, web: false }).unwrap();
+}
+
+#[test]
+fn validates_shard_ranges() {
+    let sharding = |shards: &str, range: Option<&str>| {
+        let mut config = config();
+        config.discord.shards = shards.to_string();
+        config.discord.shard_range = range.map(str::to_string);
+        config.sharding()
+    };
+    assert_eq!(sharding("auto", None), Ok(Sharding::Auto));
+    assert_eq!(sharding("4", None), Ok(Sharding::Range { first: 0, last: 3, total: 4 }));
+    assert_eq!(sharding("4", Some("2-3")), Ok(Sharding::Range { first: 2, last: 3, total: 4 }));
+
+    assert!(sharding("0", None).is_err());
+    assert!(sharding("many", None).is_err());
+    // 範囲を指定する場合はシャード数も固定する
+    assert!(sharding("auto", Some("0-1")).is_err());
+    assert!(sharding("4", Some("3-1")).is_err());
+    assert!(sharding("4", Some("2-4")).is_err());
+    assert!(sharding("4", Some("2")).is_err());
+
+    let mut config = config();
+    config.discord.shard_range = Some("0-9".to_string());
+    assert!(config.validate(Uses::ALL).unwrap_err().iter().any(|e| e.contains("shard_range")));
+}
@@ -0,0 +1,850 @@
+// Bot のイベント処理 (テキストコマンド・スラッシュコマンド・ボタン・モーダル)
+// serenity の Context に依存しないよう、入力は独自の型で受け取り、
+// 返答などの外向きの操作はすべて Outbound 経由で行う
+#[cfg(test)]
+mod tests;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::async_trait;
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::model::channel::AttachmentType;
+
+use crate::commands::{self, Command};
+use crate::list;
+use crate::metrics;
+use crate::registration::{self, Registration};
+use crate::reminders::{self, NewReminder};
+use crate::retention;
+use crate::scheduler::{self, NewSchedule};
+use crate::scopes::{self, ScopeContext, ScopeKind};
+use crate::store::CommandStore;
+use crate::telemetry;
+
+// 受信したテキストメッセージ
+pub struct IncomingMessage {
+    pub guild_id: Option<i64>,
+    pub content: String,
+    pub scope: ScopeContext,
+}
+
+// スラッシュコマンド/コンテキストメニューの実行
+pub struct CommandInvocation {
+    pub guild_id: Option<i64>,
+    pub name: String,
+    pub options: Vec<CommandOption>,
+    // メッセージのコンテキストメニューから実行された場合の対象メッセージ
+    pub target_message_id: Option<u64>,
+    pub scope: ScopeContext,
+    // 実行したチャンネル (スレッドならスレッド自体。scope では親チャンネルになる)
+    pub channel_id: i64,
+    // 実行したユーザ
+    pub user_id: i64,
+}
+
+pub struct CommandOption {
+    pub name: String,
+    pub value: OptionValue,
+}
+
+pub enum OptionValue {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Channel { id: i64, is_category: bool },
+    Role(i64),
+    SubCommand(Vec<CommandOption>),
+    Other,
+}
+
+// モーダルの送信内容 (入力欄の custom_id と値の組)
+pub struct ModalSubmission {
+    pub guild_id: Option<i64>,
+    pub custom_id: String,
+    pub values: Vec<(String, String)>,
+    pub user_id: i64,
+}
+
+// 再取得したメッセージ (「Register as Response」用)
+pub struct FetchedMessage {
+    pub content: String,
+    pub attachment_urls: Vec<String>,
+}
+
+// インタラクションへの返答
+pub enum Response {
+    Message { content: String, ephemeral: bool },
+    // 実行者にだけ見えるファイル添付
+    File { content: String, attachment: AttachmentType<'static> },
+    // /list の最初のページ (ページが 1 つならボタンなし) や /reminders の一覧
+    List { embed: CreateEmbed, components: Option<CreateComponents> },
+    // /list・/reminders のボタン操作によるメッセージ更新 (None は /list のセッション期限切れ)
+    UpdateList(Option<(CreateEmbed, CreateComponents)>),
+    Modal(Modal),
+}
+
+pub struct Modal {
+    pub custom_id: String,
+    pub title: String,
+    pub inputs: Vec<ModalInput>,
+}
+
+pub struct ModalInput {
+    pub custom_id: &'static str,
+    pub label: &'static str,
+    pub placeholder: &'static str,
+    pub value: Option<String>,
+    // 複数行入力にする
+    pub paragraph: bool,
+    pub max_length: u64,
+}
+
+// Discord への外向きの操作
+#[async_trait]
+pub trait Outbound: Send + Sync {
+    // インタラクションに返答する
+    async fn respond(&self, response: Response);
+    // カスタムコマンドの返答をコマンドの送り方の設定に従って送る
+    async fn deliver(&self, command: &Command, content: String);
+    // ギルドのスラッシュコマンドを登録し直す
+    async fn register_commands(&self, guild_id: i64) -> Result<Registration, String>;
+    // 実行元チャンネルのメッセージを取得する
+    async fn fetch_message(&self, message_id: u64) -> Option<FetchedMessage>;
+    // チャンネル/カテゴリ名 (キャッシュにない場合は None)
+    fn channel_name(&self, channel_id: i64) -> Option<String>;
+}
+
+fn message(content: impl Into<String>) -> Response {
+    Response::Message { content: content.into(), ephemeral: false }
+}
+
+fn ephemeral(content: impl Into<String>) -> Response {
+    Response::Message { content: content.into(), ephemeral: true }
+}
+
+fn option<'a>(options: &'a [CommandOption], name: &str) -> Option<&'a OptionValue> {
+    options.iter().find(|o| o.name == name).map(|o| &o.value)
+}
+
+// オプション名から文字列値を取り出す
+fn option_str<'a>(options: &'a [CommandOption], name: &str) -> Option<&'a str> {
+    match option(options, name) {
+        Some(OptionValue::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+// オプション名から真偽値を取り出す
+fn option_bool(options: &[CommandOption], name: &str) -> Option<bool> {
+    match option(options, name) {
+        Some(OptionValue::Boolean(b)) => Some(*b),
+        _ => None,
+    }
+}
+
+fn option_int(options: &[CommandOption], name: &str) -> Option<i64> {
+    match option(options, name) {
+        Some(OptionValue::Integer(i)) => Some(*i),
+        _ => None,
+    }
+}
+
+fn modal_value<'a>(modal: &'a ModalSubmission, custom_id: &str) -> Option<&'a str> {
+    modal.values.iter().find(|(id, _)| id == custom_id).map(|(_, v)| v.as_str())
+}
+
+// /add, /update で返答を複数行入力するためのモーダル
+// モーダルの入力欄と custom_id の長さの上限 (初期値が長すぎると Discord がモーダルを受け付けない)
+const MODAL_NAME_MAX_LEN: usize = 50;
+const MODAL_RESPONSE_MAX_LEN: usize = 2000;
+const CUSTOM_ID_MAX_LEN: usize = 100;
+
+fn response_input(response: &str) -> ModalInput {
+    ModalInput {
+        custom_id: "response",
+        label: "返答内容",
+        placeholder: "例: Hello, world!",
+        value: Some(response.to_string()),
+        paragraph: true,
+        max_length: MODAL_RESPONSE_MAX_LEN as u64,
+    }
+}
+
+// /add のモーダル。入力されたコマンド名は編集できる
+fn add_modal(name: &str) -> Modal {
+    Modal {
+        custom_id: "cmd_modal:add".to_string(),
+        title: "コマンドを追加".to_string(),
+        inputs: vec![
+            ModalInput {
+                custom_id: "command_name",
+                label: "コマンド名",
+                placeholder: "例: hello",
+                value: Some(name.chars().take(MODAL_NAME_MAX_LEN).collect()),
+                paragraph: false,
+                max_length: MODAL_NAME_MAX_LEN as u64,
+            },
+            response_input(""),
+        ],
+    }
+}
+
+// /update のモーダル。更新するコマンドは custom_id で決まり、名前は変えられない
+// 名前や現在の返答が長すぎてモーダルに収まらない場合は None
+fn update_modal(name: &str, response: &str) -> Option<Modal> {
+    let custom_id = format!("cmd_modal:update:{}", name);
+    if custom_id.chars().count() > CUSTOM_ID_MAX_LEN || response.chars().count() > MODAL_RESPONSE_MAX_LEN {
+        return None;
+    }
+    Some(Modal { custom_id, title: format!("コマンド '{}' を更新", list::truncate(name, 30)), inputs: vec![response_input(response)] })
+}
+
+pub struct Dispatcher {
+    store: Arc<dyn CommandStore>,
+    lists: list::ListSessions,
+    // /purge を使える Bot のオーナー (admin.owner_ids)
+    owner_ids: Vec<u64>,
+}
+
+impl Dispatcher {
+    // list_ttl: /list のボタン操作を受け付ける期間
+    pub fn new(store: Arc<dyn CommandStore>, list_ttl: Duration, owner_ids: Vec<u64>) -> Dispatcher {
+        Dispatcher { store, lists: list::ListSessions::new(list_ttl), owner_ids }
+    }
+
+    // スコープ対象の表示用ラベル
+    fn scope_label(&self, out: &dyn Outbound, scope: &scopes::Scope) -> String {
+        match scope.kind() {
+            Some(ScopeKind::Channel) => format!("<#{}>", scope.target_id),
+            Some(ScopeKind::Category) => format!(
+                "カテゴリ {}",
+                out.channel_name(scope.target_id).unwrap_or_else(|| scope.target_id.to_string())
+            ),
+            Some(ScopeKind::Role) => format!("<@&{}>", scope.target_id),
+            None => scope.target_id.to_string(),
+        }
+    }
+
+    // 実行場所/実行者がスコープ内か (スコープを読めない場合は制限を確かめられないので使わせない)
+    async fn in_scope(&self, guild_id: i64, name: &str, ctx: &ScopeContext) -> bool {
+        match self.store.get_scopes(guild_id, name).await {
+            Ok(scopes) => scopes.is_empty() || scopes::is_allowed(&scopes, ctx),
+            Err(e) => {
+                e.log();
+                false
+            }
+        }
+    }
+
+    pub async fn message(&self, out: &dyn Outbound, msg: IncomingMessage) {
+        let content = msg.content.trim();
+        // 通常コマンドのみテキストで応答
+        if !content.starts_with('!') || content.len() <= 1 {
+            return;
+        }
+        let name = &content[1..];
+        let Some(guild_id) = msg.guild_id else { return };
+        let command = match self.store.get_command(guild_id, name).await {
+            Ok(Some(command)) => command,
+            Ok(None) => {
+                metrics::COMMAND_DISPATCHES.with_label_values(&["text", "miss"]).inc();
+                return;
+            }
+            Err(e) => return e.log(),
+        };
+        tracing::Span::current().record("command", name);
+        if !self.in_scope(guild_id, name, &msg.scope).await {
+            tracing::debug!("out of scope; ignoring");
+            metrics::COMMAND_DISPATCHES.with_label_values(&["text", "out_of_scope"]).inc();
+            return;
+        }
+        metrics::COMMAND_DISPATCHES.with_label_values(&["text", "hit"]).inc();
+        let response = command.response.clone();
+        out.deliver(&command, response).await;
+    }
+
+    pub async fn command(&self, out: &dyn Outbound, cmd: CommandInvocation) {
+        let Some(guild_id) = cmd.guild_id else { return };
+        let options = &cmd.options;
+        match cmd.name.as_str() {
+            "add" => {
+                let cname = option_str(options, "name").unwrap_or("");
+                match option_str(options, "response") {
+                    Some(resp) => {
+                        let reply = match self.store.add_command(guild_id, cname, resp).await {
+                            Ok(()) => {
+                                // 作成者の記録に失敗してもコマンドは保存できているので、記録だけして続ける
+                                if let Err(e) = self.store.record_author(guild_id, cname, cmd.user_id).await {
+                                    e.log();
+                                }
+                                format!("コマンド '{}' を追加しました。", cname)
+                            }
+                            Err(e) => e.report(),
+                        };
+                        out.respond(message(reply)).await;
+                    }
+                    // 返答が省略された場合は複数行入力できるモーダルを開く
+                    None => out.respond(Response::Modal(add_modal(cname))).await,
+                }
+            }
+            "remove" => {
+                let Some(cname) = option_str(options, "name") else { return };
+                let was_slash = match self.store.get_command(guild_id, cname).await {
+                    Ok(command) => command.is_some_and(|c| c.slash),
+                    Err(e) => return out.respond(message(e.report())).await,
+                };
+                let result = self.store.remove_command(guild_id, cname).await;
+                if result.is_ok() && was_slash {
+                    let _ = out.register_commands(guild_id).await;
+                }
+                let reply = match result {
+                    Ok(()) => format!("コマンド '{}' を削除しました。", cname),
+                    Err(e) => e.report(),
+                };
+                out.respond(message(reply)).await;
+            }
+            "update" => {
+                let cname = option_str(options, "name").unwrap_or("");
+                match option_str(options, "response") {
+                    Some(resp) => {
+                        let reply = match self.store.update_command(guild_id, cname, resp).await {
+                            Ok(()) => {
+                                if let Err(e) = self.store.record_author(guild_id, cname, cmd.user_id).await {
+                                    e.log();
+                                }
+                                format!("コマンド '{}' を更新しました。", cname)
+                            }
+                            Err(e) => e.report(),
+                        };
+                        out.respond(message(reply)).await;
+                    }
+                    // 返答が省略された場合は現在の返答を入れたモーダルで編集してもらう
+                    None => match self.store.get_command(guild_id, cname).await {
+                        Ok(Some(current)) => match update_modal(cname, &current.response) {
+                            Some(modal) => out.respond(Response::Modal(modal)).await,
+                            None => out.respond(message("モーダルで編集できない長さです。response オプションで新しい返答を指定してください。")).await,
+                        },
+                        Ok(None) => out.respond(message(commands::CommandError::NotFound.to_string())).await,
+                        Err(e) => out.respond(message(e.report())).await,
+                    },
+                }
+            }
+            "list" => self.list(out, guild_id, options).await,
+            "scope" => {
+                if let Some(sub) = options.first() {
+                    let sub_options = match &sub.value {
+                        OptionValue::SubCommand(o) => o.as_slice(),
+                        _ => &[],
+                    };
+                    let reply = self.scope(out, guild_id, &sub.name, sub_options).await;
+                    out.respond(message(reply)).await;
+                }
+            }
+            "style" => {
+                let cname = option_str(options, "name").unwrap_or("");
+                let mode = option_str(options, "mode").and_then(commands::ReplyMode::parse);
+                let target_channel_id = match option(options, "channel") {
+                    Some(OptionValue::Channel { id, .. }) => Some(*id),
+                    _ => None,
+                };
+                let delete_trigger = option_bool(options, "delete_trigger").unwrap_or(false);
+                let delete_after = option_int(options, "delete_after")
+                    .filter(|secs| *secs > 0)
+                    .map(|secs| secs.min(commands::MAX_DELETE_AFTER_SECS as i64) as i32);
+
+                let reply = match mode {
+                    None => "返答の送り方が不正です。".to_string(),
+                    Some(commands::ReplyMode::Channel) if target_channel_id.is_none() => "送信先チャンネルを指定してください。".to_string(),
+                    Some(reply_mode) => {
+                        let delivery = commands::Delivery { reply_mode, target_channel_id, delete_trigger, delete_after };
+                        match self.store.set_delivery(guild_id, cname, &delivery).await {
+                            Ok(()) => format!("コマンド '{}' の返答方法を「{}」に設定しました。", cname, reply_mode.label()),
+                            Err(e) => e.report(),
+                        }
+                    }
+                };
+                out.respond(message(reply)).await;
+            }
+            "slash" => {
+                let cname = option_str(options, "name").unwrap_or("");
+                let reply = match self.store.get_command(guild_id, cname).await {
+                    Ok(None) => commands::CommandError::NotFound.to_string(),
+                    Err(e) => e.report(),
+                    Ok(Some(existing)) => {
+                        let previous = existing.slash_options();
+                        // 省略されたオプションは現在の設定を引き継ぐ
+                        let slash = commands::SlashOptions {
+                            enabled: option_bool(options, "enabled").unwrap_or(previous.enabled),
+                            description: option_str(options, "description").map(|d| d.to_string()).or_else(|| previous.description.clone()),
+                            arguments: option_str(options, "arguments").map(registration::parse_arguments).unwrap_or_else(|| previous.arguments.clone()),
+                        };
+                        match registration::validate_slash_options(self.store.as_ref(), guild_id, cname, &slash).await {
+                            Err(msg) => msg,
+                            Ok(()) => match self.store.set_slash(guild_id, cname, &slash).await {
+                                Err(e) => e.report(),
+                                Ok(()) => match out.register_commands(guild_id).await {
+                                    // Discord に登録されている内容と食い違わないよう元の設定に戻す
+                                    Err(msg) => match self.store.set_slash(guild_id, cname, &previous).await {
+                                        Ok(()) => format!("{}\n公開設定は変更していません。", msg),
+                                        Err(e) => format!("{}\n公開設定を元に戻せませんでした。{}", msg, e.report()),
+                                    },
+                                    Ok(_) if slash.enabled => format!("コマンド '{}' を /{} として公開しました。", cname, cname),
+                                    Ok(_) => format!("コマンド '{}' のスラッシュコマンドを非公開にしました。", cname),
+                                },
+                            },
+                        }
+                    }
+                };
+                out.respond(message(reply)).await;
+            }
+            "schedule" => {
+                if let Some(sub) = options.first() {
+                    let sub_options = match &sub.value {
+                        OptionValue::SubCommand(o) => o.as_slice(),
+                        _ => &[],
+                    };
+                    let reply = self.schedule(guild_id, cmd.user_id, &sub.name, sub_options).await;
+                    out.respond(ephemeral(reply)).await;
+                }
+            }
+            "remind" => {
+                let new = NewReminder {
+                    user_id: cmd.user_id,
+                    guild_id: Some(guild_id),
+                    channel_id: (!option_bool(options, "dm").unwrap_or(false)).then_some(cmd.channel_id),
+                    when: option_str(options, "when").unwrap_or(""),
+                    content: option_str(options, "text").unwrap_or(""),
+                };
+                let reply = match reminders::create(self.store.as_ref(), new, retention::now()).await {
+                    Ok(reminder) => match reminders::user_timezone(self.store.as_ref(), cmd.user_id).await {
+                        Ok((timezone, offset)) => format!(
+                            "⏰ {} ({}) に{}でお知らせします (#{})。",
+                            scheduler::cron::format_local(reminder.remind_at, offset),
+                            timezone,
+                            if reminder.channel_id.is_some() { "このチャンネル" } else { " DM " },
+                            reminder.id
+                        ),
+                        // 保存はできているので、日時を表示できなくても追加したことは伝える
+                        Err(e) => {
+                            e.log();
+                            format!("⏰ リマインダーを追加しました (#{})。", reminder.id)
+                        }
+                    },
+                    Err(msg) => msg,
+                };
+                out.respond(ephemeral(reply)).await;
+            }
+            "reminders" => {
+                // タイムゾーンが指定されたら設定だけする
+                if let Some(timezone) = option_str(options, "timezone") {
+                    let reply = match reminders::set_timezone(self.store.as_ref(), cmd.user_id, timezone).await {
+                        Ok(()) => format!("タイムゾーンを {} に設定しました。", timezone.trim()),
+                        Err(msg) => msg,
+                    };
+                    out.respond(ephemeral(reply)).await;
+                    return;
+                }
+                let (list, (timezone, offset)) = match (
+                    self.store.list_reminders(cmd.user_id).await,
+                    reminders::user_timezone(self.store.as_ref(), cmd.user_id).await,
+                ) {
+                    (Ok(list), Ok(timezone)) => (list, timezone),
+                    (Err(e), _) | (_, Err(e)) => return out.respond(ephemeral(e.report())).await,
+                };
+                if list.is_empty() {
+                    out.respond(ephemeral("リマインダーはありません。")).await;
+                    return;
+                }
+                let (embed, components) = reminders::list_view(&list, offset, &timezone);
+                out.respond(Response::List { embed, components: Some(components) }).await;
+            }
+            "purge" => {
+                let reply = self.purge(out, guild_id, cmd.user_id, option_str(options, "guild")).await;
+                out.respond(ephemeral(reply)).await;
+            }
+            "mydata" => self.my_data(out, cmd.user_id, option_str(options, "action")).await,
+            "Register as Response" => {
+                // メッセージコンテキストメニューから、対象メッセージの ID を custom_id に入れてコマンド名を入力してもらう
+                let response = match cmd.target_message_id {
+                    Some(message_id) => Response::Modal(Modal {
+                        custom_id: format!("reg_resp:{}", message_id),
+                        title: "コマンド名を入力".to_string(),
+                        inputs: vec![ModalInput {
+                            custom_id: "command_name",
+                            label: "コマンド名",
+                            placeholder: "例: hello",
+                            value: None,
+                            paragraph: false,
+                            max_length: 50,
+                        }],
+                    }),
+                    None => message("メッセージが見つかりませんでした。"),
+                };
+                out.respond(response).await;
+            }
+            name => {
+                // スラッシュコマンドとして公開されたカスタムコマンド
+                let command = match self.store.get_command(guild_id, name).await {
+                    Ok(Some(command)) if command.slash => command,
+                    Ok(_) => {
+                        metrics::COMMAND_DISPATCHES.with_label_values(&["slash", "miss"]).inc();
+                        return;
+                    }
+                    Err(e) => return out.respond(ephemeral(e.report())).await,
+                };
+                if !self.in_scope(guild_id, name, &cmd.scope).await {
+                    metrics::COMMAND_DISPATCHES.with_label_values(&["slash", "out_of_scope"]).inc();
+                    out.respond(ephemeral("ここではこのコマンドは使えません。")).await;
+                    return;
+                }
+                metrics::COMMAND_DISPATCHES.with_label_values(&["slash", "hit"]).inc();
+                let args: Vec<(String, String)> = options
+                    .iter()
+                    .filter_map(|o| match &o.value {
+                        OptionValue::String(s) => Some((o.name.clone(), s.clone())),
+                        _ => None,
+                    })
+                    .collect();
+                let content = command.render_response(&args);
+                out.deliver(&command, content).await;
+            }
+        }
+    }
+
+    // Bot のオーナーが指定したギルドのデータをすぐに削除する (退出後の保持期間を待たない)
+    async fn purge(&self, out: &dyn Outbound, guild_id: i64, user_id: i64, target: Option<&str>) -> String {
+        if !self.owner_ids.contains(&(user_id as u64)) {
+            return "このコマンドは Bot のオーナーだけが使えます。".to_string();
+        }
+        let Some(target) = target.and_then(|t| t.trim().parse::<i64>().ok()) else {
+            return "ギルド ID を指定してください。".to_string();
+        };
+        match self.store.purge_guild(target).await {
+            Ok(removed) => {
+                tracing::info!(user_id, guild_id = target, removed, "purged guild data by owner command");
+                // 実行したギルド自身のデータを消した場合はスラッシュコマンドも取り下げる
+                if target == guild_id {
+                    let _ = out.register_commands(guild_id).await;
+                }
+                format!("ギルド {} のデータを削除しました (コマンド {} 件)。", target, removed)
+            }
+            Err(e) => e.report(),
+        }
+    }
+
+    // /schedule のサブコマンドを処理して返答内容を返す
+    async fn schedule(&self, guild_id: i64, user_id: i64, sub: &str, options: &[CommandOption]) -> String {
+        match sub {
+            "add" => {
+                let Some(OptionValue::Channel { id: channel_id, .. }) = option(options, "channel") else {
+                    return "送信先チャンネルを指定してください。".to_string();
+                };
+                let new = NewSchedule {
+                    guild_id,
+                    channel_id: *channel_id,
+                    when: option_str(options, "when").unwrap_or(""),
+                    timezone: option_str(options, "timezone"),
+                    content: option_str(options, "content"),
+                    command_name: option_str(options, "command"),
+                    created_by: Some(user_id),
+                };
+                match scheduler::create(self.store.as_ref(), new, retention::now()).await {
+                    Ok(schedule) => format!("予約 #{} を追加しました。次回: {}", schedule.id, schedule.next_run_label()),
+                    Err(msg) => msg,
+                }
+            }
+            "remove" => {
+                let Some(id) = option_int(options, "id") else { return "予約の ID を指定してください。".to_string() };
+                match self.store.remove_schedule(guild_id, id).await {
+                    Ok(true) => format!("予約 #{} を削除しました。", id),
+                    Ok(false) => format!("予約 #{} は存在しません。", id),
+                    Err(e) => e.report(),
+                }
+            }
+            "list" => {
+                let schedules = match self.store.list_schedules(guild_id).await {
+                    Ok(schedules) => schedules,
+                    Err(e) => return e.report(),
+                };
+                if schedules.is_empty() {
+                    return "予約はありません。".to_string();
+                }
+                let mut lines = vec!["予約一覧:".to_string()];
+                for s in &schedules {
+                    let mut line = format!(
+                        "- #{} <#{}> 次回 {} / {} / {}",
+                        s.id,
+                        s.channel_id,
+                        s.next_run_label(),
+                        s.repeat_label(),
+                        list::truncate(&s.content_label(), 50)
+                    );
+                    if let Some(error) = &s.last_error {
+                        line.push_str(&format!(" (前回失敗: {})", error));
+                    }
+                    lines.push(line);
+                }
+                lines.join("\n")
+            }
+            _ => "不明なサブコマンドです。".to_string(),
+        }
+    }
+
+    // 実行したユーザについて記録しているデータの書き出し・削除
+    async fn my_data(&self, out: &dyn Outbound, user_id: i64, action: Option<&str>) {
+        match action {
+            Some("delete") => {
+                let reply = match self.store.forget_user(user_id).await {
+                    Ok(count) => format!("{} 件のコマンドからあなたの記録を削除しました。リマインダーとタイムゾーンの設定も削除しました。", count),
+                    Err(e) => e.report(),
+                };
+                out.respond(ephemeral(reply)).await;
+            }
+            _ => {
+                // 一部でも読めなければ、欠けたデータを渡さずにエラーを返す
+                let (authored, reminders, timezone) = match (
+                    self.store.list_authored_commands(user_id).await,
+                    self.store.list_reminders(user_id).await,
+                    self.store.get_user_timezone(user_id).await,
+                ) {
+                    (Ok(authored), Ok(reminders), Ok(timezone)) => (authored, reminders, timezone),
+                    (Err(e), ..) | (_, Err(e), _) | (.., Err(e)) => return out.respond(ephemeral(e.report())).await,
+                };
+                let data = serde_json::json!({
+                    "user_id": user_id.to_string(),
+                    "commands": authored,
+                    "reminders": reminders,
+                    "timezone": timezone,
+                });
+                let json = serde_json::to_string_pretty(&data).expect("user data is serializable");
+                let content = format!("あなたが追加・更新したコマンドの記録です ({} 件)。", authored.len());
+                let attachment = AttachmentType::Bytes { data: json.into_bytes().into(), filename: "mydata.json".to_string() };
+                out.respond(Response::File { content, attachment }).await;
+            }
+        }
+    }
+
+    async fn list(&self, out: &dyn Outbound, guild_id: i64, options: &[CommandOption]) {
+        let filter = option_str(options, "filter").filter(|f| !f.is_empty());
+        let sort = option_str(options, "sort").and_then(list::ListSort::parse).unwrap_or(list::ListSort::Name);
+        let commands = match self.store.list_commands(guild_id, filter, sort).await {
+            Ok(commands) => commands,
+            Err(e) => return out.respond(ephemeral(e.report())).await,
+        };
+        let entries: Vec<list::ListEntry> = commands
+            .into_iter()
+            .map(|c| list::ListEntry { name: c.name, response: c.response })
+            .collect();
+
+        if entries.is_empty() {
+            let msg = if filter.is_some() { "条件に一致するコマンドはありません。" } else { "コマンドは登録されていません。" };
+            out.respond(ephemeral(msg)).await;
+            return;
+        }
+        // 件数が多すぎる場合はページ送りせずファイルで返す
+        if entries.len() > list::FILE_THRESHOLD {
+            let content = format!("コマンドが多いため、一覧をファイルで送信します ({} 件)。", entries.len());
+            out.respond(Response::File { content, attachment: list::as_attachment(&entries) }).await;
+            return;
+        }
+
+        let title = match filter {
+            Some(f) => format!("「{}」を含むコマンド", f),
+            None => "コマンド一覧".to_string(),
+        };
+        let session_id = self.lists.insert(list::ListSession::new(title, entries));
+        let Some((embed, components)) = self
+            .lists
+            .with_session(&session_id, |s| (s.embed(), (s.page_count() > 1).then(|| s.components(&session_id))))
+        else {
+            return;
+        };
+        out.respond(Response::List { embed, components }).await;
+    }
+
+    // /scope のサブコマンドを処理して返答内容を返す
+    async fn scope(&self, out: &dyn Outbound, guild_id: i64, sub: &str, options: &[CommandOption]) -> String {
+        let cname = option_str(options, "name").unwrap_or("");
+        // チャンネル/カテゴリ・ロールの指定を (種類, ID) の組にする
+        let mut targets = Vec::new();
+        if let Some(OptionValue::Channel { id, is_category }) = option(options, "channel") {
+            let kind = if *is_category { ScopeKind::Category } else { ScopeKind::Channel };
+            targets.push((kind, *id));
+        }
+        if let Some(OptionValue::Role(id)) = option(options, "role") {
+            targets.push((ScopeKind::Role, *id));
+        }
+
+        if matches!(sub, "set" | "unset") && targets.is_empty() {
+            return "チャンネルかロールを指定してください。".to_string();
+        }
+        match self.store.get_command(guild_id, cname).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return commands::CommandError::NotFound.to_string(),
+            Err(e) => return e.report(),
+        }
+        match sub {
+            "set" => {
+                let allow = option_str(options, "mode") != Some("deny");
+                let mut result = Ok(());
+                for (kind, id) in &targets {
+                    result = result.and(self.store.set_scope(guild_id, cname, *kind, *id, allow).await);
+                }
+                match result {
+                    Ok(()) => format!("コマンド '{}' のスコープを更新しました。", cname),
+                    Err(e) => e.report(),
+                }
+            }
+            "unset" => {
+                let mut removed = false;
+                for (kind, id) in &targets {
+                    match self.store.remove_scope(guild_id, cname, *kind, *id).await {
+                        Ok(()) => removed = true,
+                        // 登録されていない対象は飛ばす
+                        Err(commands::CommandError::NotFound) => {}
+                        Err(e) => return e.report(),
+                    }
+                }
+                if removed {
+                    format!("コマンド '{}' のスコープから削除しました。", cname)
+                } else {
+                    "指定された対象はスコープに登録されていません。".to_string()
+                }
+            }
+            "clear" => match self.store.clear_scopes(guild_id, cname).await {
+                Ok(()) => format!("コマンド '{}' のスコープ設定をすべて削除しました。", cname),
+                Err(e) => e.report(),
+            },
+            "show" => {
+                let scopes = match self.store.get_scopes(guild_id, cname).await {
+                    Ok(scopes) => scopes,
+                    Err(e) => return e.report(),
+                };
+                if scopes.is_empty() {
+                    format!("コマンド '{}' はすべてのチャンネルで使えます。", cname)
+                } else {
+                    let mut lines = vec![format!("コマンド '{}' のスコープ:", cname)];
+                    for s in &scopes {
+                        let mode = if s.allow { "許可" } else { "拒否" };
+                        lines.push(format!("- {}: {}", mode, self.scope_label(out, s)));
+                    }
+                    lines.join("\n")
+                }
+            }
+            _ => "不明なサブコマンドです。".to_string(),
+        }
+    }
+
+    // ボタン操作
+    pub async fn component(&self, out: &dyn Outbound, custom_id: &str, user_id: i64) {
+        // /reminders の取り消しボタン (本人のリマインダーだけ取り消せる)
+        if let Some(id) = custom_id.strip_prefix("remind_cancel:").and_then(|id| id.parse::<i64>().ok()) {
+            if let Err(e) = self.store.cancel_reminder(user_id, id).await {
+                out.respond(ephemeral(e.report())).await;
+                return;
+            }
+            let (list, (timezone, offset)) = match (self.store.list_reminders(user_id).await, reminders::user_timezone(self.store.as_ref(), user_id).await) {
+                (Ok(list), Ok(timezone)) => (list, timezone),
+                (Err(e), _) | (_, Err(e)) => return out.respond(ephemeral(e.report())).await,
+            };
+            out.respond(Response::UpdateList(Some(reminders::list_view(&list, offset, &timezone)))).await;
+            return;
+        }
+        // /list のページ送りボタン
+        let Some((session_id, action)) = list::parse_custom_id(custom_id) else { return };
+        if action == "jump" && self.lists.with_session(session_id, |_| ()).is_some() {
+            let modal = Modal {
+                custom_id: format!("list_jump:{}", session_id),
+                title: "ページ指定".to_string(),
+                inputs: vec![ModalInput {
+                    custom_id: "page",
+                    label: "ページ番号",
+                    placeholder: "例: 3",
+                    value: None,
+                    paragraph: false,
+                    max_length: 5,
+                }],
+            };
+            out.respond(Response::Modal(modal)).await;
+            return;
+        }
+        let update = self.lists.with_session(session_id, |s| {
+            match action {
+                "first" => s.set_page(0),
+                "prev" => s.set_page(s.page().saturating_sub(1)),
+                "next" => s.set_page(s.page() + 1),
+                "last" => s.set_page(usize::MAX),
+                _ => {}
+            }
+            (s.embed(), s.components(session_id))
+        });
+        out.respond(Response::UpdateList(update)).await;
+    }
+
+    pub async fn modal(&self, out: &dyn Outbound, modal: ModalSubmission) {
+        // /list のページ指定
+        if let Some(session_id) = modal.custom_id.strip_prefix("list_jump:") {
+            let page = modal_value(&modal, "page").and_then(|v| v.trim().parse::<usize>().ok());
+            let update = self.lists.with_session(session_id, |s| {
+                if let Some(page) = page {
+                    s.set_page(page.saturating_sub(1));
+                }
+                (s.embed(), s.components(session_id))
+            });
+            out.respond(Response::UpdateList(update)).await;
+            return;
+        }
+        let Some(guild_id) = modal.guild_id else { return };
+        // /add, /update のモーダル
+        if let Some(action) = modal.custom_id.strip_prefix("cmd_modal:") {
+            let resp = modal_value(&modal, "response").unwrap_or("");
+            let (cname, result, done) = match action.strip_prefix("update:") {
+                Some(cname) => (cname, self.store.update_command(guild_id, cname, resp).await, "更新"),
+                None if action == "add" => {
+                    let cname = modal_value(&modal, "command_name").unwrap_or("").trim();
+                    (cname, self.store.add_command(guild_id, cname, resp).await, "追加")
+                }
+                None => return,
+            };
+            let reply = match result {
+                Ok(()) => {
+                    if let Err(e) = self.store.record_author(guild_id, cname, modal.user_id).await {
+                        e.log();
+                    }
+                    format!("コマンド '{}' を{}しました。", cname, done)
+                }
+                Err(e) => e.report(),
+            };
+            out.respond(message(reply)).await;
+            return;
+        }
+        // 「Register as Response」のコマンド名入力
+        if let Some(message_id) = modal.custom_id.strip_prefix("reg_resp:").and_then(|id| id.parse::<u64>().ok()) {
+            let Some(command_name) = modal_value(&modal, "command_name") else { return };
+            let Some(fetched) = out.fetch_message(message_id).await else {
+                out.respond(message("メッセージの取得に失敗しました。")).await;
+                return;
+            };
+            // メッセージ内容を構築（添付ファイルがある場合はURLを追加）
+            let mut response_content = fetched.content;
+            for url in &fetched.attachment_urls {
+                if !response_content.is_empty() {
+                    response_content.push('\n');
+                }
+                response_content.push_str(url);
+            }
+            tracing::debug!(command = command_name, content = %telemetry::content(&response_content), "registering message as response");
+            let reply = match self.store.add_command(guild_id, command_name, &response_content).await {
+                Ok(()) => {
+                    if let Err(e) = self.store.record_author(guild_id, command_name, modal.user_id).await {
+                        e.log();
+                    }
+                    format!("メッセージの内容をコマンド '{}' の返答として登録しました！", command_name)
+                }
+                Err(e) => format!("登録に失敗しました。{}", e.report()),
+            };
+            out.respond(message(reply)).await;
+        }
+    }
+}
@@ -0,0 +1,467 @@
+// Discord に接続せずにイベント処理を確認するテスト
+// 返答などは FakeOutbound に記録され、ストアはメモリ上のものを使う
+
+use std::sync::Mutex;
+
+use super::*;
+use crate::store::MemoryCommandStore;
+
+const GUILD: i64 = 1;
+// コマンドを実行するユーザ
+const USER: i64 = 42;
+// Bot のオーナー
+const OWNER: i64 = 7;
+
+#[derive(Default)]
+struct FakeOutbound {
+    responses: Mutex<Vec<Response>>,
+    // (コマンド名, 送った内容)
+    delivered: Mutex<Vec<(String, String)>>,
+    registered: Mutex<Vec<i64>>,
+    // 設定すると register_commands がこのエラーで失敗する
+    register_error: Option<String>,
+    // fetch_message で返すメッセージ (ID, 本文, 添付ファイルの URL)
+    messages: Vec<(u64, String, Vec<String>)>,
+}
+
+#[async_trait]
+impl Outbound for FakeOutbound {
+    async fn respond(&self, response: Response) {
+        self.responses.lock().unwrap().push(response);
+    }
+
+    async fn deliver(&self, command: &Command, content: String) {
+        self.delivered.lock().unwrap().push((command.name.clone(), content));
+    }
+
+    async fn register_commands(&self, guild_id: i64) -> Result<Registration, String> {
+        if let Some(e) = &self.register_error {
+            return Err(e.clone());
+        }
+        self.registered.lock().unwrap().push(guild_id);
+        Ok(Registration::Registered)
+    }
+
+    async fn fetch_message(&self, message_id: u64) -> Option<FetchedMessage> {
+        self.messages.iter().find(|(id, _, _)| *id == message_id).map(|(_, content, urls)| FetchedMessage {
+            content: content.clone(),
+            attachment_urls: urls.clone(),
+        })
+    }
+
+    fn channel_name(&self, _channel_id: i64) -> Option<String> {
+        None
+    }
+}
+
+impl FakeOutbound {
+    fn take(&self) -> Vec<Response> {
+        std::mem::take(&mut *self.responses.lock().unwrap())
+    }
+
+    // 最後の返答 (テキストのみ)
+    fn last_text(&self) -> String {
+        match self.take().pop() {
+            Some(Response::Message { content, .. }) => content,
+            _ => panic!("expected a text response"),
+        }
+    }
+
+    fn last_modal(&self) -> Modal {
+        match self.take().pop() {
+            Some(Response::Modal(modal)) => modal,
+            _ => panic!("expected a modal"),
+        }
+    }
+
+    fn delivered(&self) -> Vec<(String, String)> {
+        std::mem::take(&mut *self.delivered.lock().unwrap())
+    }
+}
+
+fn setup() -> (Dispatcher, FakeOutbound) {
+    (Dispatcher::new(Arc::new(MemoryCommandStore::new()), list::SESSION_TIMEOUT, vec![OWNER as u64]), FakeOutbound::default())
+}
+
+fn scope_ctx(channel_id: i64) -> ScopeContext {
+    ScopeContext { channel_id, category_id: None, role_ids: Vec::new() }
+}
+
+fn string(v: &str) -> OptionValue {
+    OptionValue::String(v.to_string())
+}
+
+fn invocation(name: &str, options: Vec<(&str, OptionValue)>) -> CommandInvocation {
+    CommandInvocation {
+        guild_id: Some(GUILD),
+        name: name.to_string(),
+        options: options.into_iter().map(|(name, value)| CommandOption { name: name.to_string(), value }).collect(),
+        target_message_id: None,
+        scope: scope_ctx(100),
+        channel_id: 100,
+        user_id: USER,
+    }
+}
+
+fn text(content: &str, channel_id: i64) -> IncomingMessage {
+    IncomingMessage { guild_id: Some(GUILD), content: content.to_string(), scope: scope_ctx(channel_id) }
+}
+
+fn submission(custom_id: &str, values: &[(&str, &str)]) -> ModalSubmission {
+    ModalSubmission {
+        guild_id: Some(GUILD),
+        custom_id: custom_id.to_string(),
+        values: values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        user_id: USER,
+    }
+}
+
+fn embed_field(embed: &CreateEmbed, path: &[&str]) -> String {
+    let mut value = embed.0.get(path[0]).cloned().unwrap_or_default();
+    for key in &path[1..] {
+        value = value[*key].clone();
+    }
+    value.as_str().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn add_and_trigger_text_command() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("add", vec![("name", string("hello")), ("response", string("world"))])).await;
+    assert_eq!(out.last_text(), "コマンド 'hello' を追加しました。");
+
+    bot.command(&out, invocation("add", vec![("name", string("hello")), ("response", string("again"))])).await;
+    assert_eq!(out.last_text(), "同じ名前のコマンドが既に存在します。");
+
+    bot.message(&out, text("!hello", 100)).await;
+    assert_eq!(out.delivered(), [("hello".to_string(), "world".to_string())]);
+
+    // 未登録のコマンドや ! で始まらないメッセージには反応しない
+    bot.message(&out, text("!unknown", 100)).await;
+    bot.message(&out, text("hello", 100)).await;
+    bot.message(&out, text("!", 100)).await;
+    assert!(out.delivered().is_empty());
+    assert!(out.take().is_empty());
+}
+
+#[tokio::test]
+async fn add_and_update_through_modal() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("add", vec![("name", string("multi"))])).await;
+    let modal = out.last_modal();
+    assert_eq!(modal.custom_id, "cmd_modal:add");
+    assert_eq!(modal.inputs[0].value.as_deref(), Some("multi"));
+
+    bot.modal(&out, submission("cmd_modal:add", &[("command_name", " multi "), ("response", "line 1\nline 2")])).await;
+    assert_eq!(out.last_text(), "コマンド 'multi' を追加しました。");
+
+    // /update で返答を省略すると現在の返答が入ったモーダルが開く
+    bot.command(&out, invocation("update", vec![("name", string("multi"))])).await;
+    // 更新するコマンドは custom_id で決まり、名前は入力させない
+    let modal = out.last_modal();
+    assert_eq!(modal.custom_id, "cmd_modal:update:multi");
+    assert_eq!(modal.inputs.len(), 1);
+    assert_eq!(modal.inputs[0].value.as_deref(), Some("line 1\nline 2"));
+
+    bot.modal(&out, submission("cmd_modal:update:multi", &[("command_name", "other"), ("response", "line 3")])).await;
+    assert_eq!(out.last_text(), "コマンド 'multi' を更新しました。");
+    bot.message(&out, text("!multi", 100)).await;
+    assert_eq!(out.delivered(), [("multi".to_string(), "line 3".to_string())]);
+
+    // 入力欄に収まらない返答はモーダルで編集しない
+    bot.command(&out, invocation("update", vec![("name", string("multi")), ("response", string(&"a".repeat(2001)))])).await;
+    bot.command(&out, invocation("update", vec![("name", string("multi"))])).await;
+    let reply = out.last_text();
+    assert!(reply.contains("response オプション"), "{reply}");
+    // /add で入力欄より長い名前は切り詰めて入れる
+    bot.command(&out, invocation("add", vec![("name", string(&"n".repeat(60)))])).await;
+    assert_eq!(out.last_modal().inputs[0].value.as_deref(), Some("n".repeat(50).as_str()));
+
+    bot.command(&out, invocation("update", vec![("name", string("missing"))])).await;
+    assert_eq!(out.last_text(), "そのコマンドは存在しません。");
+    bot.command(&out, invocation("update", vec![("name", string("missing")), ("response", string("x"))])).await;
+    assert_eq!(out.last_text(), "そのコマンドは存在しません。");
+}
+
+#[tokio::test]
+async fn remove_reregisters_slash_commands() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("add", vec![("name", string("plain")), ("response", string("a"))])).await;
+    bot.command(&out, invocation("add", vec![("name", string("greet")), ("response", string("hi {user}"))])).await;
+    bot.command(&out, invocation("slash", vec![("name", string("greet")), ("enabled", OptionValue::Boolean(true)), ("arguments", string("user"))]))
+        .await;
+    out.take();
+    assert_eq!(*out.registered.lock().unwrap(), [GUILD]);
+
+    // 公開中のカスタムコマンドはスラッシュコマンドとして実行できる
+    bot.command(&out, invocation("greet", vec![("user", string("nkmz"))])).await;
+    assert_eq!(out.delivered(), [("greet".to_string(), "hi nkmz".to_string())]);
+
+    bot.command(&out, invocation("remove", vec![("name", string("plain"))])).await;
+    assert_eq!(out.last_text(), "コマンド 'plain' を削除しました。");
+    assert_eq!(out.registered.lock().unwrap().len(), 1);
+
+    bot.command(&out, invocation("remove", vec![("name", string("greet"))])).await;
+    assert_eq!(out.last_text(), "コマンド 'greet' を削除しました。");
+    assert_eq!(out.registered.lock().unwrap().len(), 2);
+
+    bot.command(&out, invocation("remove", vec![("name", string("greet"))])).await;
+    assert_eq!(out.last_text(), "そのコマンドは存在しません。");
+    bot.command(&out, invocation("greet", vec![("user", string("nkmz"))])).await;
+    assert!(out.delivered().is_empty());
+}
+
+#[tokio::test]
+async fn slash_is_reverted_when_registration_fails() {
+    let (bot, _) = setup();
+    let out = FakeOutbound { register_error: Some("登録に失敗しました。".to_string()), ..FakeOutbound::default() };
+    bot.command(&out, invocation("add", vec![("name", string("greet")), ("response", string("hi"))])).await;
+    bot.command(&out, invocation("slash", vec![("name", string("greet")), ("enabled", OptionValue::Boolean(true))])).await;
+    assert_eq!(out.last_text(), "登録に失敗しました。\n公開設定は変更していません。");
+    assert!(!bot.store.get_command(GUILD, "greet").await.unwrap().unwrap().slash);
+    bot.command(&out, invocation("greet", vec![])).await;
+    assert!(out.delivered().is_empty());
+}
+
+#[tokio::test]
+async fn list_pages_and_buttons() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("list", vec![])).await;
+    assert_eq!(out.last_text(), "コマンドは登録されていません。");
+
+    for i in 0..15 {
+        bot.command(&out, invocation("add", vec![("name", string(&format!("cmd{:02}", i))), ("response", string("r"))])).await;
+    }
+    out.take();
+
+    bot.command(&out, invocation("list", vec![])).await;
+    let Some(Response::List { embed, components: Some(components) }) = out.take().pop() else { panic!("expected a paged list") };
+    assert_eq!(embed_field(&embed, &["title"]), "コマンド一覧 (15 件)");
+    assert_eq!(embed_field(&embed, &["footer", "text"]), "ページ 1 / 2");
+
+    // ボタンの custom_id は "list:<session>:<action>"
+    let next = components.0[0]["components"][3]["custom_id"].as_str().unwrap().to_string();
+    let session_id = list::parse_custom_id(&next).unwrap().0.to_string();
+    bot.component(&out, &next, USER).await;
+    let Some(Response::UpdateList(Some((embed, _)))) = out.take().pop() else { panic!("expected a page update") };
+    assert_eq!(embed_field(&embed, &["footer", "text"]), "ページ 2 / 2");
+    assert!(embed_field(&embed, &["description"]).contains("!cmd14"));
+
+    // ページ指定はモーダルで受け付ける
+    bot.component(&out, &format!("list:{}:jump", session_id), USER).await;
+    assert_eq!(out.last_modal().custom_id, format!("list_jump:{}", session_id));
+    bot.modal(&out, submission(&format!("list_jump:{}", session_id), &[("page", "1")])).await;
+    let Some(Response::UpdateList(Some((embed, _)))) = out.take().pop() else { panic!("expected a page update") };
+    assert_eq!(embed_field(&embed, &["footer", "text"]), "ページ 1 / 2");
+
+    bot.component(&out, "list:expired:next", USER).await;
+    assert!(matches!(out.take().pop(), Some(Response::UpdateList(None))));
+
+    // 絞り込み結果が 1 ページに収まる場合はボタンを付けない
+    bot.command(&out, invocation("list", vec![("filter", string("cmd01")), ("sort", string("newest"))])).await;
+    let Some(Response::List { embed, components: None }) = out.take().pop() else { panic!("expected a single page") };
+    assert_eq!(embed_field(&embed, &["title"]), "「cmd01」を含むコマンド (1 件)");
+}
+
+#[tokio::test]
+async fn list_falls_back_to_file() {
+    let (bot, out) = setup();
+    for i in 0..=list::FILE_THRESHOLD {
+        bot.command(&out, invocation("add", vec![("name", string(&format!("cmd{}", i))), ("response", string("r"))])).await;
+    }
+    out.take();
+    bot.command(&out, invocation("list", vec![])).await;
+    let Some(Response::File { content, .. }) = out.take().pop() else { panic!("expected a file") };
+    assert!(content.contains(&format!("{} 件", list::FILE_THRESHOLD + 1)));
+}
+
+#[tokio::test]
+async fn register_message_as_response() {
+    let (bot, mut out) = setup();
+    out.messages.push((42, "copied".to_string(), vec!["https://cdn.example/a.png".to_string()]));
+
+    let mut cmd = invocation("Register as Response", vec![]);
+    cmd.target_message_id = Some(42);
+    bot.command(&out, cmd).await;
+    assert_eq!(out.last_modal().custom_id, "reg_resp:42");
+
+    bot.modal(&out, submission("reg_resp:42", &[("command_name", "saved")])).await;
+    assert_eq!(out.last_text(), "メッセージの内容をコマンド 'saved' の返答として登録しました！");
+    bot.message(&out, text("!saved", 100)).await;
+    assert_eq!(out.delivered(), [("saved".to_string(), "copied\nhttps://cdn.example/a.png".to_string())]);
+
+    bot.modal(&out, submission("reg_resp:42", &[("command_name", "saved")])).await;
+    assert_eq!(out.last_text(), "登録に失敗しました。同じ名前のコマンドが既に存在します。");
+    bot.modal(&out, submission("reg_resp:7", &[("command_name", "other")])).await;
+    assert_eq!(out.last_text(), "メッセージの取得に失敗しました。");
+
+    bot.command(&out, invocation("Register as Response", vec![])).await;
+    assert_eq!(out.last_text(), "メッセージが見つかりませんでした。");
+}
+
+#[tokio::test]
+async fn scopes_restrict_where_commands_run() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("add", vec![("name", string("here")), ("response", string("ok"))])).await;
+    let scope = |sub: &str, options: Vec<(&str, OptionValue)>| {
+        let options = options.into_iter().map(|(name, value)| CommandOption { name: name.to_string(), value }).collect();
+        invocation("scope", vec![(sub, OptionValue::SubCommand(options))])
+    };
+    bot.command(&out, scope("set", vec![("name", string("here")), ("mode", string("allow")), ("channel", OptionValue::Channel { id: 100, is_category: false })])).await;
+    assert_eq!(out.last_text(), "コマンド 'here' のスコープを更新しました。");
+
+    bot.message(&out, text("!here", 200)).await;
+    assert!(out.delivered().is_empty());
+    bot.message(&out, text("!here", 100)).await;
+    assert_eq!(out.delivered().len(), 1);
+
+    bot.command(&out, scope("show", vec![("name", string("here"))])).await;
+    assert_eq!(out.last_text(), "コマンド 'here' のスコープ:\n- 許可: <#100>");
+    bot.command(&out, scope("set", vec![("name", string("missing")), ("role", OptionValue::Role(5))])).await;
+    assert_eq!(out.last_text(), "そのコマンドは存在しません。");
+    bot.command(&out, scope("clear", vec![("name", string("here"))])).await;
+    bot.message(&out, text("!here", 200)).await;
+    assert_eq!(out.delivered().len(), 1);
+}
+
+#[tokio::test]
+async fn store_errors_are_not_treated_as_empty() {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("./migrations_sqlite").run(&pool).await.unwrap();
+    let bot = Dispatcher::new(Arc::new(crate::store::SqliteCommandStore::new(pool.clone())), list::SESSION_TIMEOUT, vec![OWNER as u64]);
+    let out = FakeOutbound::default();
+    bot.command(&out, invocation("add", vec![("name", string("here")), ("response", string("ok"))])).await;
+    out.take();
+
+    // スコープを読めない場合は、制限があるかもしれないので実行しない
+    sqlx::query("DROP TABLE command_scopes").execute(&pool).await.unwrap();
+    bot.message(&out, text("!here", 100)).await;
+    assert!(out.delivered().is_empty());
+    // 削除できなかったことを「登録されていない」とは答えない
+    let options = vec![CommandOption { name: "name".to_string(), value: string("here") }, CommandOption { name: "role".to_string(), value: OptionValue::Role(5) }];
+    bot.command(&out, invocation("scope", vec![("unset", OptionValue::SubCommand(options))])).await;
+    assert_eq!(out.last_text(), "データベースエラーが発生しました。時間をおいて再度お試しください。");
+
+    // DB に接続できない間は「登録されていない」とは答えない
+    pool.close().await;
+    bot.command(&out, invocation("list", vec![])).await;
+    assert_eq!(out.last_text(), "データベースエラーが発生しました。時間をおいて再度お試しください。");
+}
+
+#[tokio::test]
+async fn my_data_export_and_delete() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("add", vec![("name", string("hello")), ("response", string("world"))])).await;
+    out.take();
+
+    bot.command(&out, invocation("mydata", vec![("action", string("export"))])).await;
+    match out.take().pop() {
+        Some(Response::File { content, attachment: AttachmentType::Bytes { data, .. } }) => {
+            assert!(content.contains("(1 件)"));
+            let json: serde_json::Value = serde_json::from_slice(&data).unwrap();
+            assert_eq!(json["commands"][0]["name"], "hello");
+            assert_eq!(json["commands"][0]["created"], true);
+        }
+        _ => panic!("expected a file"),
+    }
+
+    // 記録は消すがコマンドはギルドのものなので残す
+    bot.command(&out, invocation("mydata", vec![("action", string("delete"))])).await;
+    assert_eq!(out.last_text(), "1 件のコマンドからあなたの記録を削除しました。リマインダーとタイムゾーンの設定も削除しました。");
+    assert!(bot.store.list_authored_commands(USER).await.unwrap().is_empty());
+    assert!(bot.store.get_command(GUILD, "hello").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn purge_is_owner_only() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("add", vec![("name", string("hello")), ("response", string("world"))])).await;
+    bot.store.add_command(2, "other", "guild").await.unwrap();
+    out.take();
+
+    bot.command(&out, invocation("purge", vec![("guild", string("2"))])).await;
+    assert_eq!(out.last_text(), "このコマンドは Bot のオーナーだけが使えます。");
+    assert!(bot.store.get_command(2, "other").await.unwrap().is_some());
+
+    let mut owner = invocation("purge", vec![("guild", string("2"))]);
+    owner.user_id = OWNER;
+    bot.command(&out, owner).await;
+    assert_eq!(out.last_text(), "ギルド 2 のデータを削除しました (コマンド 1 件)。");
+    assert!(bot.store.get_command(2, "other").await.unwrap().is_none());
+    // 他のギルドのデータを消した場合は実行したギルドのコマンドを登録し直さない
+    assert!(out.registered.lock().unwrap().is_empty());
+    assert!(bot.store.get_command(GUILD, "hello").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn schedule_add_list_remove() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("add", vec![("name", string("hello")), ("response", string("world"))])).await;
+    out.take();
+    let schedule = |sub: &str, options: Vec<(&str, OptionValue)>| {
+        let options = options.into_iter().map(|(name, value)| CommandOption { name: name.to_string(), value }).collect();
+        invocation("schedule", vec![(sub, OptionValue::SubCommand(options))])
+    };
+    let channel = || OptionValue::Channel { id: 100, is_category: false };
+
+    bot.command(&out, schedule("add", vec![("channel", channel()), ("when", string("0 9 * * 1-5")), ("command", string("hello")), ("timezone", string("Asia/Tokyo"))])).await;
+    assert!(out.last_text().starts_with("予約 #1 を追加しました。次回: "));
+    bot.command(&out, schedule("add", vec![("channel", channel()), ("when", string("2000-01-01 09:00")), ("content", string("late"))])).await;
+    assert_eq!(out.last_text(), "過去の日時は指定できません。");
+    bot.command(&out, schedule("add", vec![("channel", channel()), ("when", string("0 9 * * *")), ("command", string("missing"))])).await;
+    assert_eq!(out.last_text(), "コマンド 'missing' は存在しません。");
+    bot.command(&out, schedule("add", vec![("channel", channel()), ("when", string("0 9 * * *"))])).await;
+    assert_eq!(out.last_text(), "送る内容かカスタムコマンド名のどちらか一方を指定してください。");
+
+    bot.command(&out, schedule("list", vec![])).await;
+    let listed = out.last_text();
+    assert!(listed.contains("- #1 <#100> 次回 ") && listed.contains("0 9 * * 1-5 (Asia/Tokyo) / !hello"), "{listed}");
+    assert_eq!(bot.store.list_schedules(GUILD).await.unwrap()[0].created_by, Some(USER));
+
+    bot.command(&out, schedule("remove", vec![("id", OptionValue::Integer(1))])).await;
+    assert_eq!(out.last_text(), "予約 #1 を削除しました。");
+    bot.command(&out, schedule("remove", vec![("id", OptionValue::Integer(1))])).await;
+    assert_eq!(out.last_text(), "予約 #1 は存在しません。");
+    bot.command(&out, schedule("list", vec![])).await;
+    assert_eq!(out.last_text(), "予約はありません。");
+}
+
+#[tokio::test]
+async fn remind_and_cancel_with_buttons() {
+    let (bot, out) = setup();
+    bot.command(&out, invocation("reminders", vec![("timezone", string("Mars/Olympus"))])).await;
+    assert!(out.last_text().contains("対応していません"));
+    bot.command(&out, invocation("reminders", vec![("timezone", string("Asia/Tokyo"))])).await;
+    assert_eq!(out.last_text(), "タイムゾーンを Asia/Tokyo に設定しました。");
+
+    bot.command(&out, invocation("remind", vec![("when", string("in 2h")), ("text", string("お茶"))])).await;
+    let reply = out.last_text();
+    assert!(reply.contains("(Asia/Tokyo) にこのチャンネルでお知らせします (#1)。"), "{reply}");
+    bot.command(&out, invocation("remind", vec![("when", string("tomorrow 9:00")), ("text", string("会議")), ("dm", OptionValue::Boolean(true))])).await;
+    assert!(out.last_text().contains("09:00 (Asia/Tokyo) に DM でお知らせします (#2)。"));
+    bot.command(&out, invocation("remind", vec![("when", string("someday")), ("text", string("x"))])).await;
+    assert!(out.last_text().contains("解釈できません"));
+    let stored = bot.store.list_reminders(USER).await.unwrap();
+    assert_eq!(stored.iter().map(|r| (r.channel_id, r.guild_id)).collect::<Vec<_>>(), [(Some(100), Some(GUILD)), (None, Some(GUILD))]);
+
+    bot.command(&out, invocation("reminders", vec![])).await;
+    match out.take().pop() {
+        Some(Response::List { embed, components: Some(components) }) => {
+            assert!(embed_field(&embed, &["description"]).contains("**#1**"));
+            assert_eq!(embed_field(&embed, &["footer", "text"]), "タイムゾーン: Asia/Tokyo");
+            assert_eq!(components.0[0]["components"][1]["custom_id"], "remind_cancel:2");
+        }
+        _ => panic!("expected a list"),
+    }
+
+    // 他のユーザのボタン操作では取り消せない
+    bot.component(&out, "remind_cancel:1", USER + 1).await;
+    out.take();
+    assert_eq!(bot.store.list_reminders(USER).await.unwrap().len(), 2);
+    bot.component(&out, "remind_cancel:1", USER).await;
+    match out.take().pop() {
+        Some(Response::UpdateList(Some((embed, _)))) => assert!(!embed_field(&embed, &["description"]).contains("**#1**")),
+        _ => panic!("expected a list update"),
+    }
+    assert_eq!(bot.store.list_reminders(USER).await.unwrap().iter().map(|r| r.id).collect::<Vec<_>>(), [2]);
+}
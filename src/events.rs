@@ -0,0 +1,128 @@
+// プロセス間の通知 (Bot と Web を別プロセスで動かす場合の連携)
+// Postgres では LISTEN/NOTIFY で全プロセスに配る。SQLite/メモリでは同じプロセス内にだけ配る
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgListener, PgPool, PgPoolOptions};
+use tokio::sync::broadcast;
+
+use crate::shutdown::Shutdown;
+
+// NOTIFY のチャンネル名
+const CHANNEL: &str = "nkmzbot_events";
+// LISTEN の接続が切れた場合に再接続するまでの間隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    // Web・CLI がギルドのコマンドを変更した。Bot はスラッシュコマンドを同期する
+    CommandsChanged { guild_id: i64 },
+    // Bot がギルドのチャンネル・ロールの変更を受け取った。Web はキャッシュを捨てる
+    GuildChanged { guild_id: i64 },
+    // LISTEN の接続を張り直した (その間の通知を取りこぼした可能性がある)。全キャッシュを捨てる
+    Resync,
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    local: broadcast::Sender<Event>,
+    // Postgres の場合のみ。NOTIFY の送信と LISTEN に使う
+    pool: Option<PgPool>,
+}
+
+impl EventBus {
+    // 同じプロセス内だけに配る
+    pub fn local() -> EventBus {
+        let (local, _) = broadcast::channel(256);
+        EventBus { local, pool: None }
+    }
+
+    // DATABASE_URL が Postgres なら LISTEN/NOTIFY を使う
+    pub async fn connect(database_url: &str) -> Result<EventBus, String> {
+        if !database_url.starts_with("postgres") {
+            return Ok(EventBus::local());
+        }
+        // LISTEN 用に 1 本、NOTIFY 用に 1 本
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(database_url)
+            .await
+            .map_err(|e| format!("イベント用の DB 接続に失敗しました: {}", e))?;
+        let (local, _) = broadcast::channel(256);
+        Ok(EventBus { local, pool: Some(pool) })
+    }
+
+    // 他のプロセスにも届くか (false の場合、別プロセスの Bot/Web とは連携できない)
+    pub fn is_distributed(&self) -> bool {
+        self.pool.is_some()
+    }
+
+    // 送信に失敗しても処理は続ける (キャッシュの期限切れと起動時の同期で追いつく)
+    pub async fn publish(&self, event: Event) {
+        let Some(pool) = &self.pool else {
+            let _ = self.local.send(event);
+            return;
+        };
+        // 自分のプロセスにも LISTEN 経由で届く
+        let payload = serde_json::to_string(&event).expect("event is serializable");
+        if let Err(e) = sqlx::query("SELECT pg_notify($1, $2)").bind(CHANNEL).bind(&payload).execute(pool).await {
+            tracing::warn!(error = %e, ?event, "failed to publish event");
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.local.subscribe()
+    }
+
+    // Postgres の通知を受け取り、subscribe() した全員に配る。終了処理が始まるまで続ける
+    pub fn listen(&self, shutdown: Shutdown) {
+        let Some(pool) = self.pool.clone() else { return };
+        let local = self.local.clone();
+        tokio::spawn(async move {
+            loop {
+                match listen_once(&pool, &local, &shutdown).await {
+                    Ok(()) => return,
+                    Err(e) => tracing::warn!(error = %e, "event listener disconnected; reconnecting"),
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(RECONNECT_INTERVAL) => {}
+                    _ = shutdown.wait() => return,
+                }
+                let _ = local.send(Event::Resync);
+            }
+        });
+    }
+
+    pub async fn close(&self) {
+        if let Some(pool) = &self.pool {
+            pool.close().await;
+        }
+    }
+}
+
+// 終了処理が始まった場合は Ok、接続が切れた場合は Err を返す
+async fn listen_once(pool: &PgPool, local: &broadcast::Sender<Event>, shutdown: &Shutdown) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+    tracing::info!(channel = CHANNEL, "listening for events");
+    loop {
+        let notification = tokio::select! {
+            n = listener.try_recv() => n?,
+            _ = shutdown.wait() => return Ok(()),
+        };
+        let Some(notification) = notification else {
+            // 接続が切れた。次の try_recv() で再接続されるが、その間の通知は届かない
+            tracing::warn!("event listener connection lost; reconnecting");
+            let _ = local.send(Event::Resync);
+            continue;
+        };
+        match serde_json::from_str::<Event>(notification.payload()) {
+            Ok(event) => {
+                tracing::debug!(?event, "event received");
+                let _ = local.send(event);
+            }
+            Err(e) => tracing::warn!(error = %e, payload = notification.payload(), "ignoring malformed event"),
+        }
+    }
+}
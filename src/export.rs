@@ -0,0 +1,139 @@
+// ギルドのコマンド一式 (返答・送り方・スラッシュコマンド設定・スコープ) の書き出しと読み込み
+// 形式は JSON。別のギルドやサーバへの移行、バックアップに使う
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{CommandError, Delivery, ReplyMode, SlashOptions};
+use crate::list::ListSort;
+use crate::registration;
+use crate::scopes::ScopeKind;
+use crate::store::CommandStore;
+
+// 形式を変えた場合は上げる
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GuildExport {
+    pub version: u32,
+    pub guild_id: i64,
+    pub commands: Vec<ExportedCommand>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedCommand {
+    pub name: String,
+    pub response: String,
+    #[serde(default = "default_reply_mode")]
+    pub reply_mode: String,
+    #[serde(default)]
+    pub target_channel_id: Option<i64>,
+    #[serde(default)]
+    pub delete_trigger: bool,
+    #[serde(default)]
+    pub delete_after: Option<i32>,
+    #[serde(default)]
+    pub slash: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<ExportedScope>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedScope {
+    pub kind: String,
+    pub target_id: i64,
+    pub allow: bool,
+}
+
+fn default_reply_mode() -> String {
+    ReplyMode::Reply.as_str().to_string()
+}
+
+// 読み込み結果
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    // 既に存在し、上書きしなかったコマンド
+    pub skipped: usize,
+    // 一部の設定を反映できなかったコマンドの警告
+    pub warnings: Vec<String>,
+}
+
+pub async fn export_guild(store: &dyn CommandStore, guild_id: i64) -> Result<GuildExport, CommandError> {
+    let mut scopes: HashMap<String, Vec<ExportedScope>> = HashMap::new();
+    for scope in store.list_guild_scopes(guild_id).await? {
+        scopes.entry(scope.name).or_default().push(ExportedScope { kind: scope.kind, target_id: scope.target_id, allow: scope.allow });
+    }
+    let commands = store
+        .list_commands(guild_id, None, ListSort::Name)
+        .await?
+        .into_iter()
+        .map(|c| ExportedCommand {
+            scopes: scopes.remove(&c.name).unwrap_or_default(),
+            name: c.name,
+            response: c.response,
+            reply_mode: c.reply_mode,
+            target_channel_id: c.target_channel_id,
+            delete_trigger: c.delete_trigger,
+            delete_after: c.delete_after,
+            slash: c.slash,
+            description: c.description,
+            arguments: c.arguments,
+        })
+        .collect();
+    Ok(GuildExport { version: FORMAT_VERSION, guild_id, commands })
+}
+
+// guild_id のギルドに読み込む (書き出し元のギルド ID は使わない)
+// replace = false の場合、既に存在するコマンドはそのまま残す
+pub async fn import_guild(store: &dyn CommandStore, guild_id: i64, data: &GuildExport, replace: bool) -> Result<ImportSummary, CommandError> {
+    let mut summary = ImportSummary::default();
+    for command in &data.commands {
+        let name = command.name.as_str();
+        match store.add_command(guild_id, name, &command.response).await {
+            Ok(()) => summary.added += 1,
+            Err(CommandError::AlreadyExists) if replace => {
+                store.update_command(guild_id, name, &command.response).await?;
+                summary.updated += 1;
+            }
+            Err(CommandError::AlreadyExists) => {
+                summary.skipped += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+
+        let reply_mode = ReplyMode::parse(&command.reply_mode).unwrap_or_else(|| {
+            summary.warnings.push(format!("{}: 不明な送り方 {:?} のため既定値にしました", name, command.reply_mode));
+            ReplyMode::Reply
+        });
+        let delivery = Delivery {
+            reply_mode,
+            target_channel_id: command.target_channel_id,
+            delete_trigger: command.delete_trigger,
+            delete_after: command.delete_after,
+        };
+        store.set_delivery(guild_id, name, &delivery).await?;
+
+        let slash = SlashOptions { enabled: command.slash, description: command.description.clone(), arguments: command.arguments.clone() };
+        match registration::validate_slash_options(store, guild_id, name, &slash).await {
+            Ok(()) => store.set_slash(guild_id, name, &slash).await?,
+            Err(e) => summary.warnings.push(format!("{}: スラッシュコマンドとして公開できません: {}", name, e)),
+        }
+
+        store.clear_scopes(guild_id, name).await?;
+        for scope in &command.scopes {
+            let Some(kind) = ScopeKind::parse(&scope.kind) else {
+                summary.warnings.push(format!("{}: 不明なスコープの種類 {:?} を無視しました", name, scope.kind));
+                continue;
+            };
+            store.set_scope(guild_id, name, kind, scope.target_id, scope.allow).await?;
+        }
+    }
+    Ok(summary)
+}
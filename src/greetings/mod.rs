@@ -0,0 +1,270 @@
+// ギルドの入退室メッセージ (参加したメンバーへの挨拶と自動ロールの付与、退出時のメッセージ)
+// guild_member_addition / guild_member_removal で送るため、特権インテントの GUILD_MEMBERS が必要 (features.greetings)
+#[cfg(test)]
+mod tests;
+
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::id::{ChannelId, UserId};
+
+use crate::commands::CommandError;
+use crate::metrics;
+use crate::store::CommandStore;
+
+// メッセージの長さの上限 (Discord のメッセージの上限)
+pub const MAX_MESSAGE_LEN: usize = 2000;
+// メッセージ中で置き換えるプレースホルダ (Web の説明にも使う)
+pub const PLACEHOLDERS: [(&str, &str); 4] = [
+    ("{user}", "メンバーへのメンション"),
+    ("{username}", "メンバーの名前"),
+    ("{server}", "サーバー名"),
+    ("{member_count}", "メンバー数"),
+];
+
+#[derive(sqlx::FromRow, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Greeting {
+    pub guild_id: i64,
+    pub welcome_enabled: bool,
+    // 参加時のメッセージを送るチャンネル。None ならチャンネルには送らない
+    pub welcome_channel_id: Option<i64>,
+    pub welcome_message: String,
+    // 参加したメンバーに DM でも送る
+    pub welcome_dm: bool,
+    // 参加したメンバーに付けるロール (welcome_enabled に関係なく付ける)
+    pub auto_role_id: Option<i64>,
+    pub farewell_enabled: bool,
+    pub farewell_channel_id: Option<i64>,
+    pub farewell_message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Welcome,
+    Farewell,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Welcome => "welcome",
+            Kind::Farewell => "farewell",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Kind> {
+        match s {
+            "welcome" => Some(Kind::Welcome),
+            "farewell" => Some(Kind::Farewell),
+            _ => None,
+        }
+    }
+}
+
+// プレースホルダに入れるメンバーとギルドの情報
+pub struct MemberInfo {
+    pub user_id: i64,
+    pub username: String,
+    pub server: String,
+    // キャッシュにない場合は None
+    pub member_count: Option<u64>,
+}
+
+// メッセージ中のプレースホルダを置き換える
+pub fn render(template: &str, member: &MemberInfo) -> String {
+    substitute(template, member, &format!("<@{}>", member.user_id))
+}
+
+// Web のプレビュー用 (メンションは解決できないので @名前 にする)
+pub fn preview(template: &str, member: &MemberInfo) -> String {
+    substitute(template, member, &format!("@{}", member.username))
+}
+
+// テンプレートを一度だけ走査して置き換える
+// (ユーザー名やサーバー名に含まれる {server} などは展開しない)
+fn substitute(template: &str, member: &MemberInfo, user: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let value = tail.find('}').and_then(|end| {
+            let value = match &tail[1..end] {
+                "user" => user.to_string(),
+                "username" => member.username.clone(),
+                "server" => member.server.clone(),
+                "member_count" => member.member_count.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string()),
+                _ => return None,
+            };
+            Some((value, end + 1))
+        });
+        match value {
+            Some((value, len)) => {
+                out.push_str(&value);
+                rest = &tail[len..];
+            }
+            // 未知のプレースホルダや閉じていない { はそのまま残す
+            None => {
+                out.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+impl Greeting {
+    // 未設定のギルドの既定値
+    pub fn new(guild_id: i64) -> Greeting {
+        Greeting {
+            guild_id,
+            welcome_message: "{user} さん、{server} へようこそ！".to_string(),
+            farewell_message: "{username} さんが退出しました。".to_string(),
+            ..Greeting::default()
+        }
+    }
+
+    pub fn message(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Welcome => &self.welcome_message,
+            Kind::Farewell => &self.farewell_message,
+        }
+    }
+
+    pub fn channel_id(&self, kind: Kind) -> Option<i64> {
+        match kind {
+            Kind::Welcome => self.welcome_channel_id,
+            Kind::Farewell => self.farewell_channel_id,
+        }
+    }
+
+    // 送り先と内容がそろっているか (有効にする場合とテスト送信で確認する)
+    pub fn check_sendable(&self, kind: Kind) -> Result<(), String> {
+        if self.message(kind).trim().is_empty() {
+            return Err("メッセージを入力してください。".to_string());
+        }
+        let dm = kind == Kind::Welcome && self.welcome_dm;
+        if self.channel_id(kind).is_none() && !dm {
+            return Err(match kind {
+                Kind::Welcome => "送信先のチャンネルを選ぶか、DM で送るようにしてください。".to_string(),
+                Kind::Farewell => "送信先のチャンネルを選んでください。".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for kind in [Kind::Welcome, Kind::Farewell] {
+            if self.message(kind).chars().count() > MAX_MESSAGE_LEN {
+                return Err(format!("メッセージは {} 文字以内で入力してください。", MAX_MESSAGE_LEN));
+            }
+        }
+        if self.welcome_enabled {
+            self.check_sendable(Kind::Welcome)?;
+        }
+        if self.farewell_enabled {
+            self.check_sendable(Kind::Farewell)?;
+        }
+        Ok(())
+    }
+}
+
+// 設定を取得する (未設定なら既定値)
+pub async fn settings(store: &dyn CommandStore, guild_id: i64) -> Result<Greeting, CommandError> {
+    Ok(store.get_greeting(guild_id).await?.unwrap_or_else(|| Greeting::new(guild_id)))
+}
+
+// 入力を検証して設定を保存する
+pub async fn save(store: &dyn CommandStore, greeting: &Greeting) -> Result<(), String> {
+    greeting.validate()?;
+    store.set_greeting(greeting).await.map_err(|e| e.report())
+}
+
+// 入退室メッセージの送信先
+#[async_trait]
+pub trait Greeter: Send + Sync {
+    // メッセージ中のメンションはメンバー本人だけ通知する
+    async fn post(&self, channel_id: i64, content: &str, user_id: i64) -> Result<(), String>;
+    async fn dm(&self, user_id: i64, content: &str) -> Result<(), String>;
+    async fn add_role(&self, guild_id: i64, user_id: i64, role_id: i64) -> Result<(), String>;
+}
+
+#[async_trait]
+impl Greeter for Http {
+    async fn post(&self, channel_id: i64, content: &str, user_id: i64) -> Result<(), String> {
+        let user = UserId(user_id as u64);
+        metrics::time_discord("channels/messages", ChannelId(channel_id as u64).send_message(self, |m| m.content(content).allowed_mentions(|a| a.users([user]))))
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn dm(&self, user_id: i64, content: &str) -> Result<(), String> {
+        let channel = metrics::time_discord("users/@me/channels", UserId(user_id as u64).create_dm_channel(self)).await.map_err(|e| e.to_string())?;
+        metrics::time_discord("channels/messages", channel.say(self, content)).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    async fn add_role(&self, guild_id: i64, user_id: i64, role_id: i64) -> Result<(), String> {
+        metrics::time_discord(
+            "guilds/members/roles",
+            self.add_member_role(guild_id as u64, user_id as u64, role_id as u64, Some("自動ロール")),
+        )
+        .await
+        .map_err(|e| e.to_string())
+    }
+}
+
+// 設定どおりにメッセージを送る (Web のテスト送信でも使う)。失敗した送り先の理由を返す
+pub async fn send(greeting: &Greeting, kind: Kind, greeter: &dyn Greeter, member: &MemberInfo) -> Result<(), String> {
+    greeting.check_sendable(kind)?;
+    let content = render(greeting.message(kind), member);
+    let mut errors = Vec::new();
+    if let Some(channel_id) = greeting.channel_id(kind) {
+        if let Err(e) = greeter.post(channel_id, &content, member.user_id).await {
+            errors.push(format!("チャンネル: {}", e));
+        }
+    }
+    if kind == Kind::Welcome && greeting.welcome_dm {
+        if let Err(e) = greeter.dm(member.user_id, &content).await {
+            errors.push(format!("DM: {}", e));
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors.join(" / ")) }
+}
+
+fn record(kind: &str, guild_id: i64, result: Result<(), String>) {
+    match result {
+        Ok(()) => metrics::GREETINGS.with_label_values(&[kind, "sent"]).inc(),
+        Err(e) => {
+            metrics::GREETINGS.with_label_values(&[kind, "failed"]).inc();
+            tracing::warn!(guild_id, kind, error = %e, "failed to greet member");
+        }
+    }
+}
+
+// メンバーが参加したとき。自動ロールを付けてから挨拶する
+pub async fn welcome(store: &dyn CommandStore, greeter: &dyn Greeter, guild_id: i64, member: &MemberInfo) {
+    let greeting = match store.get_greeting(guild_id).await {
+        Ok(Some(greeting)) => greeting,
+        Ok(None) => return,
+        Err(e) => return e.log(),
+    };
+    if let Some(role_id) = greeting.auto_role_id {
+        record("auto_role", guild_id, greeter.add_role(guild_id, member.user_id, role_id).await);
+    }
+    if greeting.welcome_enabled {
+        record(Kind::Welcome.as_str(), guild_id, send(&greeting, Kind::Welcome, greeter, member).await);
+    }
+}
+
+// メンバーが退出したとき (キック・BAN を含む)
+pub async fn farewell(store: &dyn CommandStore, greeter: &dyn Greeter, guild_id: i64, member: &MemberInfo) {
+    let greeting = match store.get_greeting(guild_id).await {
+        Ok(Some(greeting)) => greeting,
+        Ok(None) => return,
+        Err(e) => return e.log(),
+    };
+    if greeting.farewell_enabled {
+        record(Kind::Farewell.as_str(), guild_id, send(&greeting, Kind::Farewell, greeter, member).await);
+    }
+}
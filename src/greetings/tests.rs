@@ -0,0 +1,140 @@
+// プレースホルダの置き換えと、参加・退出時に設定どおり送ることを確認するテスト
+
+use std::sync::Mutex;
+
+use super::*;
+use crate::store::MemoryCommandStore;
+
+const GUILD: i64 = 1;
+
+#[derive(Default)]
+struct FakeGreeter {
+    // 送った内容 ("channel:<ID>" / "dm:<ユーザ ID>" / "role:<ユーザ ID>:<ロール ID>", 内容)
+    sent: Mutex<Vec<(String, String)>>,
+    // 送れないチャンネル
+    failing_channel: Option<i64>,
+}
+
+#[async_trait]
+impl Greeter for FakeGreeter {
+    async fn post(&self, channel_id: i64, content: &str, _user_id: i64) -> Result<(), String> {
+        if self.failing_channel == Some(channel_id) {
+            return Err("Missing Access".to_string());
+        }
+        self.sent.lock().unwrap().push((format!("channel:{}", channel_id), content.to_string()));
+        Ok(())
+    }
+
+    async fn dm(&self, user_id: i64, content: &str) -> Result<(), String> {
+        self.sent.lock().unwrap().push((format!("dm:{}", user_id), content.to_string()));
+        Ok(())
+    }
+
+    async fn add_role(&self, _guild_id: i64, user_id: i64, role_id: i64) -> Result<(), String> {
+        self.sent.lock().unwrap().push((format!("role:{}:{}", user_id, role_id), String::new()));
+        Ok(())
+    }
+}
+
+impl FakeGreeter {
+    fn take(&self) -> Vec<(String, String)> {
+        std::mem::take(&mut *self.sent.lock().unwrap())
+    }
+}
+
+fn member() -> MemberInfo {
+    MemberInfo { user_id: 42, username: "alice".to_string(), server: "Test Server".to_string(), member_count: Some(7) }
+}
+
+fn enabled() -> Greeting {
+    Greeting {
+        welcome_enabled: true,
+        welcome_channel_id: Some(100),
+        farewell_enabled: true,
+        farewell_channel_id: Some(200),
+        ..Greeting::new(GUILD)
+    }
+}
+
+#[test]
+fn placeholders() {
+    let template = "{user} ({username}) さん、{server} へようこそ！ {member_count} 人目です";
+    assert_eq!(render(template, &member()), "<@42> (alice) さん、Test Server へようこそ！ 7 人目です");
+    assert_eq!(preview(template, &member()), "@alice (alice) さん、Test Server へようこそ！ 7 人目です");
+    let unknown_count = MemberInfo { member_count: None, ..member() };
+    assert_eq!(render("{member_count} {unknown}", &unknown_count), "? {unknown}");
+
+    // 置き換えた値に含まれるプレースホルダは展開しない
+    let tricky = MemberInfo { username: "{server}{user}".to_string(), server: "{member_count}".to_string(), ..member() };
+    assert_eq!(render("{username} / {server} / {{user}", &tricky), "{server}{user} / {member_count} / {<@42>");
+    assert_eq!(preview("{user}", &tricky), "@{server}{user}");
+}
+
+#[test]
+fn validates_settings() {
+    // 無効なら送り先がなくても保存できる
+    assert_eq!(Greeting::new(GUILD).validate(), Ok(()));
+    assert_eq!(enabled().validate(), Ok(()));
+
+    let no_destination = Greeting { welcome_channel_id: None, ..enabled() };
+    assert_eq!(no_destination.validate(), Err("送信先のチャンネルを選ぶか、DM で送るようにしてください。".to_string()));
+    // DM だけでもよい
+    assert_eq!(Greeting { welcome_dm: true, ..no_destination }.validate(), Ok(()));
+    let no_farewell_channel = Greeting { farewell_channel_id: None, ..enabled() };
+    assert_eq!(no_farewell_channel.validate(), Err("送信先のチャンネルを選んでください。".to_string()));
+    let empty = Greeting { welcome_message: " ".to_string(), ..enabled() };
+    assert_eq!(empty.validate(), Err("メッセージを入力してください。".to_string()));
+    let long = Greeting { farewell_message: "a".repeat(MAX_MESSAGE_LEN + 1), farewell_enabled: false, ..enabled() };
+    assert!(long.validate().unwrap_err().contains("2000 文字"));
+}
+
+#[tokio::test]
+async fn welcome_assigns_role_and_greets() {
+    let store = MemoryCommandStore::new();
+    let greeter = FakeGreeter::default();
+    // 未設定のギルドでは何もしない
+    welcome(&store, &greeter, GUILD, &member()).await;
+    assert!(greeter.take().is_empty());
+
+    save(&store, &Greeting { welcome_dm: true, auto_role_id: Some(30), ..enabled() }).await.unwrap();
+    welcome(&store, &greeter, GUILD, &member()).await;
+    assert_eq!(
+        greeter.take(),
+        [
+            ("role:42:30".to_string(), String::new()),
+            ("channel:100".to_string(), "<@42> さん、Test Server へようこそ！".to_string()),
+            ("dm:42".to_string(), "<@42> さん、Test Server へようこそ！".to_string()),
+        ]
+    );
+
+    // メッセージを無効にしても自動ロールは付ける
+    save(&store, &Greeting { welcome_enabled: false, auto_role_id: Some(30), ..enabled() }).await.unwrap();
+    welcome(&store, &greeter, GUILD, &member()).await;
+    assert_eq!(greeter.take(), [("role:42:30".to_string(), String::new())]);
+}
+
+#[tokio::test]
+async fn farewell_follows_settings() {
+    let store = MemoryCommandStore::new();
+    let greeter = FakeGreeter::default();
+    save(&store, &enabled()).await.unwrap();
+    farewell(&store, &greeter, GUILD, &member()).await;
+    assert_eq!(greeter.take(), [("channel:200".to_string(), "alice さんが退出しました。".to_string())]);
+
+    save(&store, &Greeting { farewell_enabled: false, ..enabled() }).await.unwrap();
+    farewell(&store, &greeter, GUILD, &member()).await;
+    assert!(greeter.take().is_empty());
+}
+
+#[tokio::test]
+async fn send_reports_failed_destinations() {
+    let greeter = FakeGreeter { failing_channel: Some(100), ..FakeGreeter::default() };
+    let greeting = Greeting { welcome_dm: true, ..enabled() };
+    // チャンネルに送れなくても DM は送る
+    assert_eq!(send(&greeting, Kind::Welcome, &greeter, &member()).await, Err("チャンネル: Missing Access".to_string()));
+    assert_eq!(greeter.take().len(), 1);
+    // 無効でも送り先がそろっていれば送れる (Web のテスト送信)
+    let disabled = Greeting { farewell_enabled: false, ..enabled() };
+    assert_eq!(send(&disabled, Kind::Farewell, &greeter, &member()).await, Ok(()));
+    assert_eq!(send(&Greeting::new(GUILD), Kind::Farewell, &greeter, &member()).await, Err("送信先のチャンネルを選んでください。".to_string()));
+}
@@ -0,0 +1,152 @@
+// Gateway の接続状態 (/healthz と /readyz 用)
+// main の監視タスクがシャードの状態とハートビートの応答時間を定期的に書き込む
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+// 各シャードの状態 (Bot プロセスが定期的に DB に書き込み、Web のダッシュボードで表示する)
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct ShardStatus {
+    pub shard_id: i64,
+    pub shard_total: i64,
+    pub stage: String,
+    pub latency_ms: Option<i64>,
+    // このシャードが担当するギルドの数
+    pub guilds: i64,
+    pub updated_at: Option<String>,
+}
+
+// この時間ハートビートの応答がなければ準備未完了とみなす (Discord の間隔は約 41 秒)
+const HEARTBEAT_STALE: Duration = Duration::from_secs(120);
+// この時間以上準備未完了が続いたら異常とみなす (/healthz が 503 を返す)
+const UNREADY_LIMIT: Duration = Duration::from_secs(300);
+
+struct Shard {
+    stage: String,
+    latency: Option<Duration>,
+    // 最後にハートビートの応答 (または接続完了) を確認した時刻
+    last_heartbeat: Option<Instant>,
+}
+
+struct Gateway {
+    shards: BTreeMap<u64, Shard>,
+    // 準備未完了になった時刻 (準備完了中は None)
+    unready_since: Option<Instant>,
+    restarts: u32,
+}
+
+pub struct Health {
+    gateway: Mutex<Gateway>,
+    // web-only では Gateway に接続しないため、準備完了の判定から外す
+    gateway_required: bool,
+    // 特権インテントが許可されていないため動かない機能
+    degraded: Mutex<Vec<String>>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health::new()
+    }
+}
+
+impl Health {
+    pub fn new() -> Health {
+        Health {
+            gateway: Mutex::new(Gateway { shards: BTreeMap::new(), unready_since: Some(Instant::now()), restarts: 0 }),
+            gateway_required: true,
+            degraded: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn without_gateway() -> Health {
+        Health {
+            gateway: Mutex::new(Gateway { shards: BTreeMap::new(), unready_since: None, restarts: 0 }),
+            gateway_required: false,
+            degraded: Mutex::new(Vec::new()),
+        }
+    }
+
+    // シャードの状態を更新する。latency が前回から変わっていればハートビートの応答があったとみなす
+    pub fn update_shard(&self, shard: u64, stage: &str, latency: Option<Duration>) {
+        let mut gateway = self.gateway.lock().unwrap();
+        let now = Instant::now();
+        let entry = gateway.shards.entry(shard).or_insert(Shard { stage: String::new(), latency: None, last_heartbeat: None });
+        let connected = stage == "connected";
+        if connected && (entry.stage != stage || (latency.is_some() && latency != entry.latency)) {
+            entry.last_heartbeat = Some(now);
+        }
+        entry.stage = stage.to_string();
+        entry.latency = latency;
+        let ready = gateway_ready(&gateway.shards, now);
+        match (ready, gateway.unready_since) {
+            (true, _) => gateway.unready_since = None,
+            (false, None) => gateway.unready_since = Some(now),
+            (false, Some(_)) => {}
+        }
+    }
+
+    pub fn set_degraded(&self, features: Vec<String>) {
+        *self.degraded.lock().unwrap() = features;
+    }
+
+    pub fn degraded(&self) -> Vec<String> {
+        self.degraded.lock().unwrap().clone()
+    }
+
+    // クライアントを作り直す前にシャードの状態を捨てる
+    pub fn client_restarting(&self) {
+        let mut gateway = self.gateway.lock().unwrap();
+        gateway.shards.clear();
+        gateway.restarts += 1;
+        gateway.unready_since.get_or_insert_with(Instant::now);
+    }
+
+    // すべてのシャードが接続済みで、ハートビートの応答が途切れていないか
+    pub fn gateway_ready(&self) -> bool {
+        if !self.gateway_required {
+            return true;
+        }
+        let gateway = self.gateway.lock().unwrap();
+        gateway_ready(&gateway.shards, Instant::now())
+    }
+
+    // 準備未完了の状態が長く続いていないか
+    pub fn gateway_alive(&self) -> bool {
+        let gateway = self.gateway.lock().unwrap();
+        gateway.unready_since.map(|since| since.elapsed() < UNREADY_LIMIT).unwrap_or(true)
+    }
+
+    pub fn gateway_report(&self) -> Value {
+        let gateway = self.gateway.lock().unwrap();
+        let shards: Vec<Value> = gateway
+            .shards
+            .iter()
+            .map(|(id, shard)| {
+                json!({
+                    "shard": id,
+                    "stage": shard.stage,
+                    "latency_ms": shard.latency.map(|l| l.as_millis() as u64),
+                    "last_heartbeat_secs_ago": shard.last_heartbeat.map(|t| t.elapsed().as_secs()),
+                })
+            })
+            .collect();
+        if !self.gateway_required {
+            return json!({ "required": false });
+        }
+        json!({
+            "ready": gateway_ready(&gateway.shards, Instant::now()),
+            "unready_secs": gateway.unready_since.map(|t| t.elapsed().as_secs()),
+            "restarts": gateway.restarts,
+            "shards": shards,
+        })
+    }
+}
+
+fn gateway_ready(shards: &BTreeMap<u64, Shard>, now: Instant) -> bool {
+    !shards.is_empty()
+        && shards.values().all(|s| {
+            s.stage == "connected" && s.last_heartbeat.map(|t| now.duration_since(t) < HEARTBEAT_STALE).unwrap_or(false)
+        })
+}
@@ -0,0 +1,92 @@
+// Gateway インテントの決定
+// 有効な機能から必要なインテントだけを求め、開発者ポータルで許可されていない特権インテントは外して接続する
+// (特権インテントを要求したまま接続すると Gateway に拒否され、Bot 全体が動かなくなるため)
+#[cfg(test)]
+mod tests;
+
+use serde::Deserialize;
+use serenity::http::request::RequestBuilder;
+use serenity::http::routing::RouteInfo;
+use serenity::http::Http;
+use serenity::model::gateway::GatewayIntents;
+
+use crate::config::Features;
+use crate::metrics;
+
+// 開発者ポータルで個別に許可が必要なインテント
+pub const PRIVILEGED: GatewayIntents = GatewayIntents::GUILD_MEMBERS
+    .union(GatewayIntents::GUILD_PRESENCES)
+    .union(GatewayIntents::MESSAGE_CONTENT);
+
+// アプリケーションのフラグ (serenity 0.11 の ApplicationFlags は MESSAGE_CONTENT の値が誤っているため自前で持つ)
+const FLAG_PRESENCE: u64 = (1 << 12) | (1 << 13);
+const FLAG_GUILD_MEMBERS: u64 = (1 << 14) | (1 << 15);
+const FLAG_MESSAGE_CONTENT: u64 = (1 << 18) | (1 << 19);
+
+// 機能ごとに必要なインテント
+struct Need {
+    feature: &'static str,
+    intents: GatewayIntents,
+}
+
+// ロールのスコープ判定はメッセージ・インタラクションに付くメンバー情報を使うため GUILD_MEMBERS は入退室メッセージにだけ必要
+fn needs(features: &Features) -> Vec<Need> {
+    // ギルド・チャンネル・ロールのキャッシュとスラッシュコマンドの登録
+    let mut needs = vec![Need { feature: "core", intents: GatewayIntents::GUILDS }];
+    if features.text_commands {
+        needs.push(Need { feature: "text_commands", intents: GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT });
+    }
+    if features.greetings {
+        needs.push(Need { feature: "greetings", intents: GatewayIntents::GUILD_MEMBERS });
+    }
+    needs
+}
+
+// 有効な機能に必要なインテント (discord.intents = ["auto"])
+pub fn required(features: &Features) -> GatewayIntents {
+    needs(features).iter().fold(GatewayIntents::empty(), |acc, n| acc | n.intents)
+}
+
+#[derive(Debug)]
+pub struct Resolved {
+    // 実際に要求するインテント
+    pub intents: GatewayIntents,
+    // 許可されていないため外した特権インテント
+    pub missing: GatewayIntents,
+    // 必要なインテントが揃わず動かない機能 (features.* の名前)
+    pub degraded: Vec<&'static str>,
+}
+
+// granted が None (許可状況を確認できなかった) の場合は要求どおりに接続する
+pub fn resolve(requested: GatewayIntents, granted: Option<GatewayIntents>, features: &Features) -> Resolved {
+    let missing = match granted {
+        Some(granted) => requested & PRIVILEGED & !granted,
+        None => GatewayIntents::empty(),
+    };
+    let intents = requested & !missing;
+    let degraded = needs(features).into_iter().filter(|n| !intents.contains(n.intents)).map(|n| n.feature).collect();
+    Resolved { intents, missing, degraded }
+}
+
+#[derive(Deserialize)]
+struct ApplicationFlags {
+    #[serde(default)]
+    flags: u64,
+}
+
+// 開発者ポータルで許可されている特権インテント
+pub async fn granted_privileged(http: &Http) -> Result<GatewayIntents, serenity::Error> {
+    let request = RequestBuilder::new(RouteInfo::GetCurrentApplicationInfo).build();
+    let app: ApplicationFlags = metrics::time_discord("applications/@me", http.fire(request)).await?;
+    let mut granted = GatewayIntents::empty();
+    if app.flags & FLAG_PRESENCE != 0 {
+        granted |= GatewayIntents::GUILD_PRESENCES;
+    }
+    if app.flags & FLAG_GUILD_MEMBERS != 0 {
+        granted |= GatewayIntents::GUILD_MEMBERS;
+    }
+    if app.flags & FLAG_MESSAGE_CONTENT != 0 {
+        granted |= GatewayIntents::MESSAGE_CONTENT;
+    }
+    Ok(granted)
+}
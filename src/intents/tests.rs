@@ -0,0 +1,50 @@
+// 有効な機能から求めるインテントと、許可されていない特権インテントの扱いを確認するテスト
+
+use super::*;
+
+fn features(text_commands: bool, greetings: bool) -> Features {
+    Features { text_commands, greetings, ..Features::default() }
+}
+
+#[test]
+fn requires_only_what_enabled_features_need() {
+    assert_eq!(required(&features(false, false)), GatewayIntents::GUILDS);
+    assert_eq!(required(&features(true, false)), GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT);
+    assert!(required(&features(false, true)).contains(GatewayIntents::GUILD_MEMBERS));
+    // 既定では特権インテントの guild_members を要求しない
+    assert!(!required(&Features::default()).contains(GatewayIntents::GUILD_MEMBERS));
+}
+
+#[test]
+fn drops_privileged_intents_that_are_not_granted() {
+    let features = features(true, true);
+    let requested = required(&features);
+
+    let resolved = resolve(requested, Some(GatewayIntents::MESSAGE_CONTENT), &features);
+    assert_eq!(resolved.missing, GatewayIntents::GUILD_MEMBERS);
+    assert_eq!(resolved.intents, requested - GatewayIntents::GUILD_MEMBERS);
+    assert_eq!(resolved.degraded, ["greetings"]);
+
+    // 特権インテントでないものは外さない
+    let resolved = resolve(requested, Some(GatewayIntents::empty()), &features);
+    assert_eq!(resolved.missing, GatewayIntents::GUILD_MEMBERS | GatewayIntents::MESSAGE_CONTENT);
+    assert!(resolved.intents.contains(GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES));
+    assert_eq!(resolved.degraded, ["text_commands", "greetings"]);
+
+    let resolved = resolve(requested, Some(PRIVILEGED), &features);
+    assert_eq!((resolved.intents, resolved.missing), (requested, GatewayIntents::empty()));
+    assert!(resolved.degraded.is_empty());
+}
+
+#[test]
+fn connects_as_requested_when_grants_are_unknown() {
+    let features = features(true, true);
+    let requested = required(&features);
+    let resolved = resolve(requested, None, &features);
+    assert_eq!((resolved.intents, resolved.missing), (requested, GatewayIntents::empty()));
+    assert!(resolved.degraded.is_empty());
+
+    // 明示的に指定したインテントが機能に足りない場合も動かない機能として報告する
+    let resolved = resolve(GatewayIntents::GUILDS, None, &features);
+    assert_eq!(resolved.degraded, ["text_commands", "greetings"]);
+}
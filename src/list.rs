@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rand::{distributions::Alphanumeric, Rng};
+use serenity::builder::{CreateComponents, CreateEmbed, CreateInteractionResponseData};
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::channel::AttachmentType;
+
+// 1 ページに表示するコマンド数
+pub const PAGE_SIZE: usize = 10;
+// これを超える件数はページ送りせずテキストファイルで返す
+pub const FILE_THRESHOLD: usize = 250;
+// 最後の操作からこの時間が経つとボタン操作を受け付けない
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(300);
+// 一覧に表示する返答のプレビュー文字数
+const PREVIEW_LEN: usize = 80;
+
+// /list の並び順
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListSort {
+    Name,
+    Newest,
+    Oldest,
+}
+
+impl ListSort {
+    pub fn parse(s: &str) -> Option<ListSort> {
+        match s {
+            "name" => Some(ListSort::Name),
+            "newest" => Some(ListSort::Newest),
+            "oldest" => Some(ListSort::Oldest),
+            _ => None,
+        }
+    }
+}
+
+pub struct ListEntry {
+    pub name: String,
+    pub response: String,
+}
+
+// 1 回の /list 実行に対応するページ送りの状態
+pub struct ListSession {
+    pub title: String,
+    entries: Vec<ListEntry>,
+    page: usize,
+    last_used: Instant,
+}
+
+impl ListSession {
+    pub fn new(title: String, entries: Vec<ListEntry>) -> ListSession {
+        ListSession { title, entries, page: 0, last_used: Instant::now() }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.entries.len().div_ceil(PAGE_SIZE).max(1)
+    }
+
+    // 範囲外のページは先頭/末尾に丸める
+    pub fn set_page(&mut self, page: usize) {
+        self.page = page.min(self.page_count() - 1);
+    }
+
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    pub fn embed(&self) -> CreateEmbed {
+        let lines: Vec<String> = self
+            .entries
+            .iter()
+            .skip(self.page * PAGE_SIZE)
+            .take(PAGE_SIZE)
+            .map(|e| format!("**!{}** {}", truncate(&e.name, 50), truncate(&e.response, PREVIEW_LEN)))
+            .collect();
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(format!("{} ({} 件)", self.title, self.entries.len()))
+            .description(lines.join("\n"))
+            .footer(|f| f.text(format!("ページ {} / {}", self.page + 1, self.page_count())));
+        embed
+    }
+
+    pub fn components(&self, session_id: &str) -> CreateComponents {
+        let last = self.page_count() - 1;
+        let mut components = CreateComponents::default();
+        components.create_action_row(|row| {
+            row.create_button(|b| {
+                b.custom_id(format!("list:{}:first", session_id)).label("≪").style(ButtonStyle::Secondary).disabled(self.page == 0)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("list:{}:prev", session_id)).label("前へ").style(ButtonStyle::Primary).disabled(self.page == 0)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("list:{}:jump", session_id)).label("ページ指定").style(ButtonStyle::Secondary).disabled(last == 0)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("list:{}:next", session_id)).label("次へ").style(ButtonStyle::Primary).disabled(self.page == last)
+            })
+            .create_button(|b| {
+                b.custom_id(format!("list:{}:last", session_id)).label("≫").style(ButtonStyle::Secondary).disabled(self.page == last)
+            })
+        });
+        components
+    }
+}
+
+// 実行中の /list セッション (メモリ上のみ。再起動すると期限切れ扱いになる)
+pub struct ListSessions {
+    inner: Mutex<HashMap<String, ListSession>>,
+    ttl: Duration,
+}
+
+impl Default for ListSessions {
+    fn default() -> Self {
+        ListSessions::new(SESSION_TIMEOUT)
+    }
+}
+
+impl ListSessions {
+    pub fn new(ttl: Duration) -> ListSessions {
+        ListSessions { inner: Mutex::new(HashMap::new()), ttl }
+    }
+
+    pub fn insert(&self, session: ListSession) -> String {
+        let id: String = rand::thread_rng().sample_iter(&Alphanumeric).take(16).map(char::from).collect();
+        let mut inner = self.inner.lock().unwrap();
+        inner.retain(|_, s| s.last_used.elapsed() < self.ttl);
+        inner.insert(id.clone(), session);
+        id
+    }
+
+    // 期限内のセッションがあれば操作する (操作するたびに期限を延長する)
+    pub fn with_session<R>(&self, id: &str, f: impl FnOnce(&mut ListSession) -> R) -> Option<R> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.get_mut(id) {
+            Some(s) if s.last_used.elapsed() < self.ttl => {
+                s.last_used = Instant::now();
+                Some(f(s))
+            }
+            Some(_) => {
+                inner.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+// ボタン操作/ページ指定に対するメッセージ更新内容 (None はセッション期限切れ)
+pub fn render_update<'a, 'b>(
+    d: &'b mut CreateInteractionResponseData<'a>,
+    update: Option<(CreateEmbed, CreateComponents)>,
+) -> &'b mut CreateInteractionResponseData<'a> {
+    match update {
+        Some((embed, components)) => d.set_embed(embed).set_components(components),
+        None => d
+            .content("一覧の有効期限が切れました。もう一度 /list を実行してください。")
+            .set_embeds(Vec::new())
+            .set_components(CreateComponents::default()),
+    }
+}
+
+// "list:<session>:<action>" を (session, action) に分解する
+pub fn parse_custom_id(custom_id: &str) -> Option<(&str, &str)> {
+    custom_id.strip_prefix("list:")?.split_once(':')
+}
+
+// 件数が多い場合に添付するテキストファイル
+pub fn as_attachment(entries: &[ListEntry]) -> AttachmentType<'static> {
+    let text: String = entries.iter().map(|e| format!("!{}: {}\n", e.name, e.response)).collect();
+    AttachmentType::Bytes { data: Cow::Owned(text.into_bytes()), filename: "commands.txt".to_string() }
+}
+
+pub fn truncate(s: &str, max: usize) -> String {
+    let s = s.replace('\n', " ");
+    if s.chars().count() <= max {
+        return s;
+    }
+    let mut out: String = s.chars().take(max - 1).collect();
+    out.push('…');
+    out
+}
@@ -1,323 +1,538 @@
 use serenity::async_trait;
+use serenity::builder::CreateInteractionResponse;
 use serenity::model::{channel::Message, gateway::Ready};
 use serenity::model::application::interaction::Interaction;
 use serenity::model::application::command::CommandOptionType;
-use serenity::model::application::command::CommandType;
 use serenity::model::application::component::ActionRowComponent;
 use serenity::model::application::component::InputTextStyle;
-use serenity::model::guild::Guild;
-use serenity::model::id::GuildId;
+use serenity::model::application::interaction::application_command::{ApplicationCommandInteraction, CommandDataOption};
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::application::interaction::modal::ModalSubmitInteraction;
+use serenity::model::prelude::InteractionResponseType;
+use serenity::model::channel::ChannelType;
+use serenity::model::guild::{Guild, Member, Role, UnavailableGuild};
+use serenity::model::user::User;
+use serenity::model::channel::{Channel, ChannelCategory, GuildChannel};
+use serenity::client::bridge::gateway::event::ShardStageUpdateEvent;
+use serenity::gateway::ConnectionStage;
+use serenity::client::bridge::gateway::ShardManager;
+use serenity::client::ClientError;
+use serenity::gateway::GatewayError;
+use serenity::model::id::{ChannelId, GuildId, MessageId, RoleId};
 use serenity::prelude::*;
-use sqlx::PgPool;
-use futures::StreamExt;
+use serenity::http::Http;
 use std::sync::Arc;
 use axum::Router;
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use tokio::task::JoinSet;
+use tracing::Instrument;
+use clap::Parser;
 mod web;
 mod commands;
+mod scopes;
+mod registration;
+mod list;
+mod store;
+mod dispatch;
+mod telemetry;
+mod metrics;
+mod health;
+mod shutdown;
+mod config;
+mod intents;
+mod events;
+mod cli;
+mod export;
+mod retention;
+mod scheduler;
+mod reminders;
+mod greetings;
+
+use store::CommandStore;
 
 struct Handler {
-    pool: Arc<PgPool>,
+    store: Arc<dyn CommandStore>,
+    dispatcher: dispatch::Dispatcher,
+    registration: registration::RegistrationQueue,
+    shutdown: shutdown::Shutdown,
+    // features.text_commands
+    text_commands: bool,
+    // features.greetings
+    greetings: bool,
+    // チャンネル・ロールの変更を別プロセスの Web に知らせる
+    events: events::EventBus,
+    // 退出したギルドのデータを残す期間 (retention.guild_days)
+    guild_retention: std::time::Duration,
 }
 
-async fn register_guild_commands(ctx: &Context, guild_id: GuildId) {
-    // ギルド内のアプリケーションコマンドを「置き換え」る（重複防止）
-    if let Err(e) = guild_id
-        .set_application_commands(&ctx.http, |commands| {
-            commands
-                .create_application_command(|command| {
-                    command
-                        .name("add")
-                        .description("新しいコマンドを追加します")
-                        .dm_permission(false)
-                        .create_option(|option| {
-                            option
-                                .name("name")
-                                .description("コマンド名")
-                                .kind(CommandOptionType::String)
-                                .required(true)
-                        })
-                        .create_option(|option| {
-                            option
-                                .name("response")
-                                .description("返答内容")
-                                .kind(CommandOptionType::String)
-                                .required(true)
-                        })
-                })
-                .create_application_command(|command| {
-                    command
-                        .name("remove")
-                        .description("コマンドを削除します")
-                        .dm_permission(false)
-                        .create_option(|option| {
-                            option
-                                .name("name")
-                                .description("削除するコマンド名")
-                                .kind(CommandOptionType::String)
-                                .required(true)
-                        })
+// 実行場所と実行者ロールからスコープ判定用のコンテキストを作る
+fn scope_context(ctx: &Context, channel_id: ChannelId, roles: &[RoleId]) -> scopes::ScopeContext {
+    let channel = ctx.cache.guild_channel(channel_id);
+    // スレッド内のメッセージは親チャンネルの設定に従う
+    let (channel_id, parent_id) = match &channel {
+        Some(c) if matches!(c.kind, ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread) => {
+            let parent = c.parent_id.and_then(|p| ctx.cache.guild_channel(p));
+            (c.parent_id.unwrap_or(channel_id), parent.and_then(|p| p.parent_id))
+        }
+        Some(c) => (channel_id, c.parent_id),
+        None => (channel_id, None),
+    };
+    scopes::ScopeContext {
+        channel_id: channel_id.0 as i64,
+        category_id: parent_id.map(|c| c.0 as i64),
+        role_ids: roles.iter().map(|r| r.0 as i64).collect(),
+    }
+}
+
+// コマンドの設定に従って返答を送る
+async fn deliver(ctx: &Context, msg: &Message, command: &commands::Command, content: &str) {
+    let sent = match command.reply_mode() {
+        commands::ReplyMode::Reply => msg.reply(ctx, content).await,
+        commands::ReplyMode::ReplyPing => msg.reply_ping(ctx, content).await,
+        commands::ReplyMode::Send => msg.channel_id.say(&ctx.http, content).await,
+        commands::ReplyMode::Dm => msg.author.direct_message(ctx, |m| m.content(content)).await,
+        commands::ReplyMode::Channel => match command.target_channel_id {
+            Some(id) => ChannelId(id as u64).say(&ctx.http, content).await,
+            // 送信先が未設定の場合は通常の返信にフォールバック
+            None => msg.reply(ctx, content).await,
+        },
+    };
+
+    if command.delete_trigger {
+        if let Err(e) = msg.delete(ctx).await {
+            tracing::warn!(channel_id = msg.channel_id.0, error = ?e, "failed to delete trigger message");
+        }
+    }
+
+    match (sent, command.delete_after) {
+        (Ok(reply), Some(secs)) if secs > 0 => {
+            let http = ctx.http.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(secs as u64)).await;
+                let _ = reply.delete(&http).await;
+            });
+        }
+        (Err(e), _) => tracing::warn!(command = %command.name, error = ?e, "failed to send response"),
+        _ => {}
+    }
+}
+
+// スラッシュコマンドとして実行されたカスタムコマンドの返答を送る
+async fn deliver_interaction(ctx: &Context, cmd: &ApplicationCommandInteraction, command: &commands::Command, content: String) {
+    // 返答には実行者が自由に入力できる引数が入るため、メンションでは通知しない
+    // 返信以外の送り方では、実行者にだけ見える確認メッセージで応答する
+    let sent = match command.reply_mode() {
+        commands::ReplyMode::Send => Some(cmd.channel_id.send_message(&ctx.http, |m| m.content(&content).allowed_mentions(|a| a.empty_parse())).await),
+        commands::ReplyMode::Dm => Some(cmd.user.direct_message(ctx, |m| m.content(&content).allowed_mentions(|a| a.empty_parse())).await),
+        commands::ReplyMode::Channel => match command.target_channel_id {
+            Some(id) => Some(ChannelId(id as u64).send_message(&ctx.http, |m| m.content(&content).allowed_mentions(|a| a.empty_parse())).await),
+            // 送信先が未設定の場合は通常の返信にフォールバック
+            None => None,
+        },
+        commands::ReplyMode::Reply | commands::ReplyMode::ReplyPing => None,
+    };
+
+    let delete_after = command.delete_after.filter(|secs| *secs > 0).map(|secs| std::time::Duration::from_secs(secs as u64));
+    match sent {
+        Some(Ok(message)) => {
+            let _ = cmd
+                .create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content("送信しました。").ephemeral(true)))
+                .await;
+            if let Some(delay) = delete_after {
+                let http = ctx.http.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = message.delete(&http).await;
+                });
+            }
+        }
+        Some(Err(e)) => {
+            tracing::warn!(command = %command.name, error = ?e, "failed to send response");
+            let _ = cmd
+                .create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content("返答の送信に失敗しました。").ephemeral(true)))
+                .await;
+        }
+        None => {
+            let _ = cmd
+                .create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content(&content).allowed_mentions(|a| a.empty_parse())))
+                .await;
+            if let Some(delay) = delete_after {
+                let http = ctx.http.clone();
+                let cmd = cmd.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = cmd.delete_original_interaction_response(&http).await;
+                });
+            }
+        }
+    }
+}
+
+// イベントの発生元 (返答先)
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Message(&'a Message),
+    Command(&'a ApplicationCommandInteraction),
+    Component(&'a MessageComponentInteraction),
+    Modal(&'a ModalSubmitInteraction),
+}
+
+// serenity の Context を使って Discord に送る Outbound
+struct DiscordOutbound<'a> {
+    ctx: &'a Context,
+    store: &'a dyn CommandStore,
+    source: Source<'a>,
+}
+
+fn interaction_response(response: dispatch::Response) -> CreateInteractionResponse<'static> {
+    let mut r = CreateInteractionResponse::default();
+    match response {
+        dispatch::Response::Message { content, ephemeral } => {
+            r.interaction_response_data(|d| d.content(content).ephemeral(ephemeral).allowed_mentions(|m| m.empty_parse()));
+        }
+        dispatch::Response::File { content, attachment } => {
+            r.interaction_response_data(|d| d.content(content).add_file(attachment).ephemeral(true));
+        }
+        dispatch::Response::List { embed, components } => {
+            r.interaction_response_data(|d| {
+                d.set_embed(embed).ephemeral(true);
+                if let Some(components) = components {
+                    d.set_components(components);
+                }
+                d
+            });
+        }
+        dispatch::Response::UpdateList(update) => {
+            r.kind(InteractionResponseType::UpdateMessage).interaction_response_data(|d| list::render_update(d, update));
+        }
+        dispatch::Response::Modal(modal) => {
+            r.kind(InteractionResponseType::Modal).interaction_response_data(|d| {
+                d.custom_id(modal.custom_id).title(modal.title).components(|components| {
+                    for input in modal.inputs {
+                        components.create_action_row(|row| {
+                            row.create_input_text(|i| {
+                                i.custom_id(input.custom_id)
+                                    .label(input.label)
+                                    .placeholder(input.placeholder)
+                                    .required(true)
+                                    .max_length(input.max_length)
+                                    .style(if input.paragraph { InputTextStyle::Paragraph } else { InputTextStyle::Short });
+                                if let Some(value) = input.value {
+                                    i.value(value);
+                                }
+                                i
+                            })
+                        });
+                    }
+                    components
                 })
-                .create_application_command(|command| {
-                    command
-                        .name("update")
-                        .description("コマンドを更新します")
-                        .dm_permission(false)
-                        .create_option(|option| {
-                            option
-                                .name("name")
-                                .description("更新するコマンド名")
-                                .kind(CommandOptionType::String)
-                                .required(true)
-                        })
-                        .create_option(|option| {
-                            option
-                                .name("response")
-                                .description("新しい返答内容")
-                                .kind(CommandOptionType::String)
-                                .required(true)
-                        })
+            });
+        }
+    }
+    r
+}
+
+#[async_trait]
+impl dispatch::Outbound for DiscordOutbound<'_> {
+    async fn respond(&self, response: dispatch::Response) {
+        let http = &self.ctx.http;
+        let result = match self.source {
+            // テキストコマンドへの返答は通常の返信にする
+            Source::Message(msg) => match response {
+                dispatch::Response::Message { content, .. } => msg.reply(self.ctx, content).await.map(|_| ()),
+                _ => Ok(()),
+            },
+            Source::Command(i) => {
+                let built = interaction_response(response);
+                i.create_interaction_response(http, |r| {
+                    *r = built;
+                    r
                 })
-                .create_application_command(|command| {
-                    command
-                        .name("list")
-                        .description("登録されているコマンド一覧を表示します")
-                        .dm_permission(false)
+                .await
+            }
+            Source::Component(i) => {
+                let built = interaction_response(response);
+                i.create_interaction_response(http, |r| {
+                    *r = built;
+                    r
                 })
-                .create_application_command(|command| {
-                    command
-                        .name("Register as Response")
-                        .kind(CommandType::Message)
+                .await
+            }
+            Source::Modal(i) => {
+                let built = interaction_response(response);
+                i.create_interaction_response(http, |r| {
+                    *r = built;
+                    r
                 })
+                .await
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, "failed to respond to interaction");
+        }
+    }
+
+    async fn deliver(&self, command: &commands::Command, content: String) {
+        match self.source {
+            Source::Message(msg) => deliver(self.ctx, msg, command, &content).await,
+            Source::Command(cmd) => deliver_interaction(self.ctx, cmd, command, content).await,
+            Source::Component(_) | Source::Modal(_) => {}
+        }
+    }
+
+    async fn register_commands(&self, guild_id: i64) -> Result<registration::Registration, String> {
+        registration::register_guild_commands(&self.ctx.http, self.store, GuildId(guild_id as u64)).await
+    }
+
+    async fn fetch_message(&self, message_id: u64) -> Option<dispatch::FetchedMessage> {
+        let channel_id = match self.source {
+            Source::Message(m) => m.channel_id,
+            Source::Command(i) => i.channel_id,
+            Source::Component(i) => i.channel_id,
+            Source::Modal(i) => i.channel_id,
+        };
+        let message = channel_id.message(&self.ctx.http, MessageId(message_id)).await.ok()?;
+        Some(dispatch::FetchedMessage {
+            content: message.content,
+            attachment_urls: message.attachments.into_iter().map(|a| a.url).collect(),
         })
-        .await
-    {
-        eprintln!(
-            "Failed to register application commands for guild {}: {:?}",
-            guild_id.0, e
-        );
-    } else {
-        println!("Registered application commands for guild {}", guild_id.0);
+    }
+
+    fn channel_name(&self, channel_id: i64) -> Option<String> {
+        self.ctx.cache.guild_channel(channel_id as u64).map(|c| c.name)
+    }
+}
+
+// serenity のオプションを dispatch の型に変換する
+fn convert_options(options: &[CommandDataOption], is_category: &dyn Fn(u64) -> bool) -> Vec<dispatch::CommandOption> {
+    use dispatch::OptionValue;
+    options
+        .iter()
+        .map(|o| {
+            let raw = o.value.as_ref();
+            let id = || raw.and_then(|v| v.as_str()).and_then(|v| v.parse::<u64>().ok());
+            let value = match o.kind {
+                CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup => {
+                    Some(OptionValue::SubCommand(convert_options(&o.options, is_category)))
+                }
+                CommandOptionType::String => raw.and_then(|v| v.as_str()).map(|v| OptionValue::String(v.to_string())),
+                CommandOptionType::Integer => raw.and_then(|v| v.as_i64()).map(OptionValue::Integer),
+                CommandOptionType::Boolean => raw.and_then(|v| v.as_bool()).map(OptionValue::Boolean),
+                CommandOptionType::Channel => id().map(|id| OptionValue::Channel { id: id as i64, is_category: is_category(id) }),
+                CommandOptionType::Role => id().map(|id| OptionValue::Role(id as i64)),
+                _ => None,
+            };
+            dispatch::CommandOption { name: o.name.clone(), value: value.unwrap_or(OptionValue::Other) }
+        })
+        .collect()
+}
+
+// インタラクションごとのスパンを作り、種類ごとの受信数を数える (command には コマンド名 または custom_id を入れる)
+fn interaction_span(kind: &'static str, guild_id: Option<GuildId>, channel_id: ChannelId, user_id: u64, command: &str) -> tracing::Span {
+    metrics::INTERACTIONS.with_label_values(&[kind]).inc();
+    tracing::info_span!(
+        "interaction",
+        kind,
+        guild_id = guild_id.map(|g| g.0),
+        channel_id = channel_id.0,
+        user_id,
+        command,
+    )
+}
+
+impl Handler {
+    async fn guild_changed(&self, guild_id: GuildId) {
+        // 同じプロセスの Web は Gateway のキャッシュを直接参照するので知らせる必要はない
+        if self.events.is_distributed() {
+            self.events.publish(events::Event::GuildChanged { guild_id: guild_id.0 as i64 }).await;
+        }
+    }
+
+    fn outbound<'a>(&'a self, ctx: &'a Context, source: Source<'a>) -> DiscordOutbound<'a> {
+        DiscordOutbound { ctx, store: self.store.as_ref(), source }
+    }
+
+    // 入退室メッセージのプレースホルダに入れる情報 (サーバー名とメンバー数は Gateway のキャッシュから)
+    fn member_info(ctx: &Context, guild_id: GuildId, user: &User) -> greetings::MemberInfo {
+        let (server, member_count) = ctx.cache.guild_field(guild_id, |g| (g.name.clone(), Some(g.member_count))).unwrap_or_default();
+        greetings::MemberInfo { user_id: user.id.0 as i64, username: user.name.clone(), server, member_count }
     }
 }
 
 #[async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
-        
-        // 既存の全ギルドにスラッシュコマンドを登録（重複防止のため置換）
+        tracing::info!(user = %ready.user.name, guilds = ready.guilds.len(), "connected to gateway");
+
+        // 既存の全ギルドにスラッシュコマンドを登録（変更のあるギルドのみ、間隔を空けて順に処理）
         for guild in ready.guilds {
-            register_guild_commands(&ctx, guild.id).await;
+            self.registration.enqueue(ctx.http.clone(), guild.id);
         }
     }
 
-    async fn guild_create(&self, ctx: Context, guild: Guild) {
+    async fn shard_stage_update(&self, _ctx: Context, event: ShardStageUpdateEvent) {
+        tracing::info!(shard = event.shard_id.0, old = %event.old, new = %event.new, "shard stage changed");
+        metrics::GATEWAY_SHARD_CONNECTED
+            .with_label_values(&[&event.shard_id.0.to_string()])
+            .set(i64::from(event.new == ConnectionStage::Connected));
+    }
+
+    async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: bool) {
         // ギルドが作成/利用可能になったら、コマンドを確実に登録
-        println!("Guild available/joined: {} (id={}) — ensuring commands", guild.name, guild.id.0);
-        register_guild_commands(&ctx, guild.id).await;
+        tracing::info!(guild_id = guild.id.0, guild = %guild.name, "guild available; ensuring commands");
+        self.registration.enqueue(ctx.http.clone(), guild.id);
+        retention::guild_joined(self.store.as_ref(), guild.id.0 as i64).await;
+    }
+
+    async fn guild_delete(&self, _ctx: Context, incomplete: UnavailableGuild, _full: Option<Guild>) {
+        // unavailable は Discord 側の障害で一時的に使えないだけなので、データはそのまま残す
+        if incomplete.unavailable {
+            tracing::warn!(guild_id = incomplete.id.0, "guild became unavailable");
+            return;
+        }
+        retention::guild_left(self.store.as_ref(), incomplete.id.0 as i64, self.guild_retention).await;
+        self.guild_changed(incomplete.id).await;
+    }
+
+    // チャンネル・ロールの変更 (Web のスコープ選択肢に反映させる)
+    async fn channel_create(&self, _ctx: Context, channel: &GuildChannel) {
+        self.guild_changed(channel.guild_id).await;
+    }
+
+    async fn channel_update(&self, _ctx: Context, _old: Option<Channel>, new: Channel) {
+        match new {
+            Channel::Guild(channel) => self.guild_changed(channel.guild_id).await,
+            Channel::Category(category) => self.guild_changed(category.guild_id).await,
+            _ => {}
+        }
+    }
+
+    async fn channel_delete(&self, _ctx: Context, channel: &GuildChannel) {
+        self.guild_changed(channel.guild_id).await;
+    }
+
+    async fn category_create(&self, _ctx: Context, category: &ChannelCategory) {
+        self.guild_changed(category.guild_id).await;
+    }
+
+    async fn category_delete(&self, _ctx: Context, category: &ChannelCategory) {
+        self.guild_changed(category.guild_id).await;
+    }
+
+    async fn guild_role_create(&self, _ctx: Context, role: Role) {
+        self.guild_changed(role.guild_id).await;
+    }
+
+    async fn guild_role_update(&self, _ctx: Context, _old: Option<Role>, role: Role) {
+        self.guild_changed(role.guild_id).await;
+    }
+
+    async fn guild_role_delete(&self, _ctx: Context, guild_id: GuildId, _role_id: RoleId, _role: Option<Role>) {
+        self.guild_changed(guild_id).await;
+    }
+
+    // メンバーの参加・退出 (features.greetings。Bot は対象にしない)
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
+        if !self.greetings || new_member.user.bot {
+            return;
+        }
+        let Some(_in_flight) = self.shutdown.track() else { return };
+        let member = Handler::member_info(&ctx, new_member.guild_id, &new_member.user);
+        greetings::welcome(self.store.as_ref(), ctx.http.as_ref(), new_member.guild_id.0 as i64, &member).await;
+    }
+
+    async fn guild_member_removal(&self, ctx: Context, guild_id: GuildId, user: User, _member: Option<Member>) {
+        if !self.greetings || user.bot {
+            return;
+        }
+        let Some(_in_flight) = self.shutdown.track() else { return };
+        let member = Handler::member_info(&ctx, guild_id, &user);
+        greetings::farewell(self.store.as_ref(), ctx.http.as_ref(), guild_id.0 as i64, &member).await;
     }
 
     async fn message(&self, ctx: Context, msg: Message) {
-        let content = msg.content.trim();
-        // 通常コマンドのみテキストで応答
-        if content.starts_with('!') && content.len() > 1 {
-            let cmd = &content[1..];
-            let guild_id = msg.guild_id.map(|g| g.0 as i64);
-            if let Some(guild_id) = guild_id {
-                if let Some(command) = commands::get_command(&self.pool, guild_id, cmd).await {
-                    let _ = msg.reply(&ctx, &command.response).await;
-                }
-            }
+        // コマンド以外のメッセージではスコープ判定の準備も不要
+        if !self.text_commands || !msg.content.trim().starts_with('!') {
+            return;
         }
+        // 終了処理中は新しいイベントを受け付けない
+        let Some(_in_flight) = self.shutdown.track() else { return };
+        // 本文は記録しない。コマンド名は登録済みのコマンドに一致したときだけ dispatch 側で記録する
+        let span = tracing::info_span!(
+            "message",
+            guild_id = msg.guild_id.map(|g| g.0),
+            channel_id = msg.channel_id.0,
+            user_id = msg.author.id.0,
+            command = tracing::field::Empty,
+        );
+        let roles = msg.member.as_ref().map(|m| m.roles.as_slice()).unwrap_or(&[]);
+        let incoming = dispatch::IncomingMessage {
+            guild_id: msg.guild_id.map(|g| g.0 as i64),
+            content: msg.content.clone(),
+            scope: scope_context(&ctx, msg.channel_id, roles),
+        };
+        self.dispatcher
+            .message(&self.outbound(&ctx, Source::Message(&msg)), incoming)
+            .instrument(span)
+            .await;
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Some(_in_flight) = self.shutdown.track() else { return };
         match interaction {
             Interaction::ApplicationCommand(cmd) => {
-                let name = cmd.data.name.as_str();
-                let guild_id = cmd.guild_id.map(|g| g.0 as i64);
-                match name {
-                    "add" => {
-                        if let Some(guild_id) = guild_id {
-                            if cmd.data.options.len() >= 2 {
-                                let cname = cmd.data.options[0].value.as_ref().and_then(|v| v.as_str()).unwrap_or("");
-                                let resp = cmd.data.options[1].value.as_ref().and_then(|v| v.as_str()).unwrap_or("");
-                                let ok = commands::add_command(&self.pool, guild_id, cname, resp).await;
-                                let reply = if ok { format!("コマンド '{}' を追加しました。", cname) } else { "追加に失敗しました。".to_string() };
-                                let _ = cmd.create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content(reply))).await;
-                            }
-                        }
-                    },
-                    "remove" => {
-                        if let Some(guild_id) = guild_id {
-                            if !cmd.data.options.is_empty() {
-                                let cname = cmd.data.options[0].value.as_ref().and_then(|v| v.as_str()).unwrap_or("");
-                                let ok = commands::remove_command(&self.pool, guild_id, cname).await;
-                                let reply = if ok { format!("コマンド '{}' を削除しました。", cname) } else { "そのコマンドは存在しません。".to_string() };
-                                let _ = cmd.create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content(reply))).await;
-                            }
-                        }
-                    },
-                    "update" => {
-                        if let Some(guild_id) = guild_id {
-                            if cmd.data.options.len() >= 2 {
-                                let cname = cmd.data.options[0].value.as_ref().and_then(|v| v.as_str()).unwrap_or("");
-                                let resp = cmd.data.options[1].value.as_ref().and_then(|v| v.as_str()).unwrap_or("");
-                                let ok = commands::update_command(&self.pool, guild_id, cname, resp).await;
-                                let reply = if ok { format!("コマンド '{}' を更新しました。", cname) } else { "そのコマンドは存在しません。".to_string() };
-                                let _ = cmd.create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content(reply))).await;
-                            }
-                        }
-                    },
-                    "list" => {
-                        if let Some(guild_id) = guild_id {
-                            let mut rows = sqlx::query_as::<_, commands::Command>("SELECT guild_id, name, response FROM commands WHERE guild_id = $1 ORDER BY name")
-                                .bind(guild_id)
-                                .fetch(&*self.pool);
-                            let mut entries = Vec::new();
-                            while let Some(Ok(cmd)) = rows.next().await {
-                                entries.push(format!("!{}: {}", cmd.name, cmd.response));
-                            }
-                            if entries.is_empty() {
-                                let _ = cmd.create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content("コマンドは登録されていません。"))).await;
-                                return;
-                            }
-                            // 2000文字制限で分割送信
-                            let mut buffer = String::new();
-                            for entry in entries {
-                                if buffer.len() + entry.len() + 1 > 2000 {
-                                    let _ = cmd.channel_id.say(&ctx.http, buffer.clone()).await;
-                                    buffer.clear();
-                                }
-                                if !buffer.is_empty() {
-                                    buffer.push('\n');
-                                }
-                                buffer.push_str(&entry);
-                            }
-                            if !buffer.is_empty() {
-                                let _ = cmd.channel_id.say(&ctx.http, buffer).await;
-                            }
-                            let _ = cmd.create_interaction_response(&ctx.http, |r| r.interaction_response_data(|d| d.content("コマンド一覧を送信しました。"))).await;
-                        }
-                    },
-                    "Register as Response" => {
-                        if let Some(guild_id) = guild_id {
-                            println!("Processing Register as Response command");
-                            // メッセージコンテキストメニューからの場合
-                            if !cmd.data.resolved.messages.is_empty() {
-                                println!("Found {} messages", cmd.data.resolved.messages.len());
-                                if let Some((_, message)) = cmd.data.resolved.messages.iter().next() {
-                                    println!("Processing message: {}", message.content);
-                                    let message_content = &message.content;
-                                    println!("Processing message: {}", message_content);
-                                    // メッセージIDをcustom_idに使用
-                                    let custom_id = format!("reg_resp:{}", message.id.0);
-                                    
-                                    // モーダルでコマンド名を入力してもらう
-                                    match cmd.create_interaction_response(&ctx.http, |response| {
-                                        response
-                                            .kind(serenity::model::prelude::InteractionResponseType::Modal)
-                                            .interaction_response_data(|data| {
-                                                data.custom_id(&custom_id)
-                                                    .title("コマンド名を入力")
-                                                    .components(|components| {
-                                                        components.create_action_row(|row| {
-                                                            row.create_input_text(|input| {
-                                                                input
-                                                                    .custom_id("command_name")
-                                                                    .label("コマンド名")
-                                                                    .placeholder("例: hello")
-                                                                    .required(true)
-                                                                    .max_length(50)
-                                                                    .style(InputTextStyle::Short)
-                                                            })
-                                                        })
-                                                    })
-                                            })
-                                    }).await {
-                                        Ok(_) => println!("Modal created successfully"),
-                                        Err(e) => println!("Failed to create modal: {:?}", e),
-                                    }
-                                } else {
-                                    let _ = cmd.create_interaction_response(&ctx.http, |response| {
-                                        response
-                                            .kind(serenity::model::prelude::InteractionResponseType::ChannelMessageWithSource)
-                                            .interaction_response_data(|message| {
-                                                message.content("メッセージが見つかりませんでした。")
-                                            })
-                                    }).await;
-                                }
-                            } else {
-                                let _ = cmd.create_interaction_response(&ctx.http, |response| {
-                                    response
-                                        .kind(serenity::model::prelude::InteractionResponseType::ChannelMessageWithSource)
-                                        .interaction_response_data(|message| {
-                                            message.content("メッセージが見つかりませんでした。")
-                                        })
-                                }).await;
-                            }
-                        }
-                    },
-                    _ => {}
-                }
+                let roles = cmd.member.as_ref().map(|m| m.roles.as_slice()).unwrap_or(&[]);
+                let resolved_channels = &cmd.data.resolved.channels;
+                let is_category = |id: u64| {
+                    resolved_channels
+                        .get(&ChannelId(id))
+                        .map(|c| c.kind == ChannelType::Category)
+                        .unwrap_or(false)
+                };
+                let invocation = dispatch::CommandInvocation {
+                    guild_id: cmd.guild_id.map(|g| g.0 as i64),
+                    name: cmd.data.name.clone(),
+                    options: convert_options(&cmd.data.options, &is_category),
+                    target_message_id: cmd.data.resolved.messages.keys().next().map(|id| id.0),
+                    scope: scope_context(&ctx, cmd.channel_id, roles),
+                    channel_id: cmd.channel_id.0 as i64,
+                    user_id: cmd.user.id.0 as i64,
+                };
+                let span = interaction_span("command", cmd.guild_id, cmd.channel_id, cmd.user.id.0, &cmd.data.name);
+                self.dispatcher
+                    .command(&self.outbound(&ctx, Source::Command(&cmd)), invocation)
+                    .instrument(span)
+                    .await;
+            },
+            Interaction::MessageComponent(comp) => {
+                let span = interaction_span("component", comp.guild_id, comp.channel_id, comp.user.id.0, &comp.data.custom_id);
+                self.dispatcher
+                    .component(&self.outbound(&ctx, Source::Component(&comp)), &comp.data.custom_id, comp.user.id.0 as i64)
+                    .instrument(span)
+                    .await;
             },
             Interaction::ModalSubmit(modal) => {
-                if modal.data.custom_id.starts_with("reg_resp:") {
-                    if let Some(guild_id) = modal.guild_id.map(|g| g.0 as i64) {
-                        // custom_idからメッセージIDを取得
-                        let message_id_str = &modal.data.custom_id[9..]; // "reg_resp:"の後
-                        if let Ok(message_id) = message_id_str.parse::<u64>() {
-                            let message_id = serenity::model::id::MessageId(message_id);
-                            // メッセージを再取得
-                            if let Ok(message) = modal.channel_id.message(&ctx.http, message_id).await {
-                                if let Some(action_row) = modal.data.components.get(0) {
-                                    if let Some(component) = action_row.components.get(0) {
-                                        if let ActionRowComponent::InputText(input) = component {
-                                            let command_name = &input.value;
-                                            
-                                            // メッセージ内容を構築（添付ファイルがある場合はURLを追加）
-                                            let mut response_content = message.content.clone();
-                                            
-                                            // 添付ファイルがある場合はURLを追加
-                                            if !message.attachments.is_empty() {
-                                                for attachment in &message.attachments {
-                                                    // すべての添付ファイルを追加（画像のみにしたい場合は下の行のコメントアウトを外す）
-                                                    // if attachment.content_type.as_ref().map_or(false, |ct| ct.starts_with("image/")) {
-                                                        if !response_content.is_empty() {
-                                                            response_content.push('\n');
-                                                        }
-                                                        response_content.push_str(&attachment.url);
-                                                    // }
-                                                }
-                                            }
-                                            
-                                            // メッセージ内容をコマンドの返答として登録
-                                            let ok = commands::add_command(&self.pool, guild_id, command_name, &response_content).await;
-                                            let reply = if ok {
-                                                format!("メッセージの内容をコマンド '{}' の返答として登録しました！", command_name)
-                                            } else {
-                                                "登録に失敗しました。同じ名前のコマンドが既に存在するかもしれません。".to_string()
-                                            };
-                                            let _ = modal.create_interaction_response(&ctx.http, |r| {
-                                                r.interaction_response_data(|d| {
-                                                    d.content(reply)
-                                                })
-                                            }).await;
-                                        }
-                                    }
-                                }
-                            } else {
-                                let _ = modal.create_interaction_response(&ctx.http, |r| {
-                                    r.interaction_response_data(|d| {
-                                        d.content("メッセージの取得に失敗しました。")
-                                    })
-                                }).await;
-                            }
-                        }
-                    }
-                }
+                let values = modal
+                    .data
+                    .components
+                    .iter()
+                    .flat_map(|row| row.components.iter())
+                    .filter_map(|c| match c {
+                        ActionRowComponent::InputText(input) => Some((input.custom_id.clone(), input.value.clone())),
+                        _ => None,
+                    })
+                    .collect();
+                let submission = dispatch::ModalSubmission {
+                    guild_id: modal.guild_id.map(|g| g.0 as i64),
+                    custom_id: modal.data.custom_id.clone(),
+                    values,
+                    user_id: modal.user.id.0 as i64,
+                };
+                let span = interaction_span("modal", modal.guild_id, modal.channel_id, modal.user.id.0, &modal.data.custom_id);
+                self.dispatcher
+                    .modal(&self.outbound(&ctx, Source::Modal(&modal)), submission)
+                    .instrument(span)
+                    .await;
             },
             _ => {}
         }
@@ -327,66 +542,344 @@ impl EventHandler for Handler {
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
-    let token = std::env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
-    let database_url = std::env::var("DATABASE_URL").expect("Expected a database url in the environment");
-    let web_bind = std::env::var("WEB_BIND").unwrap_or_else(|_| "0.0.0.0:3000".to_string());
-    let discord_client_id = std::env::var("DISCORD_CLIENT_ID").expect("DISCORD_CLIENT_ID not set");
-    let discord_client_secret = std::env::var("DISCORD_CLIENT_SECRET").expect("DISCORD_CLIENT_SECRET not set");
-    let discord_redirect_uri = std::env::var("DISCORD_REDIRECT_URI").unwrap_or_else(|_| "http://localhost:3000/oauth/callback".to_string());
-    let session_secret = std::env::var("SESSION_SECRET").unwrap_or_else(|_| "dev-only-change-me".to_string());
-    
-    // DB接続とマイグレーション実行
-    let pool = PgPool::connect(&database_url).await.expect("DB接続失敗");
-    
-    // マイグレーション実行
-    println!("Running database migrations...");
-    if let Err(e) = sqlx::migrate!("./migrations").run(&pool).await {
-        eprintln!("Migration failed: {}", e);
-        std::process::exit(1);
-    }
-    println!("Migrations completed successfully!");
-    
-    let pool = Arc::new(pool);
-    let handler = Handler { pool: pool.clone() };
-    let intents = GatewayIntents::all();
-    let mut client = Client::builder(&token, intents)
-        .event_handler(handler)
-        .await
-        .expect("Error creating client");
+    let cli = cli::Cli::parse();
+    telemetry::init();
+
+    // サブコマンドを省略した場合は serve
+    let command = cli.command.unwrap_or(cli::Command::Serve);
+    let config = match config::Config::load(cli.config.as_deref(), command.uses()) {
+        Ok(config) => config,
+        Err(errors) => {
+            eprintln!("設定に誤りがあります:");
+            for e in errors {
+                eprintln!("  - {}", e);
+            }
+            std::process::exit(2);
+        }
+    };
+    if cli.check_config {
+        println!("{}", config.summary());
+        println!("設定に問題はありません。");
+        return;
+    }
+    if config.dev {
+        tracing::warn!("running in dev mode; do not use this configuration in production");
+    }
+
+    let code = match command {
+        cli::Command::Serve => serve(config, true, true).await,
+        cli::Command::BotOnly => serve(config, true, false).await,
+        cli::Command::WebOnly => serve(config, false, true).await,
+        command => cli::run(command, &config).await,
+    };
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+// Bot (Gateway) と Web UI を起動し、終了するまで待つ。戻り値は終了コード
+// bot = false では Gateway に接続せず、web = false では Web UI を公開しない (死活監視とメトリクスは残す)
+async fn serve(config: config::Config, bot: bool, web: bool) -> i32 {
+    // DB接続とマイグレーション実行 (DATABASE_URL のスキームで Postgres/SQLite/メモリを切り替える)
+    let store = match store::connect(&config.database.url).await {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return 1;
+        }
+    };
+    let shutdown = shutdown::Shutdown::new();
+    // Bot と Web の間の通知 (Postgres なら別プロセス間でも届く)
+    let events = match events::EventBus::connect(&config.database.url).await {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return 1;
+        }
+    };
+    let split = !(bot && web);
+    if split && !events.is_distributed() {
+        tracing::warn!("bot and web run in separate processes but the database is not Postgres; changes will not be propagated between them");
+    }
+    events.listen(shutdown.clone());
+    let registration_queue = registration::RegistrationQueue::start(store.clone(), shutdown.clone());
+    // validate() 済みなので失敗しない
+    let sharding = config.sharding().unwrap_or(config::Sharding::Auto);
+    let mut degraded = Vec::new();
+    let client = if bot {
+        // 許可されていない特権インテントを外し、そのインテントが必要な機能は止める
+        // validate() 済みなので失敗しない
+        let requested = config.intents().unwrap_or_else(|_| GatewayIntents::non_privileged());
+        let granted = match intents::granted_privileged(&Http::new(&config.discord.token)).await {
+            Ok(granted) => Some(granted),
+            Err(e) => {
+                tracing::warn!(error = %e, "could not check privileged intents; requesting configured intents as is");
+                None
+            }
+        };
+        let resolved = intents::resolve(requested, granted, &config.features);
+        if !resolved.missing.is_empty() {
+            tracing::warn!(
+                missing = ?resolved.missing,
+                degraded = ?resolved.degraded,
+                "privileged intents are not enabled in the developer portal; connecting without them"
+            );
+        }
+        degraded = resolved.degraded.iter().map(|f| f.to_string()).collect();
+        let handler = Handler {
+            store: store.clone(),
+            dispatcher: dispatch::Dispatcher::new(store.clone(), config.list_session_ttl(), config.admin.owner_ids.clone()),
+            registration: registration_queue.clone(),
+            shutdown: shutdown.clone(),
+            text_commands: config.features.text_commands && !resolved.degraded.contains(&"text_commands"),
+            greetings: config.features.greetings && !resolved.degraded.contains(&"greetings"),
+            events: events.clone(),
+            guild_retention: config.guild_retention(),
+        };
+        tracing::info!(sharding = ?sharding, intents = ?resolved.intents, "starting gateway client");
+        let client = Client::builder(&config.discord.token, resolved.intents)
+            .event_handler(handler)
+            .await
+            .expect("Error creating client");
+        Some(client)
+    } else {
+        None
+    };
+
+    // Gateway に接続しない場合はチャンネル・ロールを REST API で取得する
+    let (guilds, http, health) = match &client {
+        Some(client) => (
+            web::guilds::GuildDirectory::gateway(client.cache_and_http.cache.clone()),
+            client.cache_and_http.http.clone(),
+            Arc::new(health::Health::new()),
+        ),
+        None => match cli::discord_http(&config.discord.token).await {
+            Ok(http) => (web::guilds::GuildDirectory::rest(http.clone()), http, Arc::new(health::Health::without_gateway())),
+            Err(e) => {
+                tracing::error!("{}", e);
+                return 1;
+            }
+        },
+    };
 
     // Web state 構築
-    let session_key = web::session::derive_key_from_env(&session_secret);
+    health.set_degraded(degraded);
+    let guilds = Arc::new(guilds);
+    tokio::spawn(web::guilds::follow_events(guilds.clone(), events.subscribe()));
+    let session_key = web::session::derive_key_from_env(config.session_secret());
     let state = web::AppState {
-        pool: pool.clone(),
-        discord_client_id,
-        discord_client_secret,
-        discord_redirect_uri,
+        store: store.clone(),
+        guilds,
+        http: http.clone(),
+        discord_client_id: config.oauth.client_id.clone(),
+        discord_client_secret: config.oauth.client_secret.clone(),
+        discord_redirect_uri: config.oauth.redirect_uri.clone(),
+        discord_api_base: web::oauth::DEFAULT_DISCORD_API_BASE.to_string(),
         session_key,
+        health: health.clone(),
+        events: events.clone(),
+        owner_ids: config.admin.owner_ids.clone(),
     };
-    let app: Router = web::build_router(state);
+    // Web UI を無効にしても死活監視のエンドポイントは公開する
+    let mut app: Router = if web && config.features.web { web::build_router(state) } else { web::build_health_router(state) };
+    // 管理用ポートが指定されていなければ /metrics も Web と同じポートで公開する
+    let metrics_bind = config.web.metrics_bind.clone().filter(|_| config.features.metrics);
+    if config.features.metrics && metrics_bind.is_none() {
+        app = app.merge(web::metrics_router());
+    }
+    let web_bind = config.web.bind.clone();
+    let shutdown_grace = config.shutdown_grace();
 
-    let mut set = JoinSet::new();
-    // Discord Bot
-    set.spawn(async move {
-        if let Err(why) = client.start().await {
-            eprintln!("Client error: {:?}", why);
+    // SIGTERM/Ctrl+C で終了処理を始める
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            tracing::info!("shutdown requested");
+            shutdown.trigger();
         }
     });
+    // 各タスクは終了理由を返す。Err は回復できない失敗
+    let mut set: JoinSet<Result<(), String>> = JoinSet::new();
+    // Discord Bot
+    if let Some(client) = client {
+        // 終了処理が始まったら全シャードの Gateway セッションを閉じる (client.start() が戻る)
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+            let shard_manager = client.shard_manager.clone();
+            async move {
+                shutdown.wait().await;
+                shard_manager.lock().await.shutdown_all().await;
+            }
+        });
+        // Web・CLI でのコマンド変更をスラッシュコマンドに反映する
+        tokio::spawn(registration::follow_events(registration_queue.clone(), http.clone(), store.clone(), events.subscribe()));
+        retention::start(store.clone(), events.clone(), shutdown.clone());
+        // 予約投稿を送る
+        scheduler::start(store.clone(), http.clone(), shutdown.clone());
+        tokio::spawn(monitor_gateway(client.shard_manager.clone(), client.cache_and_http.cache.clone(), store.clone(), health.clone(), shutdown.clone()));
+        set.spawn(run_bot(client, sharding, health, shutdown.clone()));
+        // リマインダーを送る (終了処理が始まるまで動き続ける)
+        set.spawn(reminders::run(store.clone(), http.clone(), shutdown.clone()));
+    }
     // Web server
-    set.spawn(async move {
-        use axum::routing::get;
-        use axum::http::StatusCode;
-        let listener = tokio::net::TcpListener::bind(&web_bind).await.expect("bind web");
-        println!("Web listening on http://{}", web_bind);
-        axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_signal())
-            .await
-            .map_err(|e| eprintln!("web server error: {e}"))
-            .ok();
+    set.spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            let listener = tokio::net::TcpListener::bind(&web_bind).await.map_err(|e| format!("bind web {}: {}", web_bind, e))?;
+            tracing::info!("Web listening on http://{}", web_bind);
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async move { shutdown.wait().await })
+                .await
+                .map_err(|e| format!("web server error: {}", e))
+        }
     });
 
-    // プロセスを維持
-    while let Some(_res) = set.join_next().await {}
+    // メトリクス (管理用ポート)
+    if let Some(metrics_bind) = metrics_bind {
+        let shutdown = shutdown.clone();
+        set.spawn(async move {
+            let listener = tokio::net::TcpListener::bind(&metrics_bind).await.map_err(|e| format!("bind metrics {}: {}", metrics_bind, e))?;
+            tracing::info!("Metrics listening on http://{}/metrics", metrics_bind);
+            axum::serve(listener, web::metrics_router())
+                .with_graceful_shutdown(async move { shutdown.wait().await })
+                .await
+                .map_err(|e| format!("metrics server error: {}", e))
+        });
+    }
+
+    // どれか 1 つでも終了したらプロセスごと終了する
+    // (Bot だけ止まって Web が動き続けると、Docker の再起動ポリシーが働かないため)
+    let failed = match set.join_next().await {
+        Some(Ok(Ok(()))) | None => false,
+        Some(Ok(Err(e))) => {
+            tracing::error!("{}", e);
+            true
+        }
+        Some(Err(e)) => {
+            tracing::error!(error = %e, "subsystem task panicked");
+            true
+        }
+    };
+
+    // 残りのサブシステムを止め、処理中のイベントの完了を待ってから DB を閉じる
+    shutdown.trigger();
+    let drained = tokio::time::timeout(shutdown_grace, async {
+        while let Some(result) = set.join_next().await {
+            if let Ok(Err(e)) = result {
+                tracing::warn!("{}", e);
+            }
+        }
+        shutdown.drained().await;
+        events.close().await;
+        store.close().await;
+    })
+    .await;
+    match drained {
+        Ok(()) => tracing::info!("shutdown complete"),
+        Err(_) => tracing::warn!(grace_secs = shutdown_grace.as_secs(), "shutdown grace period exceeded; exiting anyway"),
+    }
+    if failed { 1 } else { 0 }
+}
+
+// 再接続までの待ち時間 (失敗が続くたびに倍にする)
+const BOT_BACKOFF_MIN: std::time::Duration = std::time::Duration::from_secs(5);
+const BOT_BACKOFF_MAX: std::time::Duration = std::time::Duration::from_secs(300);
+// この時間以上動いてから落ちた場合は待ち時間と試行回数をリセットする
+const BOT_STABLE_AFTER: std::time::Duration = std::time::Duration::from_secs(600);
+// 連続してこの回数失敗したら諦めてプロセスを終了する
+const BOT_MAX_ATTEMPTS: u32 = 10;
+
+// Discord クライアントを動かし、落ちたらバックオフを空けて再接続する
+// トークンやインテントの誤りなど、やり直しても直らないエラーでは Err を返す
+async fn run_bot(mut client: Client, sharding: config::Sharding, health: Arc<health::Health>, shutdown: shutdown::Shutdown) -> Result<(), String> {
+    let mut backoff = BOT_BACKOFF_MIN;
+    let mut attempts = 0;
+    loop {
+        let started = std::time::Instant::now();
+        let started_client = match sharding {
+            // シャード数は再接続のたびに Discord の推奨値を取り直す
+            config::Sharding::Auto => client.start_autosharded().await,
+            config::Sharding::Range { first, last, total } => client.start_shard_range([first, last], total).await,
+        };
+        let why = match started_client {
+            Ok(()) => return Ok(()),
+            // 終了処理でシャードを閉じた場合
+            Err(_) if shutdown.is_triggered() => return Ok(()),
+            Err(why) => why,
+        };
+        if matches!(
+            why,
+            serenity::Error::Gateway(
+                GatewayError::InvalidAuthentication | GatewayError::InvalidGatewayIntents | GatewayError::DisallowedGatewayIntents
+            ) | serenity::Error::Client(ClientError::ShardBootFailure)
+        ) {
+            return Err(format!("discord client failed permanently: {:?}", why));
+        }
+        if started.elapsed() >= BOT_STABLE_AFTER {
+            backoff = BOT_BACKOFF_MIN;
+            attempts = 0;
+        }
+        attempts += 1;
+        if attempts >= BOT_MAX_ATTEMPTS {
+            return Err(format!("discord client failed {} times in a row; last error: {:?}", attempts, why));
+        }
+        health.client_restarting();
+        tracing::warn!(error = ?why, attempt = attempts, retry_in_secs = backoff.as_secs(), "discord client stopped; restarting");
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = shutdown.wait() => return Ok(()),
+        }
+        backoff = (backoff * 2).min(BOT_BACKOFF_MAX);
+    }
+}
+
+// シャードの状態を DB に書き込む間隔 (監視の間隔の何回に 1 回か)
+const SHARD_STATUS_EVERY: u32 = 6;
+
+// シャードの接続状態とハートビートの応答時間を定期的に Health とメトリクスに書き込み、
+// 30 秒ごとに DB にも記録する (別プロセスの Web のダッシュボードで表示する)
+async fn monitor_gateway(shard_manager: Arc<Mutex<ShardManager>>, cache: Arc<serenity::cache::Cache>, store: Arc<dyn CommandStore>, health: Arc<health::Health>, shutdown: shutdown::Shutdown) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
+    let mut ticks = 0u32;
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait() => return,
+        }
+        let runners = shard_manager.lock().await.runners.clone();
+        let runners: Vec<_> = runners.lock().await.iter().map(|(id, runner)| (id.0, runner.stage.to_string(), runner.latency)).collect();
+        // シャード数は READY を受け取るまで分からない
+        let total = cache.shard_count();
+        let mut guilds = std::collections::HashMap::new();
+        if total > 0 {
+            for guild_id in cache.guilds() {
+                *guilds.entry(serenity::utils::shard_id(guild_id, total)).or_insert(0i64) += 1;
+            }
+        }
+        let record = ticks == 0;
+        ticks = (ticks + 1) % SHARD_STATUS_EVERY;
+        for (id, stage, latency) in runners {
+            health.update_shard(id, &stage, latency);
+            let label = id.to_string();
+            let guild_count = guilds.get(&id).copied().unwrap_or(0);
+            metrics::GATEWAY_SHARD_LATENCY.with_label_values(&[&label]).set(latency.map(|l| l.as_secs_f64()).unwrap_or(0.0));
+            metrics::GATEWAY_SHARD_GUILDS.with_label_values(&[&label]).set(guild_count);
+            if record && total > 0 {
+                let status = health::ShardStatus {
+                    shard_id: id as i64,
+                    shard_total: total as i64,
+                    stage,
+                    latency_ms: latency.map(|l| l.as_millis() as i64),
+                    guilds: guild_count,
+                    updated_at: None,
+                };
+                if let Err(e) = store.record_shard_status(&status).await {
+                    e.log();
+                }
+            }
+        }
+    }
 }
 
 // Graceful shutdown: wait for Ctrl+C or SIGTERM
@@ -0,0 +1,100 @@
+// Prometheus 形式のメトリクス
+// 各所から直接カウンタ/ヒストグラムを更新し、/metrics で render() の結果を返す
+use std::time::Instant;
+
+use lazy_static::lazy_static;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+lazy_static! {
+    pub static ref REGISTRY: Registry = Registry::new_custom(Some("nkmzbot".to_string()), None).unwrap();
+
+    // カスタムコマンドの呼び出し (source: text|slash, result: hit|miss|out_of_scope)
+    pub static ref COMMAND_DISPATCHES: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("command_dispatches_total", "Custom command lookups by source and result"),
+        &["source", "result"],
+    ));
+
+    // 受信したインタラクション (kind: command|component|modal)
+    pub static ref INTERACTIONS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("interactions_total", "Interactions received by kind"),
+        &["kind"],
+    ));
+
+    // ストアの操作ごとの所要時間 (backend: postgres|sqlite)
+    pub static ref DB_QUERY_SECONDS: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("db_query_duration_seconds", "Command store operation latency"),
+        &["backend", "operation"],
+    ));
+
+    // Discord API の呼び出し時間とエラー
+    pub static ref DISCORD_API_SECONDS: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new("discord_api_duration_seconds", "Discord API request latency"),
+        &["endpoint"],
+    ));
+    pub static ref DISCORD_API_ERRORS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("discord_api_errors_total", "Failed Discord API requests"),
+        &["endpoint"],
+    ));
+
+    // シャードごとの接続状態 (接続中なら 1)
+    pub static ref GATEWAY_SHARD_CONNECTED: IntGaugeVec = register(IntGaugeVec::new(
+        Opts::new("gateway_shard_connected", "Whether the gateway shard is connected"),
+        &["shard"],
+    ));
+    // シャードごとのハートビートの応答時間とギルド数
+    pub static ref GATEWAY_SHARD_LATENCY: GaugeVec = register(GaugeVec::new(
+        Opts::new("gateway_shard_latency_seconds", "Gateway heartbeat latency per shard"),
+        &["shard"],
+    ));
+    pub static ref GATEWAY_SHARD_GUILDS: IntGaugeVec = register(IntGaugeVec::new(
+        Opts::new("gateway_shard_guilds", "Guilds handled by each shard"),
+        &["shard"],
+    ));
+
+    // スラッシュコマンドの登録結果 (result: registered|unchanged|failed)
+    pub static ref REGISTRATIONS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("command_registrations_total", "Guild command registrations by result"),
+        &["result"],
+    ));
+    // 予約投稿の送信結果 (result: sent|failed)
+    pub static ref SCHEDULED_MESSAGES: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("scheduled_messages_total", "Scheduled messages by result"),
+        &["result"],
+    ));
+    // リマインダーの送信結果 (result: sent|failed)
+    pub static ref REMINDERS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("reminders_total", "Reminder deliveries by result"),
+        &["result"],
+    ));
+    // 入退室メッセージの送信と自動ロールの付与の結果 (kind: welcome|farewell|auto_role, result: sent|failed)
+    pub static ref GREETINGS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("greetings_total", "Welcome/farewell messages and auto roles by result"),
+        &["kind", "result"],
+    ));
+}
+
+fn register<M: prometheus::core::Collector + Clone + 'static>(metric: prometheus::Result<M>) -> M {
+    let metric = metric.unwrap();
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+// Discord API の呼び出しを計測する。Err の場合はエラー数も数える
+pub async fn time_discord<T, E>(endpoint: &str, fut: impl std::future::Future<Output = Result<T, E>>) -> Result<T, E> {
+    let start = Instant::now();
+    let result = fut.await;
+    DISCORD_API_SECONDS.with_label_values(&[endpoint]).observe(start.elapsed().as_secs_f64());
+    if result.is_err() {
+        DISCORD_API_ERRORS.with_label_values(&[endpoint]).inc();
+    }
+    result
+}
+
+// テキスト形式 (text/plain; version=0.0.4) で全メトリクスを書き出す
+pub fn render() -> String {
+    let mut buf = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
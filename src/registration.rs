@@ -0,0 +1,632 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serenity::builder::CreateApplicationCommands;
+use serenity::http::Http;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::command::CommandType;
+use serenity::model::channel::ChannelType;
+use serenity::model::id::GuildId;
+use serenity::model::Permissions;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::commands;
+use crate::events::Event;
+use crate::metrics;
+use crate::shutdown::Shutdown;
+use crate::store::CommandStore;
+
+// Discord のギルドあたりのスラッシュコマンド上限
+pub const MAX_SLASH_COMMANDS: usize = 100;
+
+// 管理用スラッシュコマンド (カスタムコマンドはこれらと同名にできない)
+pub const MANAGEMENT_COMMANDS: [&str; 12] = ["add", "remove", "update", "list", "scope", "style", "slash", "schedule", "remind", "reminders", "purge", "mydata"];
+
+// カスタムコマンドとして公開できるスラッシュコマンドの数
+pub const MAX_CUSTOM_SLASH_COMMANDS: usize = MAX_SLASH_COMMANDS - MANAGEMENT_COMMANDS.len();
+
+// スラッシュコマンドの説明が未設定の場合に使う文言
+pub const DEFAULT_DESCRIPTION: &str = "カスタムコマンド";
+
+// スラッシュコマンド名/引数名として使えるか検証する (1〜32 文字、小文字英数字と - _ のみ)
+pub fn validate_slash_name(name: &str) -> Result<(), String> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c == '-' || c == '_' || (c.is_alphanumeric() && !c.is_uppercase()));
+    if name.is_empty() || name.chars().count() > 32 || !valid_chars {
+        return Err(format!(
+            "'{}' はスラッシュコマンド名として使えません (小文字の英数字・-・_ で 32 文字まで)。",
+            name
+        ));
+    }
+    Ok(())
+}
+
+// スラッシュコマンドとしての公開設定を検証する
+pub async fn validate_slash_options(store: &dyn CommandStore, guild_id: i64, name: &str, options: &commands::SlashOptions) -> Result<(), String> {
+    if !options.enabled {
+        return Ok(());
+    }
+    validate_slash_name(name)?;
+    if MANAGEMENT_COMMANDS.contains(&name) {
+        return Err(format!("'{}' は管理用コマンドと同じ名前のため公開できません。", name));
+    }
+    if options.description.as_deref().map(|d| d.chars().count() > 100).unwrap_or(false) {
+        return Err("説明は 100 文字以内で指定してください。".to_string());
+    }
+    if options.arguments.len() > 25 {
+        return Err("引数は 25 個までです。".to_string());
+    }
+    for (i, arg) in options.arguments.iter().enumerate() {
+        validate_slash_name(arg)?;
+        if options.arguments[..i].contains(arg) {
+            return Err(format!("引数 '{}' が重複しています。", arg));
+        }
+    }
+    let published = store.list_slash_commands(guild_id).await.map_err(|e| e.report())?;
+    let others = published.iter().filter(|c| c.name != name).count();
+    if others >= MAX_CUSTOM_SLASH_COMMANDS {
+        return Err(format!(
+            "スラッシュコマンドとして公開できるのは {} 個までです (Discord の上限 {} 個のうち {} 個は管理用コマンドです)。",
+            MAX_CUSTOM_SLASH_COMMANDS,
+            MAX_SLASH_COMMANDS,
+            MANAGEMENT_COMMANDS.len()
+        ));
+    }
+    Ok(())
+}
+
+// 引数名の指定 ("a b" や "a, b") をリストにする
+pub fn parse_arguments(s: &str) -> Vec<String> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.to_string())
+        .collect()
+}
+
+fn management_commands(commands: &mut CreateApplicationCommands) -> &mut CreateApplicationCommands {
+    commands
+        .create_application_command(|command| {
+            command
+                .name("add")
+                .description("新しいコマンドを追加します")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("name")
+                        .description("コマンド名")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_option(|option| {
+                    option
+                        .name("response")
+                        .description("返答内容 (省略すると複数行で入力できるフォームを開きます)")
+                        .kind(CommandOptionType::String)
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("remove")
+                .description("コマンドを削除します")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("name")
+                        .description("削除するコマンド名")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("update")
+                .description("コマンドを更新します")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("name")
+                        .description("更新するコマンド名")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_option(|option| {
+                    option
+                        .name("response")
+                        .description("新しい返答内容 (省略すると現在の内容を編集するフォームを開きます)")
+                        .kind(CommandOptionType::String)
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("list")
+                .description("登録されているコマンド一覧を表示します")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("filter")
+                        .description("コマンド名または返答に含まれる文字列で絞り込み")
+                        .kind(CommandOptionType::String)
+                })
+                .create_option(|option| {
+                    option
+                        .name("sort")
+                        .description("並び順")
+                        .kind(CommandOptionType::String)
+                        .add_string_choice("名前順", "name")
+                        .add_string_choice("新しい順", "newest")
+                        .add_string_choice("古い順", "oldest")
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("scope")
+                .description("コマンドを使えるチャンネル/カテゴリ/ロールを設定します")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("set")
+                        .description("許可/拒否リストに追加します")
+                        .kind(CommandOptionType::SubCommand)
+                        .create_sub_option(|o| {
+                            o.name("name").description("コマンド名").kind(CommandOptionType::String).required(true)
+                        })
+                        .create_sub_option(|o| {
+                            o.name("mode")
+                                .description("許可 (allow) または拒否 (deny)")
+                                .kind(CommandOptionType::String)
+                                .required(true)
+                                .add_string_choice("allow", "allow")
+                                .add_string_choice("deny", "deny")
+                        })
+                        .create_sub_option(|o| {
+                            o.name("channel")
+                                .description("対象のチャンネルまたはカテゴリ")
+                                .kind(CommandOptionType::Channel)
+                                .channel_types(&[ChannelType::Text, ChannelType::News, ChannelType::Category])
+                        })
+                        .create_sub_option(|o| {
+                            o.name("role").description("対象のロール").kind(CommandOptionType::Role)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("unset")
+                        .description("許可/拒否リストから外します")
+                        .kind(CommandOptionType::SubCommand)
+                        .create_sub_option(|o| {
+                            o.name("name").description("コマンド名").kind(CommandOptionType::String).required(true)
+                        })
+                        .create_sub_option(|o| {
+                            o.name("channel")
+                                .description("対象のチャンネルまたはカテゴリ")
+                                .kind(CommandOptionType::Channel)
+                                .channel_types(&[ChannelType::Text, ChannelType::News, ChannelType::Category])
+                        })
+                        .create_sub_option(|o| {
+                            o.name("role").description("対象のロール").kind(CommandOptionType::Role)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("clear")
+                        .description("スコープ設定をすべて削除します")
+                        .kind(CommandOptionType::SubCommand)
+                        .create_sub_option(|o| {
+                            o.name("name").description("コマンド名").kind(CommandOptionType::String).required(true)
+                        })
+                })
+                .create_option(|option| {
+                    option
+                        .name("show")
+                        .description("現在のスコープ設定を表示します")
+                        .kind(CommandOptionType::SubCommand)
+                        .create_sub_option(|o| {
+                            o.name("name").description("コマンド名").kind(CommandOptionType::String).required(true)
+                        })
+                })
+        })
+        .create_application_command(|command| {
+            // 返答の送り先チャンネルを変えられるため、サーバー管理の権限を持つメンバーに限る
+            command
+                .name("style")
+                .description("コマンドの返答の送り方を設定します")
+                .dm_permission(false)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .create_option(|option| {
+                    option
+                        .name("name")
+                        .description("コマンド名")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_option(|option| {
+                    let option = option
+                        .name("mode")
+                        .description("返答の送り方")
+                        .kind(CommandOptionType::String)
+                        .required(true);
+                    for mode in commands::ReplyMode::ALL {
+                        option.add_string_choice(mode.label(), mode.as_str());
+                    }
+                    option
+                })
+                .create_option(|option| {
+                    option
+                        .name("channel")
+                        .description("送信先チャンネル (指定チャンネルに送信する場合)")
+                        .kind(CommandOptionType::Channel)
+                        .channel_types(&[ChannelType::Text, ChannelType::News])
+                })
+                .create_option(|option| {
+                    option
+                        .name("delete_trigger")
+                        .description("実行したメッセージを削除する")
+                        .kind(CommandOptionType::Boolean)
+                })
+                .create_option(|option| {
+                    option
+                        .name("delete_after")
+                        .description("返答を自動削除するまでの秒数 (0 で無効)")
+                        .kind(CommandOptionType::Integer)
+                        .min_int_value(0)
+                        .max_int_value(crate::commands::MAX_DELETE_AFTER_SECS)
+                })
+        })
+        .create_application_command(|command| {
+            // ギルドのコマンド一覧を書き換えるため、サーバー管理の権限を持つメンバーに限る
+            command
+                .name("slash")
+                .description("コマンドをスラッシュコマンドとして公開します")
+                .dm_permission(false)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .create_option(|option| {
+                    option
+                        .name("name")
+                        .description("コマンド名")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_option(|option| {
+                    option
+                        .name("enabled")
+                        .description("スラッシュコマンドとして公開する")
+                        .kind(CommandOptionType::Boolean)
+                        .required(true)
+                })
+                .create_option(|option| {
+                    option
+                        .name("description")
+                        .description("スラッシュコマンドの説明 (100 文字まで)")
+                        .kind(CommandOptionType::String)
+                })
+                .create_option(|option| {
+                    option
+                        .name("arguments")
+                        .description("引数名をスペース区切りで指定 (返答中の {引数名} が置き換わります)")
+                        .kind(CommandOptionType::String)
+                })
+        })
+        .create_application_command(|command| {
+            // Bot の権限で任意のチャンネルに投稿できるため、サーバー管理の権限を持つメンバーに限る
+            command
+                .name("schedule")
+                .description("チャンネルへの予約投稿を設定します")
+                .dm_permission(false)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .create_option(|option| {
+                    option
+                        .name("add")
+                        .description("予約を追加します (送る内容かコマンドのどちらかを指定)")
+                        .kind(CommandOptionType::SubCommand)
+                        .create_sub_option(|o| {
+                            o.name("channel")
+                                .description("送信先チャンネル")
+                                .kind(CommandOptionType::Channel)
+                                .channel_types(&[ChannelType::Text, ChannelType::News])
+                                .required(true)
+                        })
+                        .create_sub_option(|o| {
+                            o.name("when")
+                                .description("日時 (例: 2025-01-31 09:00) または cron 式 (例: 0 9 * * 1-5)")
+                                .kind(CommandOptionType::String)
+                                .required(true)
+                        })
+                        .create_sub_option(|o| {
+                            o.name("content").description("送る内容").kind(CommandOptionType::String)
+                        })
+                        .create_sub_option(|o| {
+                            o.name("command").description("返答を送るカスタムコマンド名").kind(CommandOptionType::String)
+                        })
+                        .create_sub_option(|o| {
+                            o.name("timezone")
+                                .description("タイムゾーン (例: Asia/Tokyo, +09:00。省略時は UTC)")
+                                .kind(CommandOptionType::String)
+                        })
+                })
+                .create_option(|option| {
+                    option.name("list").description("予約の一覧を表示します").kind(CommandOptionType::SubCommand)
+                })
+                .create_option(|option| {
+                    option
+                        .name("remove")
+                        .description("予約を削除します")
+                        .kind(CommandOptionType::SubCommand)
+                        .create_sub_option(|o| {
+                            o.name("id").description("予約の ID (/schedule list で確認)").kind(CommandOptionType::Integer).required(true)
+                        })
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("remind")
+                .description("指定した日時にお知らせします")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("when")
+                        .description("日時 (例: in 2h, 30分後, tomorrow 9:00, 2025-01-31 09:00)")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_option(|option| {
+                    option
+                        .name("text")
+                        .description("お知らせする内容")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+                .create_option(|option| {
+                    option
+                        .name("dm")
+                        .description("このチャンネルではなく DM で知らせる")
+                        .kind(CommandOptionType::Boolean)
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("reminders")
+                .description("あなたのリマインダーの一覧を表示・取り消します")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("timezone")
+                        .description("日時の解釈に使うタイムゾーンを設定します (例: Asia/Tokyo, +09:00)")
+                        .kind(CommandOptionType::String)
+                })
+        })
+        .create_application_command(|command| {
+            // Bot のオーナー専用 (実行時に確認する)。一般のメンバーには表示しない
+            command
+                .name("purge")
+                .description("ギルドのデータをすぐに削除します (Bot のオーナー専用)")
+                .dm_permission(false)
+                .default_member_permissions(Permissions::ADMINISTRATOR)
+                .create_option(|option| {
+                    option
+                        .name("guild")
+                        .description("データを削除するギルドの ID")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("mydata")
+                .description("あなたについて記録しているデータを書き出し・削除します")
+                .dm_permission(false)
+                .create_option(|option| {
+                    option
+                        .name("action")
+                        .description("操作")
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                        .add_string_choice("書き出す", "export")
+                        .add_string_choice("削除する", "delete")
+                })
+        })
+        .create_application_command(|command| {
+            command
+                .name("Register as Response")
+                .kind(CommandType::Message)
+        })
+}
+
+// ギルドに登録するコマンド一式 (管理用 + 公開設定されたカスタムコマンド) を組み立てる
+async fn desired_commands(store: &dyn CommandStore, guild_id: GuildId) -> Result<CreateApplicationCommands, commands::CommandError> {
+    let mut custom = store.list_slash_commands(guild_id.0 as i64).await?;
+    if custom.len() > MAX_CUSTOM_SLASH_COMMANDS {
+        tracing::warn!(
+            guild_id = guild_id.0,
+            count = custom.len(),
+            limit = MAX_CUSTOM_SLASH_COMMANDS,
+            "too many slash-enabled commands; only the first ones will be registered"
+        );
+        custom.truncate(MAX_CUSTOM_SLASH_COMMANDS);
+    }
+
+    let mut commands = CreateApplicationCommands::default();
+    management_commands(&mut commands);
+    for c in &custom {
+        commands.create_application_command(|command| {
+            command
+                .name(&c.name)
+                .description(c.description.as_deref().filter(|d| !d.is_empty()).unwrap_or(DEFAULT_DESCRIPTION))
+                .dm_permission(false);
+            for arg in &c.arguments {
+                command.create_option(|option| {
+                    option
+                        .name(arg)
+                        .description(arg)
+                        .kind(CommandOptionType::String)
+                        .required(true)
+                });
+            }
+            command
+        });
+    }
+    Ok(commands)
+}
+
+// コマンド一式のハッシュ (JSON のキーは順序が固定されるので安定する)
+fn commands_hash(commands: &CreateApplicationCommands) -> String {
+    use sha2::{Digest, Sha256};
+    let json = serde_json::to_vec(&commands.0).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&json))
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct RegistrationStatus {
+    pub guild_id: i64,
+    pub command_hash: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Registration {
+    // Discord に登録した
+    Registered,
+    // 前回登録したコマンド一式から変更がないためスキップした
+    Unchanged,
+}
+
+// 管理用コマンドと、公開設定されたカスタムコマンドをギルドに登録する (変更がなければスキップ)
+pub async fn register_guild_commands(http: &Http, store: &dyn CommandStore, guild_id: GuildId) -> Result<Registration, String> {
+    sync_guild_commands(http, store, guild_id, false).await
+}
+
+// force = true の場合はハッシュが一致していても登録し直す
+pub async fn sync_guild_commands(http: &Http, store: &dyn CommandStore, guild_id: GuildId, force: bool) -> Result<Registration, String> {
+    // 公開中のコマンドを読めないまま登録すると、カスタムコマンドが Discord から消えてしまうので登録しない
+    let desired = match desired_commands(store, guild_id).await {
+        Ok(desired) => desired,
+        Err(e) => {
+            e.log();
+            metrics::REGISTRATIONS.with_label_values(&["failed"]).inc();
+            if let Err(e) = store.record_registration(guild_id.0 as i64, None, Some(&e.to_string())).await {
+                e.log();
+            }
+            return Err(format!("スラッシュコマンドの登録に失敗しました: {}", e));
+        }
+    };
+    let hash = commands_hash(&desired);
+    if !force {
+        // 前回の状態を読めない場合は、省略せずに登録し直す
+        match store.get_registration(guild_id.0 as i64).await {
+            Ok(Some(current)) if current.status == "ok" && current.command_hash.as_deref() == Some(hash.as_str()) => {
+                metrics::REGISTRATIONS.with_label_values(&["unchanged"]).inc();
+                return Ok(Registration::Unchanged);
+            }
+            Ok(_) => {}
+            Err(e) => e.log(),
+        }
+    }
+
+    // ギルド内のアプリケーションコマンドを「置き換え」る（重複防止）
+    let result = metrics::time_discord(
+        "set_application_commands",
+        guild_id.set_application_commands(http, |commands| {
+            *commands = desired;
+            commands
+        }),
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            tracing::info!(guild_id = guild_id.0, "registered application commands");
+            metrics::REGISTRATIONS.with_label_values(&["registered"]).inc();
+            if let Err(e) = store.record_registration(guild_id.0 as i64, Some(&hash), None).await {
+                e.log();
+            }
+            Ok(Registration::Registered)
+        }
+        Err(e) => {
+            tracing::warn!(guild_id = guild_id.0, error = ?e, "failed to register application commands");
+            metrics::REGISTRATIONS.with_label_values(&["failed"]).inc();
+            // 失敗時はハッシュを更新しない (次回必ず再登録させる)
+            if let Err(e) = store.record_registration(guild_id.0 as i64, None, Some(&e.to_string())).await {
+                e.log();
+            }
+            Err(format!("スラッシュコマンドの登録に失敗しました: {}", e))
+        }
+    }
+}
+
+// 多数のギルドへの登録を 1 件ずつ間隔を空けて処理するキュー
+// (再接続時に全ギルド分の登録が一斉に走ってレート制限に当たるのを防ぐ)
+#[derive(Clone)]
+pub struct RegistrationQueue {
+    tx: mpsc::UnboundedSender<(Arc<Http>, GuildId)>,
+    pending: Arc<Mutex<HashSet<GuildId>>>,
+}
+
+// Discord API を呼んだ後、次のギルドの登録までに空ける間隔
+const REGISTRATION_INTERVAL: Duration = Duration::from_millis(1500);
+
+impl RegistrationQueue {
+    // 終了処理が始まったら、処理中の 1 件を終えてから止まる (待ち行列に残ったギルドは次回起動時に登録される)
+    pub fn start(store: Arc<dyn CommandStore>, shutdown: Shutdown) -> RegistrationQueue {
+        let (tx, mut rx) = mpsc::unbounded_channel::<(Arc<Http>, GuildId)>();
+        let pending: Arc<Mutex<HashSet<GuildId>>> = Arc::new(Mutex::new(HashSet::new()));
+        let worker_pending = pending.clone();
+        tokio::spawn(async move {
+            loop {
+                let (http, guild_id) = tokio::select! {
+                    job = rx.recv() => match job {
+                        Some(job) => job,
+                        None => break,
+                    },
+                    _ = shutdown.wait() => break,
+                };
+                let Some(in_flight) = shutdown.track() else { break };
+                worker_pending.lock().unwrap().remove(&guild_id);
+                // 変更なしでスキップした場合は API を呼んでいないので待たない
+                if let Ok(Registration::Unchanged) = register_guild_commands(&http, store.as_ref(), guild_id).await {
+                    continue;
+                }
+                drop(in_flight);
+                tokio::select! {
+                    _ = tokio::time::sleep(REGISTRATION_INTERVAL) => {}
+                    _ = shutdown.wait() => break,
+                }
+            }
+        });
+        RegistrationQueue { tx, pending }
+    }
+
+    // 登録待ちに追加する (既に待ち行列にあるギルドは追加しない)
+    pub fn enqueue(&self, http: Arc<Http>, guild_id: GuildId) {
+        if !self.pending.lock().unwrap().insert(guild_id) {
+            return;
+        }
+        if self.tx.send((http, guild_id)).is_err() {
+            tracing::error!(guild_id = guild_id.0, "registration queue is closed; dropping guild");
+        }
+    }
+}
+
+// Web・CLI からのコマンド変更の通知を受けて、該当ギルドを登録待ちに追加する
+// 通知を取りこぼした可能性がある場合は全ギルドを確認する (変更のないギルドはハッシュで省略される)
+pub async fn follow_events(queue: RegistrationQueue, http: Arc<Http>, store: Arc<dyn CommandStore>, mut events: broadcast::Receiver<Event>) {
+    loop {
+        match events.recv().await {
+            Ok(Event::CommandsChanged { guild_id }) => queue.enqueue(http.clone(), GuildId(guild_id as u64)),
+            Ok(Event::Resync) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                match store.list_guild_ids().await {
+                    Ok(guild_ids) => {
+                        for guild_id in guild_ids {
+                            queue.enqueue(http.clone(), GuildId(guild_id as u64));
+                        }
+                    }
+                    Err(e) => e.log(),
+                }
+            }
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+// ユーザ個人のリマインダー (/remind)
+// 指定した日時に、実行したチャンネルまたは DM で本人に知らせる。日時はユーザごとのタイムゾーンで解釈する。
+// 送る前に DB から削除し、削除できたプロセスだけが送るため、複数のプロセスで動かしても二重に送らない
+#[cfg(test)]
+mod tests;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::async_trait;
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::http::Http;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::id::{ChannelId, UserId};
+use time::{OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+use crate::commands::CommandError;
+use crate::list;
+use crate::metrics;
+use crate::scheduler::cron;
+use crate::shutdown::Shutdown;
+use crate::store::CommandStore;
+
+// 期限の来たリマインダーを確認する間隔
+const TICK: Duration = Duration::from_secs(15);
+// ユーザあたりのリマインダーの上限 (/reminders のボタンの数の上限でもある)
+pub const MAX_REMINDERS_PER_USER: usize = 25;
+// どれだけ先まで指定できるか
+const MAX_AHEAD_SECS: i64 = 366 * 24 * 60 * 60;
+// 「明日」だけで時刻を省略した場合
+const DEFAULT_TIME: (u8, u8) = (9, 0);
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Reminder {
+    pub id: i64,
+    pub user_id: i64,
+    // 作成したギルド
+    pub guild_id: Option<i64>,
+    // 知らせるチャンネル。None なら DM で知らせる
+    pub channel_id: Option<i64>,
+    pub content: String,
+    // 知らせる日時 (UNIX 秒)
+    pub remind_at: i64,
+}
+
+// ユーザのタイムゾーン (未設定なら UTC)
+pub async fn user_timezone(store: &dyn CommandStore, user_id: i64) -> Result<(String, UtcOffset), CommandError> {
+    if let Some(name) = store.get_user_timezone(user_id).await? {
+        if let Ok(offset) = cron::parse_timezone(&name) {
+            return Ok((name, offset));
+        }
+    }
+    Ok((crate::scheduler::DEFAULT_TIMEZONE.to_string(), UtcOffset::UTC))
+}
+
+// タイムゾーンを検証して保存する
+pub async fn set_timezone(store: &dyn CommandStore, user_id: i64, name: &str) -> Result<(), String> {
+    let name = name.trim();
+    cron::parse_timezone(name)?;
+    store.set_user_timezone(user_id, name).await.map_err(|e| e.report())
+}
+
+// "in 2h" / "2時間30分後" のような相対時間、"tomorrow 9:00" / "明日 9:00" / "18:30"、"2025-01-31 09:00" を UNIX 秒にする
+pub fn parse_when(input: &str, offset: UtcOffset, now: i64) -> Result<i64, String> {
+    let invalid = || format!("日時 {:?} を解釈できません。「in 2h」「30分後」「tomorrow 9:00」「2025-01-31 09:00」のように指定してください。", input.trim());
+    let at = match cron::parse_at(input, offset) {
+        Some(at) => at,
+        None => {
+            let s = input.trim().to_lowercase();
+            let relative = s.strip_prefix("in ").unwrap_or(&s).trim_end_matches('後').trim();
+            match parse_duration(relative) {
+                Some(secs) => now + secs,
+                None => parse_day_and_time(&s, offset, now).ok_or_else(invalid)?,
+            }
+        }
+    };
+    if at <= now {
+        return Err("過去の日時は指定できません。".to_string());
+    }
+    if at - now > MAX_AHEAD_SECS {
+        return Err("1 年以上先の日時は指定できません。".to_string());
+    }
+    Ok(at)
+}
+
+// "2h30m"、"2 hours 30 minutes"、"90分"、"1日" など
+fn parse_duration(s: &str) -> Option<i64> {
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut total: i64 = 0;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            return None;
+        }
+        let n: i64 = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+        let unit_len = rest.find(|c: char| c.is_ascii_digit() || c.is_whitespace()).unwrap_or(rest.len());
+        let unit_secs = match &rest[..unit_len] {
+            "m" | "min" | "mins" | "minute" | "minutes" | "分" => 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" | "時間" => 60 * 60,
+            "d" | "day" | "days" | "日" => 24 * 60 * 60,
+            "w" | "week" | "weeks" | "週間" => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        total = total.checked_add(n.checked_mul(unit_secs)?)?;
+        rest = rest[unit_len..].trim_start();
+    }
+    Some(total)
+}
+
+// "today 18:00" / "tomorrow" / "明日 9:00" / "9:00" (時刻だけなら次にその時刻になる日)
+fn parse_day_and_time(s: &str, offset: UtcOffset, now: i64) -> Option<i64> {
+    let (days, rest) = [("tomorrow", 1), ("明日", 1), ("today", 0), ("今日", 0)]
+        .iter()
+        .find_map(|(word, days)| s.strip_prefix(word).map(|rest| (Some(*days), rest.trim())))
+        .unwrap_or((None, s));
+    let (hour, minute) = match rest.split_once(':') {
+        Some((h, m)) => (h.trim().parse().ok()?, m.trim().parse().ok()?),
+        None if rest.is_empty() && days == Some(1) => DEFAULT_TIME,
+        None => return None,
+    };
+    let time = Time::from_hms(hour, minute, 0).ok()?;
+    let today = OffsetDateTime::from_unix_timestamp(now).ok()?.to_offset(offset).date();
+    let at = |days: i64| Some(PrimitiveDateTime::new(today.checked_add(time::Duration::days(days))?, time).assume_offset(offset).unix_timestamp());
+    match days {
+        Some(days) => at(days),
+        None => at(0).filter(|t| *t > now).or_else(|| at(1)),
+    }
+}
+
+// /remind や Web から受け取ったリマインダーの内容
+pub struct NewReminder<'a> {
+    pub user_id: i64,
+    pub guild_id: Option<i64>,
+    // None なら DM で知らせる
+    pub channel_id: Option<i64>,
+    pub when: &'a str,
+    pub content: &'a str,
+}
+
+// 入力を検証してリマインダーを保存する
+pub async fn create(store: &dyn CommandStore, new: NewReminder<'_>, now: i64) -> Result<Reminder, String> {
+    let content = new.content.trim();
+    if content.is_empty() {
+        return Err("内容を指定してください。".to_string());
+    }
+    if content.chars().count() > 1000 {
+        return Err("内容は 1000 文字以内で指定してください。".to_string());
+    }
+    let (_, offset) = user_timezone(store, new.user_id).await.map_err(|e| e.report())?;
+    let remind_at = parse_when(new.when, offset, now)?;
+    if store.list_reminders(new.user_id).await.map_err(|e| e.report())?.len() >= MAX_REMINDERS_PER_USER {
+        return Err(format!("リマインダーは 1 人あたり {} 件までです。", MAX_REMINDERS_PER_USER));
+    }
+    let mut reminder = Reminder {
+        id: 0,
+        user_id: new.user_id,
+        guild_id: new.guild_id,
+        channel_id: new.channel_id,
+        content: content.to_string(),
+        remind_at,
+    };
+    reminder.id = store.add_reminder(&reminder).await.map_err(|e| e.report())?;
+    Ok(reminder)
+}
+
+impl Reminder {
+    // 知らせる場所の表示
+    pub fn place_label(&self) -> String {
+        match self.channel_id {
+            Some(channel_id) => format!("<#{}>", channel_id),
+            None => "DM".to_string(),
+        }
+    }
+}
+
+// /reminders の一覧と取り消しボタン
+pub fn list_view(reminders: &[Reminder], offset: UtcOffset, timezone: &str) -> (CreateEmbed, CreateComponents) {
+    let lines: Vec<String> = reminders
+        .iter()
+        .map(|r| format!("**#{}** {} ({}) {}", r.id, cron::format_local(r.remind_at, offset), r.place_label(), list::truncate(&r.content, 80)))
+        .collect();
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("リマインダー ({} 件)", reminders.len()))
+        .description(if lines.is_empty() { "リマインダーはありません。".to_string() } else { lines.join("\n") })
+        .footer(|f| f.text(format!("タイムゾーン: {}", timezone)));
+    let mut components = CreateComponents::default();
+    for chunk in reminders.chunks(5) {
+        components.create_action_row(|row| {
+            for r in chunk {
+                row.create_button(|b| b.custom_id(format!("remind_cancel:{}", r.id)).label(format!("#{} を取り消す", r.id)).style(ButtonStyle::Secondary));
+            }
+            row
+        });
+    }
+    (embed, components)
+}
+
+// リマインダーの送信先
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn remind(&self, reminder: &Reminder, content: &str) -> Result<(), String>;
+}
+
+#[async_trait]
+impl Notifier for Http {
+    async fn remind(&self, reminder: &Reminder, content: &str) -> Result<(), String> {
+        let user = UserId(reminder.user_id as u64);
+        let channel = match reminder.channel_id {
+            Some(channel_id) => ChannelId(channel_id as u64),
+            None => metrics::time_discord("users/@me/channels", user.create_dm_channel(self)).await.map_err(|e| e.to_string())?.id,
+        };
+        // 本文に含まれるメンションでは通知せず、本人にだけ通知する
+        metrics::time_discord("channels/messages", channel.send_message(self, |m| m.content(content).allowed_mentions(|a| a.users([user]))))
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+// 知らせる文面 (チャンネルでは本人をメンションする)
+pub fn message(reminder: &Reminder) -> String {
+    match reminder.channel_id {
+        Some(_) => format!("<@{}> ⏰ リマインダー: {}", reminder.user_id, reminder.content),
+        None => format!("⏰ リマインダー: {}", reminder.content),
+    }
+}
+
+// 期限の来たリマインダーを送り、送った件数を返す
+pub async fn deliver_due(store: &dyn CommandStore, notifier: &dyn Notifier, now: i64) -> usize {
+    let due = match store.due_reminders(now).await {
+        Ok(due) => due,
+        Err(e) => {
+            e.log();
+            return 0;
+        }
+    };
+    let mut sent = 0;
+    for reminder in due {
+        // 他のプロセスが先に取った場合は送らない
+        match store.claim_reminder(reminder.id).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                e.log();
+                continue;
+            }
+        }
+        match notifier.remind(&reminder, &message(&reminder)).await {
+            Ok(()) => {
+                metrics::REMINDERS.with_label_values(&["sent"]).inc();
+                sent += 1;
+            }
+            Err(e) => {
+                metrics::REMINDERS.with_label_values(&["failed"]).inc();
+                tracing::warn!(reminder_id = reminder.id, user_id = reminder.user_id, error = %e, "failed to deliver reminder");
+            }
+        }
+    }
+    sent
+}
+
+// 終了処理が始まるまで、期限の来たリマインダーを送り続ける
+pub async fn run(store: Arc<dyn CommandStore>, http: Arc<Http>, shutdown: Shutdown) -> Result<(), String> {
+    loop {
+        {
+            // 送信中に終了処理が始まった場合は送り終えるまで待たせる
+            let Some(_in_flight) = shutdown.track() else { return Ok(()) };
+            deliver_due(store.as_ref(), http.as_ref(), crate::retention::now()).await;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(TICK) => {}
+            _ = shutdown.wait() => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// 日時の解釈と、期限の来たリマインダーの送信を確認するテスト
+
+use std::sync::Mutex;
+
+use time::UtcOffset;
+
+use super::*;
+use crate::store::MemoryCommandStore;
+
+const USER: i64 = 42;
+
+// 2025-01-06 (月) 12:00 UTC
+const NOON: i64 = 1_736_164_800;
+
+fn jst() -> UtcOffset {
+    cron::parse_timezone("Asia/Tokyo").unwrap()
+}
+
+fn when(input: &str, offset: UtcOffset) -> String {
+    cron::format_local(parse_when(input, offset, NOON).unwrap(), offset)
+}
+
+#[test]
+fn relative_times() {
+    assert_eq!(parse_when("in 2h", UtcOffset::UTC, NOON), Ok(NOON + 2 * 3600));
+    assert_eq!(parse_when("2h30m", UtcOffset::UTC, NOON), Ok(NOON + 9000));
+    assert_eq!(parse_when("in 2 hours 30 minutes", UtcOffset::UTC, NOON), Ok(NOON + 9000));
+    assert_eq!(parse_when("90分後", UtcOffset::UTC, NOON), Ok(NOON + 5400));
+    assert_eq!(parse_when("1時間30分後", UtcOffset::UTC, NOON), Ok(NOON + 5400));
+    assert_eq!(parse_when("1w", UtcOffset::UTC, NOON), Ok(NOON + 7 * 24 * 3600));
+}
+
+#[test]
+fn days_and_times() {
+    assert_eq!(when("tomorrow 9:00", UtcOffset::UTC), "2025-01-07 09:00");
+    assert_eq!(when("Tomorrow", UtcOffset::UTC), "2025-01-07 09:00");
+    assert_eq!(when("today 18:30", UtcOffset::UTC), "2025-01-06 18:30");
+    assert_eq!(when("明日 7:15", UtcOffset::UTC), "2025-01-07 07:15");
+    // 時刻だけなら次にその時刻になる日
+    assert_eq!(when("18:00", UtcOffset::UTC), "2025-01-06 18:00");
+    assert_eq!(when("9:00", UtcOffset::UTC), "2025-01-07 09:00");
+    // ユーザのタイムゾーンでの日付・時刻 (UTC 12:00 は JST 21:00)
+    assert_eq!(when("tomorrow 9:00", jst()), "2025-01-07 09:00");
+    assert_eq!(parse_when("tomorrow 9:00", jst(), NOON), Ok(NOON + 12 * 3600));
+    assert_eq!(when("2025-02-01 08:00", jst()), "2025-02-01 08:00");
+}
+
+#[test]
+fn rejects_invalid_times() {
+    for input in ["", "soon", "in 2 fortnights", "today", "25:00", "tomorrow 9"] {
+        assert!(parse_when(input, UtcOffset::UTC, NOON).unwrap_err().contains("解釈できません"), "{input}");
+    }
+    assert_eq!(parse_when("today 11:00", UtcOffset::UTC, NOON), Err("過去の日時は指定できません。".to_string()));
+    assert_eq!(parse_when("0m", UtcOffset::UTC, NOON), Err("過去の日時は指定できません。".to_string()));
+    assert_eq!(parse_when("400d", UtcOffset::UTC, NOON), Err("1 年以上先の日時は指定できません。".to_string()));
+}
+
+#[derive(Default)]
+struct FakeNotifier {
+    // (チャンネル ID, 送った内容)
+    sent: Mutex<Vec<(Option<i64>, String)>>,
+}
+
+#[async_trait]
+impl Notifier for FakeNotifier {
+    async fn remind(&self, reminder: &Reminder, content: &str) -> Result<(), String> {
+        self.sent.lock().unwrap().push((reminder.channel_id, content.to_string()));
+        Ok(())
+    }
+}
+
+fn new_reminder<'a>(when: &'a str, channel_id: Option<i64>) -> NewReminder<'a> {
+    NewReminder { user_id: USER, guild_id: Some(1), channel_id, when, content: "お茶" }
+}
+
+#[tokio::test]
+async fn reminders_use_the_users_timezone() {
+    let store = MemoryCommandStore::new();
+    let utc = create(&store, new_reminder("tomorrow 9:00", None), NOON).await.unwrap();
+    assert!(set_timezone(&store, USER, "Europe/Paris").await.is_err());
+    set_timezone(&store, USER, "Asia/Tokyo").await.unwrap();
+    let tokyo = create(&store, new_reminder("tomorrow 9:00", None), NOON).await.unwrap();
+    assert_eq!(utc.remind_at - tokyo.remind_at, 9 * 3600);
+    assert_eq!(user_timezone(&store, USER).await.unwrap().0, "Asia/Tokyo");
+    assert_eq!(user_timezone(&store, USER + 1).await.unwrap(), ("UTC".to_string(), UtcOffset::UTC));
+}
+
+#[tokio::test]
+async fn deliver_due_sends_each_reminder_once() {
+    let store = MemoryCommandStore::new();
+    create(&store, new_reminder("in 1h", Some(100)), NOON).await.unwrap();
+    create(&store, new_reminder("in 2h", None), NOON).await.unwrap();
+
+    let notifier = FakeNotifier::default();
+    assert_eq!(deliver_due(&store, &notifier, NOON).await, 0);
+    assert_eq!(deliver_due(&store, &notifier, NOON + 3600).await, 1);
+    // 同じ時刻にもう一度動かしても (別のプロセスでも) 二重に送らない
+    assert_eq!(deliver_due(&store, &notifier, NOON + 3600).await, 0);
+    assert_eq!(deliver_due(&store, &notifier, NOON + 3 * 3600).await, 1);
+    assert_eq!(
+        *notifier.sent.lock().unwrap(),
+        [(Some(100), "<@42> ⏰ リマインダー: お茶".to_string()), (None, "⏰ リマインダー: お茶".to_string())]
+    );
+    assert!(store.list_reminders(USER).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn create_validates_input() {
+    let store = MemoryCommandStore::new();
+    let mut empty = new_reminder("in 1h", None);
+    empty.content = "  ";
+    assert_eq!(create(&store, empty, NOON).await.unwrap_err(), "内容を指定してください。");
+    for _ in 0..MAX_REMINDERS_PER_USER {
+        create(&store, new_reminder("in 1h", None), NOON).await.unwrap();
+    }
+    assert_eq!(create(&store, new_reminder("in 1h", None), NOON).await.unwrap_err(), "リマインダーは 1 人あたり 25 件までです。");
+}
@@ -0,0 +1,78 @@
+// Bot が退出したギルドのデータの保持と削除
+// 退出 (キック・BAN を含む) を記録し、保持期間を過ぎても再招待されなければコマンドなどを削除する
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::events::{Event, EventBus};
+use crate::shutdown::Shutdown;
+use crate::store::CommandStore;
+
+// 削除予定を確認する間隔
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default()
+}
+
+// 退出を記録する。保持期間が 0 ならその場で削除する
+pub async fn guild_left(store: &dyn CommandStore, guild_id: i64, retention: Duration) {
+    if retention.is_zero() {
+        match store.purge_guild(guild_id).await {
+            Ok(removed) => tracing::info!(guild_id, removed, "removed from guild; purged its data"),
+            Err(e) => tracing::warn!(guild_id, error = %e, "failed to purge guild data"),
+        }
+        return;
+    }
+    let left_at = now();
+    let purge_at = left_at + retention.as_secs() as i64;
+    match store.record_guild_departure(guild_id, left_at, purge_at).await {
+        Ok(()) => tracing::info!(guild_id, purge_at, "removed from guild; scheduled data deletion"),
+        Err(e) => e.log(),
+    }
+}
+
+// 参加中のギルドに削除予定が残っていれば取り消す (再招待された場合)
+pub async fn guild_joined(store: &dyn CommandStore, guild_id: i64) {
+    match store.cancel_guild_departure(guild_id).await {
+        Ok(true) => tracing::info!(guild_id, "re-invited to guild; cancelled scheduled data deletion"),
+        Ok(false) => {}
+        Err(e) => e.log(),
+    }
+}
+
+// 保持期間を過ぎたギルドのデータを削除し、削除したギルドを返す
+pub async fn purge_due(store: &dyn CommandStore, events: &EventBus, now: i64) -> Vec<i64> {
+    let departures = match store.list_guild_departures().await {
+        Ok(departures) => departures,
+        Err(e) => {
+            e.log();
+            return Vec::new();
+        }
+    };
+    let mut purged = Vec::new();
+    for departure in departures.into_iter().filter(|d| d.purge_at <= now) {
+        match store.purge_guild(departure.guild_id).await {
+            Ok(removed) => {
+                tracing::info!(guild_id = departure.guild_id, removed, "retention period expired; purged guild data");
+                // 別プロセスの Web のキャッシュも捨てさせる
+                events.publish(Event::GuildChanged { guild_id: departure.guild_id }).await;
+                purged.push(departure.guild_id);
+            }
+            Err(e) => tracing::warn!(guild_id = departure.guild_id, error = %e, "failed to purge guild data"),
+        }
+    }
+    purged
+}
+
+// 終了処理が始まるまで、一定間隔で期限切れのデータを削除する
+pub fn start(store: Arc<dyn CommandStore>, events: EventBus, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        loop {
+            purge_due(store.as_ref(), &events, now()).await;
+            tokio::select! {
+                _ = tokio::time::sleep(SWEEP_INTERVAL) => {}
+                _ = shutdown.wait() => return,
+            }
+        }
+    });
+}
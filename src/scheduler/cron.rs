@@ -0,0 +1,176 @@
+// cron 式 (分 時 日 月 曜日 の 5 フィールド) と日時・タイムゾーンの解釈
+// タイムゾーンのデータベースは持たないため、UTC からの固定のずれ (+09:00 など) と、夏時間のない主なタイムゾーン名だけに対応する
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+// 夏時間のないタイムゾーン名と UTC からのずれ (分)
+const ZONES: [(&str, i32); 10] = [
+    ("UTC", 0),
+    ("GMT", 0),
+    ("JST", 9 * 60),
+    ("Asia/Tokyo", 9 * 60),
+    ("Asia/Seoul", 9 * 60),
+    ("Asia/Shanghai", 8 * 60),
+    ("Asia/Taipei", 8 * 60),
+    ("Asia/Hong_Kong", 8 * 60),
+    ("Asia/Singapore", 8 * 60),
+    ("Asia/Kolkata", 5 * 60 + 30),
+];
+
+// 次の実行日時を探す範囲 (うるう年の 2/29 のみの指定も見つかるように 4 年と少し)
+const SEARCH_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    // 各フィールドで一致する値のビット集合
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日と曜日が * か (両方指定された場合はどちらかに一致すればよい)
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(format!("cron 式は「分 時 日 月 曜日」の 5 つで指定してください: {:?}", expr));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7, "曜日")?;
+        // 7 も日曜日
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Cron {
+            minutes: parse_field(minute, 0, 59, "分")?,
+            hours: parse_field(hour, 0, 23, "時")?,
+            days: parse_field(day, 1, 31, "日")?,
+            months: parse_field(month, 1, 12, "月")?,
+            weekdays,
+            any_day: *day == "*",
+            any_weekday: *weekday == "*",
+        })
+    }
+
+    fn matches_date(&self, date: Date) -> bool {
+        if self.months & (1 << u8::from(date.month())) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().number_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    // after (UNIX 秒) より後で最初に一致する日時。存在しない日付 (2/30 など) だけの指定なら None
+    pub fn next_after(&self, offset: UtcOffset, after: i64) -> Option<i64> {
+        let start = OffsetDateTime::from_unix_timestamp(after - after.rem_euclid(60) + 60).ok()?.to_offset(offset);
+        let first_minute = u32::from(start.hour()) * 60 + u32::from(start.minute());
+        for i in 0..SEARCH_DAYS {
+            let date = start.date().checked_add(time::Duration::days(i))?;
+            if !self.matches_date(date) {
+                continue;
+            }
+            let from = if i == 0 { first_minute } else { 0 };
+            for minute_of_day in from..24 * 60 {
+                let (hour, minute) = (minute_of_day / 60, minute_of_day % 60);
+                if self.hours & (1 << hour) != 0 && self.minutes & (1 << minute) != 0 {
+                    let time = Time::from_hms(hour as u8, minute as u8, 0).ok()?;
+                    return Some(PrimitiveDateTime::new(date, time).assume_offset(offset).unix_timestamp());
+                }
+            }
+        }
+        None
+    }
+}
+
+// "*", "5", "1-5", "*/15", "0-30/10", "1,15" とそれらのカンマ区切り
+fn parse_field(field: &str, min: u32, max: u32, label: &str) -> Result<u64, String> {
+    let invalid = || format!("cron 式の{}が不正です: {:?} ({}〜{})", label, field, min, max);
+    let number = |s: &str| s.parse::<u32>().ok().filter(|n| (min..=max).contains(n)).ok_or_else(invalid);
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (number(a)?, number(b)?),
+                // "5/15" は 5 から最後まで 15 おき
+                None if part.contains('/') => (number(range)?, max),
+                None => {
+                    let n = number(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        for n in (first..=last).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+// "UTC"、"Asia/Tokyo" などの名前、または "+09:00" / "-0530" / "+9" のような UTC からのずれ
+pub fn parse_timezone(name: &str) -> Result<UtcOffset, String> {
+    let name = name.trim();
+    let invalid = || {
+        format!(
+            "タイムゾーン {:?} には対応していません。+09:00 のような UTC からのずれか、{} のいずれかを指定してください (夏時間には対応していません)。",
+            name,
+            ZONES.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", ")
+        )
+    };
+    if let Some((_, minutes)) = ZONES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        return UtcOffset::from_whole_seconds(minutes * 60).map_err(|_| invalid());
+    }
+    let (sign, rest) = match name.strip_prefix("UTC").unwrap_or(name).split_at_checked(1) {
+        Some(("+", rest)) => (1, rest),
+        Some(("-", rest)) => (-1, rest),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+    UtcOffset::from_whole_seconds(sign * (hours * 3600 + minutes * 60)).map_err(|_| invalid())
+}
+
+// "2025-01-31 09:00" (または "2025-01-31T09:00") をそのタイムゾーンの日時として UNIX 秒にする
+pub fn parse_at(s: &str, offset: UtcOffset) -> Option<i64> {
+    let (date, time) = s.trim().split_once([' ', 'T'])?;
+    let mut date_parts = date.split('-').map(|p| p.parse::<i32>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+    let (hour, minute) = time.split_once(':')?;
+    let date = Date::from_calendar_date(year, Month::try_from(u8::try_from(month).ok()?).ok()?, u8::try_from(day).ok()?).ok()?;
+    let time = Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()?;
+    Some(PrimitiveDateTime::new(date, time).assume_offset(offset).unix_timestamp())
+}
+
+// 表示用の "2025-01-31 09:00"
+pub fn format_local(timestamp: i64, offset: UtcOffset) -> String {
+    match OffsetDateTime::from_unix_timestamp(timestamp) {
+        Ok(t) => {
+            let t = t.to_offset(offset);
+            format!("{:04}-{:02}-{:02} {:02}:{:02}", t.year(), u8::from(t.month()), t.day(), t.hour(), t.minute())
+        }
+        Err(_) => timestamp.to_string(),
+    }
+}
@@ -0,0 +1,222 @@
+// 予約投稿 (1 回だけ、または cron 式で繰り返しチャンネルにメッセージを送る)
+// ジョブは DB に保存し、Bot プロセスのワーカーが一定間隔で期限の来たものを送る。
+// 送る前に next_run_at を比較して更新 (1 回だけのものは削除) し、成功したプロセスだけが送るため、
+// Bot を複数のプロセスで動かしても二重に送らない (送る前にプロセスが落ちた場合はその回は送られない)
+pub mod cron;
+#[cfg(test)]
+mod tests;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::id::ChannelId;
+
+use crate::metrics;
+use crate::retention;
+use crate::shutdown::Shutdown;
+use crate::store::CommandStore;
+pub use cron::Cron;
+
+// 期限の来たジョブを確認する間隔
+const TICK: Duration = Duration::from_secs(20);
+// ギルドあたりの予約の上限
+pub const MAX_SCHEDULES_PER_GUILD: usize = 25;
+// タイムゾーンを省略した場合
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    pub id: i64,
+    pub guild_id: i64,
+    pub channel_id: i64,
+    // 繰り返す場合の cron 式。None なら next_run_at に 1 回だけ送る
+    pub cron: Option<String>,
+    pub timezone: String,
+    // 送る内容。None なら command_name のカスタムコマンドの返答を送る
+    pub content: Option<String>,
+    pub command_name: Option<String>,
+    // 次に送る日時 (UNIX 秒)
+    pub next_run_at: i64,
+    pub last_run_at: Option<i64>,
+    // 前回送れなかった場合の理由
+    pub last_error: Option<String>,
+    pub created_by: Option<i64>,
+}
+
+// /schedule add や Web から受け取った予約の内容
+pub struct NewSchedule<'a> {
+    pub guild_id: i64,
+    pub channel_id: i64,
+    // cron 式、または "2025-01-31 09:00" 形式の日時
+    pub when: &'a str,
+    pub timezone: Option<&'a str>,
+    pub content: Option<&'a str>,
+    pub command_name: Option<&'a str>,
+    pub created_by: Option<i64>,
+}
+
+// 入力を検証して予約を保存する
+pub async fn create(store: &dyn CommandStore, new: NewSchedule<'_>, now: i64) -> Result<Schedule, String> {
+    let timezone = new.timezone.map(str::trim).filter(|t| !t.is_empty()).unwrap_or(DEFAULT_TIMEZONE);
+    let offset = cron::parse_timezone(timezone)?;
+    let (cron, next_run_at) = match cron::parse_at(new.when, offset) {
+        Some(at) if at <= now => return Err("過去の日時は指定できません。".to_string()),
+        Some(at) => (None, at),
+        None => {
+            let expr = Cron::parse(new.when)?;
+            let next = expr.next_after(offset, now).ok_or_else(|| "この cron 式に一致する日時がありません。".to_string())?;
+            (Some(new.when.split_whitespace().collect::<Vec<_>>().join(" ")), next)
+        }
+    };
+    let content = new.content.filter(|c| !c.trim().is_empty());
+    let command_name = new.command_name.map(str::trim).filter(|c| !c.is_empty());
+    match (content, command_name) {
+        (Some(_), Some(_)) | (None, None) => return Err("送る内容かカスタムコマンド名のどちらか一方を指定してください。".to_string()),
+        (Some(content), None) if content.chars().count() > 2000 => return Err("送る内容は 2000 文字以内で指定してください。".to_string()),
+        (None, Some(name)) => match store.get_command(new.guild_id, name).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(format!("コマンド '{}' は存在しません。", name)),
+            Err(e) => return Err(e.report()),
+        },
+        _ => {}
+    }
+    if store.list_schedules(new.guild_id).await.map_err(|e| e.report())?.len() >= MAX_SCHEDULES_PER_GUILD {
+        return Err(format!("予約はギルドあたり {} 件までです。", MAX_SCHEDULES_PER_GUILD));
+    }
+    let mut schedule = Schedule {
+        id: 0,
+        guild_id: new.guild_id,
+        channel_id: new.channel_id,
+        cron,
+        timezone: timezone.to_string(),
+        content: content.map(str::to_string),
+        command_name: command_name.map(str::to_string),
+        next_run_at,
+        last_run_at: None,
+        last_error: None,
+        created_by: new.created_by,
+    };
+    schedule.id = store.add_schedule(&schedule).await.map_err(|e| e.report())?;
+    Ok(schedule)
+}
+
+impl Schedule {
+    // 「毎回: cron 式」または「1 回」
+    pub fn repeat_label(&self) -> String {
+        match &self.cron {
+            Some(cron) => format!("{} ({})", cron, self.timezone),
+            None => format!("1 回のみ ({})", self.timezone),
+        }
+    }
+
+    pub fn next_run_label(&self) -> String {
+        match cron::parse_timezone(&self.timezone) {
+            Ok(offset) => cron::format_local(self.next_run_at, offset),
+            Err(_) => self.next_run_at.to_string(),
+        }
+    }
+
+    // 送る内容の表示 (カスタムコマンドなら !name)
+    pub fn content_label(&self) -> String {
+        match (&self.content, &self.command_name) {
+            (Some(content), _) => content.clone(),
+            (None, Some(name)) => format!("!{}", name),
+            (None, None) => String::new(),
+        }
+    }
+
+    // 送った後の次回 (繰り返さない場合は None)
+    fn following_run(&self, now: i64) -> Option<i64> {
+        let offset = cron::parse_timezone(&self.timezone).ok()?;
+        Cron::parse(self.cron.as_deref()?).ok()?.next_after(offset, now.max(self.next_run_at))
+    }
+}
+
+// 予約投稿の送信先
+#[async_trait]
+pub trait Poster: Send + Sync {
+    async fn post(&self, channel_id: i64, content: &str) -> Result<(), String>;
+}
+
+#[async_trait]
+impl Poster for Http {
+    async fn post(&self, channel_id: i64, content: &str) -> Result<(), String> {
+        // 送る内容のメンションでは通知しない
+        metrics::time_discord("channels/messages", ChannelId(channel_id as u64).send_message(self, |m| m.content(content).allowed_mentions(|a| a.empty_parse())))
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+// 期限の来たジョブを送り、送った件数を返す
+pub async fn run_due(store: &dyn CommandStore, poster: &dyn Poster, now: i64) -> usize {
+    let due = match store.due_schedules(now).await {
+        Ok(due) => due,
+        Err(e) => {
+            e.log();
+            return 0;
+        }
+    };
+    let mut sent = 0;
+    for schedule in due {
+        let next = schedule.following_run(now);
+        // 他のプロセスが先に取った場合は送らない
+        match store.claim_schedule(schedule.id, schedule.next_run_at, next, now).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                e.log();
+                continue;
+            }
+        }
+        let content = match (&schedule.content, &schedule.command_name) {
+            (Some(content), _) => Ok(content.clone()),
+            (None, Some(name)) => match store.get_command(schedule.guild_id, name).await {
+                Ok(Some(command)) => Ok(command.render_response(&[])),
+                Ok(None) => Err(format!("コマンド '{}' が削除されています。", name)),
+                Err(e) => Err(e.report()),
+            },
+            (None, None) => Err("送る内容がありません。".to_string()),
+        };
+        let result = match content {
+            Ok(content) => poster.post(schedule.channel_id, &content).await,
+            Err(e) => Err(e),
+        };
+        match &result {
+            Ok(()) => {
+                metrics::SCHEDULED_MESSAGES.with_label_values(&["sent"]).inc();
+                sent += 1;
+            }
+            Err(e) => {
+                metrics::SCHEDULED_MESSAGES.with_label_values(&["failed"]).inc();
+                tracing::warn!(schedule_id = schedule.id, guild_id = schedule.guild_id, error = %e, "failed to send scheduled message");
+            }
+        }
+        if next.is_some() {
+            if let Err(e) = store.record_schedule_result(schedule.id, result.err().as_deref()).await {
+                e.log();
+            }
+        }
+    }
+    sent
+}
+
+// 終了処理が始まるまで、期限の来たジョブを送り続ける
+pub fn start(store: Arc<dyn CommandStore>, http: Arc<Http>, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        loop {
+            {
+                // 送信中に終了処理が始まった場合は送り終えるまで待たせる
+                let Some(_in_flight) = shutdown.track() else { return };
+                run_due(store.as_ref(), http.as_ref(), retention::now()).await;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(TICK) => {}
+                _ = shutdown.wait() => return,
+            }
+        }
+    });
+}
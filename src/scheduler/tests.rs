@@ -0,0 +1,141 @@
+// cron 式の解釈と、期限の来た予約の送信を確認するテスト
+
+use std::sync::Mutex;
+
+use time::UtcOffset;
+
+use super::*;
+use crate::store::MemoryCommandStore;
+
+const GUILD: i64 = 1;
+
+// 2025-01-06 (月) 00:00 UTC
+const MONDAY: i64 = 1_736_121_600;
+
+fn jst() -> UtcOffset {
+    cron::parse_timezone("Asia/Tokyo").unwrap()
+}
+
+fn at(s: &str) -> i64 {
+    cron::parse_at(s, UtcOffset::UTC).unwrap()
+}
+
+#[test]
+fn cron_next_run() {
+    let next = |expr: &str, after: &str| cron::format_local(Cron::parse(expr).unwrap().next_after(UtcOffset::UTC, at(after)).unwrap(), UtcOffset::UTC);
+    assert_eq!(at("2025-01-06 00:00"), MONDAY);
+    assert_eq!(next("* * * * *", "2025-01-06 00:00"), "2025-01-06 00:01");
+    assert_eq!(next("*/15 * * * *", "2025-01-06 00:15"), "2025-01-06 00:30");
+    assert_eq!(next("0 9 * * 1-5", "2025-01-10 09:00"), "2025-01-13 09:00");
+    // 7 も日曜日
+    assert_eq!(next("30 8 * * 7", "2025-01-06 00:00"), "2025-01-12 08:30");
+    assert_eq!(next("0 0 1,15 * *", "2025-01-02 00:00"), "2025-01-15 00:00");
+    // 日と曜日の両方を指定した場合はどちらかに一致すればよい
+    assert_eq!(next("0 0 31 * 3", "2025-01-06 00:00"), "2025-01-08 00:00");
+    assert_eq!(next("0 0 29 2 *", "2025-01-01 00:00"), "2028-02-29 00:00");
+    assert_eq!(Cron::parse("0 0 30 2 *").unwrap().next_after(UtcOffset::UTC, MONDAY), None);
+
+    // タイムゾーンのずれを考慮する (JST 9:00 は UTC 0:00)
+    let jst_nine = Cron::parse("0 9 * * *").unwrap().next_after(jst(), MONDAY).unwrap();
+    assert_eq!(cron::format_local(jst_nine, UtcOffset::UTC), "2025-01-07 00:00");
+    assert_eq!(cron::format_local(jst_nine, jst()), "2025-01-07 09:00");
+}
+
+#[test]
+fn cron_rejects_invalid_expressions() {
+    for expr in ["", "* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "* * * * 8", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+        assert!(Cron::parse(expr).is_err(), "{expr}");
+    }
+}
+
+#[test]
+fn timezones() {
+    let offset = |name: &str| cron::parse_timezone(name).map(|o| o.whole_minutes());
+    assert_eq!(offset("UTC"), Ok(0));
+    assert_eq!(offset("asia/tokyo"), Ok(9 * 60));
+    assert_eq!(offset("+09:00"), Ok(9 * 60));
+    assert_eq!(offset("UTC-0530"), Ok(-(5 * 60 + 30)));
+    assert_eq!(offset("+9"), Ok(9 * 60));
+    // 夏時間のあるタイムゾーンは扱えない
+    assert!(offset("America/New_York").is_err());
+    assert!(offset("+15:00").is_err());
+    assert_eq!(cron::parse_at("2025-01-06T09:00", jst()), Some(MONDAY));
+    assert_eq!(cron::parse_at("2025-02-30 09:00", jst()), None);
+}
+
+#[derive(Default)]
+struct FakePoster {
+    // (チャンネル ID, 送った内容)
+    posted: Mutex<Vec<(i64, String)>>,
+    fail: bool,
+}
+
+#[async_trait]
+impl Poster for FakePoster {
+    async fn post(&self, channel_id: i64, content: &str) -> Result<(), String> {
+        if self.fail {
+            return Err("Missing Access".to_string());
+        }
+        self.posted.lock().unwrap().push((channel_id, content.to_string()));
+        Ok(())
+    }
+}
+
+fn new_schedule<'a>(when: &'a str, content: Option<&'a str>, command_name: Option<&'a str>) -> NewSchedule<'a> {
+    NewSchedule { guild_id: GUILD, channel_id: 100, when, timezone: Some("UTC"), content, command_name, created_by: None }
+}
+
+#[tokio::test]
+async fn run_due_sends_each_run_once() {
+    let store = MemoryCommandStore::new();
+    store.add_command(GUILD, "hello", "world").await.unwrap();
+    let once = create(&store, new_schedule("2025-01-06 09:00", Some("once"), None), MONDAY).await.unwrap();
+    let hourly = create(&store, new_schedule("0 * * * *", None, Some("hello")), MONDAY).await.unwrap();
+    assert_eq!((once.cron.as_deref(), hourly.next_run_at), (None, MONDAY + 3600));
+
+    let poster = FakePoster::default();
+    assert_eq!(run_due(&store, &poster, MONDAY).await, 0);
+    assert_eq!(run_due(&store, &poster, MONDAY + 3600).await, 1);
+    // 同じ時刻にもう一度動かしても (別のプロセスでも) 二重に送らない
+    assert_eq!(run_due(&store, &poster, MONDAY + 3600).await, 0);
+    // 止まっていた間の回はまとめて 1 回だけ送り、次回は現在より後にする
+    assert_eq!(run_due(&store, &poster, MONDAY + 10 * 3600 + 60).await, 2);
+    assert_eq!(*poster.posted.lock().unwrap(), [(100, "world".to_string()), (100, "world".to_string()), (100, "once".to_string())]);
+    let remaining = store.list_schedules(GUILD).await.unwrap();
+    assert_eq!(remaining.iter().map(|s| (s.id, s.next_run_at)).collect::<Vec<_>>(), [(hourly.id, MONDAY + 11 * 3600)]);
+}
+
+#[tokio::test]
+async fn run_due_records_failures() {
+    let store = MemoryCommandStore::new();
+    store.add_command(GUILD, "hello", "world").await.unwrap();
+    create(&store, new_schedule("0 * * * *", None, Some("hello")), MONDAY).await.unwrap();
+    let failing = FakePoster { fail: true, ..FakePoster::default() };
+    assert_eq!(run_due(&store, &failing, MONDAY + 3600).await, 0);
+    assert_eq!(store.list_schedules(GUILD).await.unwrap()[0].last_error.as_deref(), Some("Missing Access"));
+
+    // コマンドが削除されていれば送らずに記録する
+    store.remove_command(GUILD, "hello").await.unwrap();
+    assert_eq!(run_due(&store, &FakePoster::default(), MONDAY + 2 * 3600).await, 0);
+    assert_eq!(store.list_schedules(GUILD).await.unwrap()[0].last_error.as_deref(), Some("コマンド 'hello' が削除されています。"));
+}
+
+#[tokio::test]
+async fn create_validates_input() {
+    let store = MemoryCommandStore::new();
+    let error = |when: &'static str, content: Option<&'static str>| {
+        let store = &store;
+        async move { create(store, new_schedule(when, content, None), MONDAY).await.unwrap_err() }
+    };
+    assert!(error("0 9 * *", Some("x")).await.contains("5 つ"));
+    assert_eq!(error("2025-01-05 09:00", Some("x")).await, "過去の日時は指定できません。");
+    assert_eq!(error("0 9 * * *", None).await, "送る内容かカスタムコマンド名のどちらか一方を指定してください。");
+    let mut tz = new_schedule("0 9 * * *", Some("x"), None);
+    tz.timezone = Some("Europe/London");
+    assert!(create(&store, tz, MONDAY).await.unwrap_err().contains("対応していません"));
+
+    for _ in 0..MAX_SCHEDULES_PER_GUILD {
+        create(&store, new_schedule("0 9 * * *", Some("x"), None), MONDAY).await.unwrap();
+    }
+    assert_eq!(error("0 9 * * *", Some("x")).await, "予約はギルドあたり 25 件までです。");
+}
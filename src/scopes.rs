@@ -0,0 +1,80 @@
+use sqlx::FromRow;
+
+// スコープの種類 (DB の kind カラムに文字列で保存)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Channel,
+    Category,
+    Role,
+}
+
+impl ScopeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScopeKind::Channel => "channel",
+            ScopeKind::Category => "category",
+            ScopeKind::Role => "role",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<ScopeKind> {
+        match s {
+            "channel" => Some(ScopeKind::Channel),
+            "category" => Some(ScopeKind::Category),
+            "role" => Some(ScopeKind::Role),
+            _ => None,
+        }
+    }
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct Scope {
+    pub guild_id: i64,
+    pub name: String,
+    pub kind: String,
+    pub target_id: i64,
+    pub allow: bool,
+}
+
+impl Scope {
+    pub fn kind(&self) -> Option<ScopeKind> {
+        ScopeKind::parse(&self.kind)
+    }
+}
+
+// メッセージが送信された場所と送信者の情報
+pub struct ScopeContext {
+    pub channel_id: i64,
+    pub category_id: Option<i64>,
+    pub role_ids: Vec<i64>,
+}
+
+// スコープ判定
+// - deny に一致するものが 1 つでもあれば不可
+// - チャンネル/カテゴリの allow が 1 つ以上あれば、いずれかに一致する必要あり
+// - ロールの allow が 1 つ以上あれば、いずれかのロールを持っている必要あり
+pub fn is_allowed(scopes: &[Scope], ctx: &ScopeContext) -> bool {
+    let matches = |s: &Scope| match s.kind() {
+        Some(ScopeKind::Channel) => s.target_id == ctx.channel_id,
+        Some(ScopeKind::Category) => Some(s.target_id) == ctx.category_id,
+        Some(ScopeKind::Role) => ctx.role_ids.contains(&s.target_id),
+        None => false,
+    };
+
+    if scopes.iter().any(|s| !s.allow && matches(s)) {
+        return false;
+    }
+
+    let (location_allows, role_allows): (Vec<&Scope>, Vec<&Scope>) = scopes
+        .iter()
+        .filter(|s| s.allow && s.kind().is_some())
+        .partition(|s| s.kind() != Some(ScopeKind::Role));
+
+    if !location_allows.is_empty() && !location_allows.iter().any(|s| matches(s)) {
+        return false;
+    }
+    if !role_allows.is_empty() && !role_allows.iter().any(|s| matches(s)) {
+        return false;
+    }
+    true
+}
@@ -0,0 +1,84 @@
+// 終了処理の調整役
+// SIGTERM/Ctrl+C を受けたら trigger() し、各サブシステムは wait() で停止を開始する。
+// 処理中のイベントは track() の戻り値を保持している間だけ数え、drained() で全件の完了を待てる。
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{watch, Notify};
+
+// 終了処理全体にかける時間の既定値 (SHUTDOWN_GRACE_SECS で変更できる)
+pub const DEFAULT_GRACE: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    triggered: watch::Sender<bool>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+// 処理中のイベント 1 件分。drop で完了扱いになる
+pub struct InFlight {
+    inner: Arc<Inner>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.inner.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.inner.idle.notify_waiters();
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Shutdown {
+        Shutdown {
+            inner: Arc::new(Inner { triggered: watch::Sender::new(false), in_flight: AtomicUsize::new(0), idle: Notify::new() }),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.inner.triggered.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.triggered.borrow()
+    }
+
+    // trigger() されるまで待つ
+    pub async fn wait(&self) {
+        let mut rx = self.inner.triggered.subscribe();
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+
+    // 新しい処理を始める。終了処理中は None を返すので、呼び出し側は何もせずに戻る
+    pub fn track(&self) -> Option<InFlight> {
+        self.inner.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlight { inner: self.inner.clone() };
+        if self.is_triggered() {
+            return None;
+        }
+        Some(guard)
+    }
+
+    // 処理中のイベントがなくなるまで待つ
+    pub async fn drained(&self) {
+        loop {
+            let idle = self.inner.idle.notified();
+            if self.inner.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serenity::async_trait;
+
+use super::{AuthoredCommand, CommandStore, GuildDeparture, GuildUsage};
+use crate::commands::{Command, CommandError, Delivery, SlashOptions};
+use crate::greetings::Greeting;
+use crate::health::ShardStatus;
+use crate::list::ListSort;
+use crate::registration::RegistrationStatus;
+use crate::reminders::Reminder;
+use crate::scheduler::Schedule;
+use crate::scopes::{Scope, ScopeKind};
+
+// メモリ上にのみ保持するストア (DB を用意せずに動かす場合やテスト用)
+#[derive(Default)]
+pub struct MemoryCommandStore {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    // (guild_id, name) -> (作成順, コマンド)
+    commands: BTreeMap<(i64, String), (u64, Command)>,
+    // (guild_id, name, kind, target_id) -> allow
+    scopes: BTreeMap<(i64, String, &'static str, i64), bool>,
+    registrations: BTreeMap<i64, RegistrationStatus>,
+    shards: BTreeMap<i64, ShardStatus>,
+    departures: BTreeMap<i64, GuildDeparture>,
+    // (guild_id, name) -> (追加したユーザ, 最後に更新したユーザ)
+    authors: BTreeMap<(i64, String), (Option<i64>, Option<i64>)>,
+    schedules: BTreeMap<i64, Schedule>,
+    reminders: BTreeMap<i64, Reminder>,
+    // user_id -> タイムゾーン
+    timezones: BTreeMap<i64, String>,
+    greetings: BTreeMap<i64, Greeting>,
+    next_seq: u64,
+    next_schedule_id: i64,
+    next_reminder_id: i64,
+}
+
+impl MemoryCommandStore {
+    pub fn new() -> MemoryCommandStore {
+        MemoryCommandStore::default()
+    }
+}
+
+impl Inner {
+    fn command_mut(&mut self, guild_id: i64, name: &str) -> Result<&mut Command, CommandError> {
+        self.commands
+            .get_mut(&(guild_id, name.to_string()))
+            .map(|(_, c)| c)
+            .ok_or(CommandError::NotFound)
+    }
+
+    fn scopes_of(&self, guild_id: i64, name: Option<&str>) -> Vec<Scope> {
+        // BTreeMap の順序が (name, kind, target_id) 順になる
+        self.scopes
+            .iter()
+            .filter(|((g, n, _, _), _)| *g == guild_id && name.map(|name| n == name).unwrap_or(true))
+            .map(|((g, n, k, t), allow)| Scope { guild_id: *g, name: n.clone(), kind: k.to_string(), target_id: *t, allow: *allow })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl CommandStore for MemoryCommandStore {
+    async fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn close(&self) {}
+
+    async fn get_command(&self, guild_id: i64, name: &str) -> Result<Option<Command>, CommandError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.commands.get(&(guild_id, name.to_string())).map(|(_, c)| c.clone()))
+    }
+
+    async fn list_commands(&self, guild_id: i64, filter: Option<&str>, sort: ListSort) -> Result<Vec<Command>, CommandError> {
+        let inner = self.inner.lock().unwrap();
+        let filter = filter.map(|f| f.to_lowercase());
+        let mut rows: Vec<&(u64, Command)> = inner
+            .commands
+            .values()
+            .filter(|(_, c)| c.guild_id == guild_id)
+            .filter(|(_, c)| match &filter {
+                Some(f) => c.name.to_lowercase().contains(f) || c.response.to_lowercase().contains(f),
+                None => true,
+            })
+            .collect();
+        // BTreeMap から取り出した時点で名前順になっている
+        match sort {
+            ListSort::Name => {}
+            ListSort::Newest => rows.sort_by_key(|r| std::cmp::Reverse(r.0)),
+            ListSort::Oldest => rows.sort_by_key(|r| r.0),
+        }
+        Ok(rows.into_iter().map(|(_, c)| c.clone()).collect())
+    }
+
+    async fn list_slash_commands(&self, guild_id: i64) -> Result<Vec<Command>, CommandError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .commands
+            .values()
+            .filter(|(_, c)| c.guild_id == guild_id && c.slash)
+            .map(|(_, c)| c.clone())
+            .collect())
+    }
+
+    async fn list_guild_ids(&self) -> Result<Vec<i64>, CommandError> {
+        let inner = self.inner.lock().unwrap();
+        let mut ids: Vec<i64> = inner.commands.keys().map(|(g, _)| *g).collect();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    async fn add_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        let key = (guild_id, name.to_string());
+        if inner.commands.contains_key(&key) {
+            return Err(CommandError::AlreadyExists);
+        }
+        inner.next_seq += 1;
+        let command = Command {
+            guild_id,
+            name: name.to_string(),
+            response: response.to_string(),
+            reply_mode: "reply".to_string(),
+            target_channel_id: None,
+            delete_trigger: false,
+            delete_after: None,
+            slash: false,
+            description: None,
+            arguments: Vec::new(),
+        };
+        let seq = inner.next_seq;
+        inner.commands.insert(key, (seq, command));
+        Ok(())
+    }
+
+    async fn update_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.command_mut(guild_id, name)?.response = response.to_string();
+        Ok(())
+    }
+
+    async fn remove_command(&self, guild_id: i64, name: &str) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.commands.remove(&(guild_id, name.to_string())).is_none() {
+            return Err(CommandError::NotFound);
+        }
+        inner.scopes.retain(|(g, n, _, _), _| !(*g == guild_id && n == name));
+        inner.authors.remove(&(guild_id, name.to_string()));
+        Ok(())
+    }
+
+    async fn set_delivery(&self, guild_id: i64, name: &str, delivery: &Delivery) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        let command = inner.command_mut(guild_id, name)?;
+        command.reply_mode = delivery.reply_mode.as_str().to_string();
+        command.target_channel_id = delivery.target_channel_id;
+        command.delete_trigger = delivery.delete_trigger;
+        command.delete_after = delivery.delete_after;
+        Ok(())
+    }
+
+    async fn set_slash(&self, guild_id: i64, name: &str, options: &SlashOptions) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        let command = inner.command_mut(guild_id, name)?;
+        command.slash = options.enabled;
+        command.description = options.description.clone();
+        command.arguments = options.arguments.clone();
+        Ok(())
+    }
+
+    async fn get_scopes(&self, guild_id: i64, name: &str) -> Result<Vec<Scope>, CommandError> {
+        Ok(self.inner.lock().unwrap().scopes_of(guild_id, Some(name)))
+    }
+
+    async fn list_guild_scopes(&self, guild_id: i64) -> Result<Vec<Scope>, CommandError> {
+        Ok(self.inner.lock().unwrap().scopes_of(guild_id, None))
+    }
+
+    async fn set_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64, allow: bool) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.command_mut(guild_id, name)?;
+        inner.scopes.insert((guild_id, name.to_string(), kind.as_str(), target_id), allow);
+        Ok(())
+    }
+
+    async fn remove_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.scopes.remove(&(guild_id, name.to_string(), kind.as_str(), target_id)) {
+            Some(_) => Ok(()),
+            None => Err(CommandError::NotFound),
+        }
+    }
+
+    async fn clear_scopes(&self, guild_id: i64, name: &str) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.scopes.retain(|(g, n, _, _), _| !(*g == guild_id && n == name));
+        inner.authors.remove(&(guild_id, name.to_string()));
+        Ok(())
+    }
+
+    async fn get_registration(&self, guild_id: i64) -> Result<Option<RegistrationStatus>, CommandError> {
+        Ok(self.inner.lock().unwrap().registrations.get(&guild_id).cloned())
+    }
+
+    async fn list_registrations(&self) -> Result<Vec<RegistrationStatus>, CommandError> {
+        Ok(self.inner.lock().unwrap().registrations.values().cloned().collect())
+    }
+
+    async fn record_registration(&self, guild_id: i64, hash: Option<&str>, error: Option<&str>) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        let previous = inner.registrations.get(&guild_id).and_then(|r| r.command_hash.clone());
+        let status = RegistrationStatus {
+            guild_id,
+            command_hash: hash.map(|h| h.to_string()).or(previous),
+            status: if error.is_some() { "error" } else { "ok" }.to_string(),
+            error: error.map(|e| e.to_string()),
+            updated_at: None,
+        };
+        inner.registrations.insert(guild_id, status);
+        Ok(())
+    }
+
+    async fn record_shard_status(&self, status: &ShardStatus) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.shards.insert(status.shard_id, ShardStatus { updated_at: None, ..status.clone() });
+        inner.shards.retain(|id, _| *id < status.shard_total);
+        Ok(())
+    }
+
+    async fn list_shard_statuses(&self) -> Result<Vec<ShardStatus>, CommandError> {
+        Ok(self.inner.lock().unwrap().shards.values().cloned().collect())
+    }
+
+    async fn list_guild_usage(&self) -> Result<Vec<GuildUsage>, CommandError> {
+        let inner = self.inner.lock().unwrap();
+        let mut usage: BTreeMap<i64, GuildUsage> = BTreeMap::new();
+        for ((guild_id, name), (_, command)) in &inner.commands {
+            let entry = usage.entry(*guild_id).or_insert(GuildUsage { guild_id: *guild_id, commands: 0, scopes: 0, bytes: 0 });
+            entry.commands += 1;
+            entry.bytes += (name.len() + command.response.len()) as i64;
+        }
+        for (guild_id, ..) in inner.scopes.keys() {
+            if let Some(entry) = usage.get_mut(guild_id) {
+                entry.scopes += 1;
+            }
+        }
+        Ok(usage.into_values().collect())
+    }
+
+    async fn database_size(&self) -> Result<Option<i64>, CommandError> {
+        Ok(None)
+    }
+
+    async fn purge_guild(&self, guild_id: i64) -> Result<u64, CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.commands.len();
+        inner.commands.retain(|(g, _), _| *g != guild_id);
+        inner.scopes.retain(|(g, ..), _| *g != guild_id);
+        inner.registrations.remove(&guild_id);
+        inner.departures.remove(&guild_id);
+        inner.authors.retain(|(g, _), _| *g != guild_id);
+        inner.schedules.retain(|_, s| s.guild_id != guild_id);
+        inner.reminders.retain(|_, r| r.guild_id != Some(guild_id));
+        inner.greetings.remove(&guild_id);
+        Ok((before - inner.commands.len()) as u64)
+    }
+
+    async fn record_guild_departure(&self, guild_id: i64, left_at: i64, purge_at: i64) -> Result<(), CommandError> {
+        self.inner.lock().unwrap().departures.insert(guild_id, GuildDeparture { guild_id, left_at, purge_at });
+        Ok(())
+    }
+
+    async fn cancel_guild_departure(&self, guild_id: i64) -> Result<bool, CommandError> {
+        Ok(self.inner.lock().unwrap().departures.remove(&guild_id).is_some())
+    }
+
+    async fn list_guild_departures(&self) -> Result<Vec<GuildDeparture>, CommandError> {
+        let mut departures: Vec<GuildDeparture> = self.inner.lock().unwrap().departures.values().cloned().collect();
+        departures.sort_by_key(|d| (d.purge_at, d.guild_id));
+        Ok(departures)
+    }
+
+    async fn record_author(&self, guild_id: i64, name: &str, user_id: i64) -> Result<(), CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        let key = (guild_id, name.to_string());
+        if !inner.commands.contains_key(&key) {
+            return Ok(());
+        }
+        let entry = inner.authors.entry(key).or_default();
+        entry.0.get_or_insert(user_id);
+        entry.1 = Some(user_id);
+        Ok(())
+    }
+
+    async fn list_authored_commands(&self, user_id: i64) -> Result<Vec<AuthoredCommand>, CommandError> {
+        Ok(self
+            .inner
+            .lock()
+            .unwrap()
+            .authors
+            .iter()
+            .filter(|(_, (created, updated))| *created == Some(user_id) || *updated == Some(user_id))
+            .map(|((guild_id, name), (created, updated))| AuthoredCommand {
+                guild_id: *guild_id,
+                name: name.clone(),
+                created: *created == Some(user_id),
+                updated: *updated == Some(user_id),
+            })
+            .collect())
+    }
+
+    async fn forget_user(&self, user_id: i64) -> Result<u64, CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut forgotten = 0;
+        for (created, updated) in inner.authors.values_mut() {
+            if *created != Some(user_id) && *updated != Some(user_id) {
+                continue;
+            }
+            forgotten += 1;
+            if *created == Some(user_id) {
+                *created = None;
+            }
+            if *updated == Some(user_id) {
+                *updated = None;
+            }
+        }
+        for schedule in inner.schedules.values_mut().filter(|s| s.created_by == Some(user_id)) {
+            schedule.created_by = None;
+        }
+        inner.reminders.retain(|_, r| r.user_id != user_id);
+        inner.timezones.remove(&user_id);
+        Ok(forgotten)
+    }
+
+    async fn add_schedule(&self, schedule: &Schedule) -> Result<i64, CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_schedule_id += 1;
+        let id = inner.next_schedule_id;
+        inner.schedules.insert(id, Schedule { id, last_run_at: None, last_error: None, ..schedule.clone() });
+        Ok(id)
+    }
+
+    async fn list_schedules(&self, guild_id: i64) -> Result<Vec<Schedule>, CommandError> {
+        let mut schedules: Vec<Schedule> = self.inner.lock().unwrap().schedules.values().filter(|s| s.guild_id == guild_id).cloned().collect();
+        schedules.sort_by_key(|s| (s.next_run_at, s.id));
+        Ok(schedules)
+    }
+
+    async fn remove_schedule(&self, guild_id: i64, id: i64) -> Result<bool, CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.schedules.get(&id).is_some_and(|s| s.guild_id == guild_id) {
+            inner.schedules.remove(&id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    async fn due_schedules(&self, now: i64) -> Result<Vec<Schedule>, CommandError> {
+        let mut schedules: Vec<Schedule> = self.inner.lock().unwrap().schedules.values().filter(|s| s.next_run_at <= now).cloned().collect();
+        schedules.sort_by_key(|s| (s.next_run_at, s.id));
+        schedules.truncate(100);
+        Ok(schedules)
+    }
+
+    async fn claim_schedule(&self, id: i64, run_at: i64, next: Option<i64>, now: i64) -> Result<bool, CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        match (inner.schedules.get_mut(&id), next) {
+            (Some(schedule), Some(next)) if schedule.next_run_at == run_at => {
+                schedule.next_run_at = next;
+                schedule.last_run_at = Some(now);
+                Ok(true)
+            }
+            (Some(schedule), None) if schedule.next_run_at == run_at => {
+                inner.schedules.remove(&id);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn record_schedule_result(&self, id: i64, error: Option<&str>) -> Result<(), CommandError> {
+        if let Some(schedule) = self.inner.lock().unwrap().schedules.get_mut(&id) {
+            schedule.last_error = error.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    async fn add_reminder(&self, reminder: &Reminder) -> Result<i64, CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_reminder_id += 1;
+        let id = inner.next_reminder_id;
+        inner.reminders.insert(id, Reminder { id, ..reminder.clone() });
+        Ok(id)
+    }
+
+    async fn list_reminders(&self, user_id: i64) -> Result<Vec<Reminder>, CommandError> {
+        let mut reminders: Vec<Reminder> = self.inner.lock().unwrap().reminders.values().filter(|r| r.user_id == user_id).cloned().collect();
+        reminders.sort_by_key(|r| (r.remind_at, r.id));
+        Ok(reminders)
+    }
+
+    async fn cancel_reminder(&self, user_id: i64, id: i64) -> Result<bool, CommandError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.reminders.get(&id).is_some_and(|r| r.user_id == user_id) {
+            inner.reminders.remove(&id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    async fn due_reminders(&self, now: i64) -> Result<Vec<Reminder>, CommandError> {
+        let mut reminders: Vec<Reminder> = self.inner.lock().unwrap().reminders.values().filter(|r| r.remind_at <= now).cloned().collect();
+        reminders.sort_by_key(|r| (r.remind_at, r.id));
+        reminders.truncate(100);
+        Ok(reminders)
+    }
+
+    async fn claim_reminder(&self, id: i64) -> Result<bool, CommandError> {
+        Ok(self.inner.lock().unwrap().reminders.remove(&id).is_some())
+    }
+
+    async fn get_user_timezone(&self, user_id: i64) -> Result<Option<String>, CommandError> {
+        Ok(self.inner.lock().unwrap().timezones.get(&user_id).cloned())
+    }
+
+    async fn set_user_timezone(&self, user_id: i64, timezone: &str) -> Result<(), CommandError> {
+        self.inner.lock().unwrap().timezones.insert(user_id, timezone.to_string());
+        Ok(())
+    }
+
+    async fn get_greeting(&self, guild_id: i64) -> Result<Option<Greeting>, CommandError> {
+        Ok(self.inner.lock().unwrap().greetings.get(&guild_id).cloned())
+    }
+
+    async fn set_greeting(&self, greeting: &Greeting) -> Result<(), CommandError> {
+        self.inner.lock().unwrap().greetings.insert(greeting.guild_id, greeting.clone());
+        Ok(())
+    }
+}
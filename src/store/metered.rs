@@ -0,0 +1,220 @@
+// 別のストアを包み、操作ごとの所要時間をメトリクスに記録する
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+use serenity::async_trait;
+
+use super::{AuthoredCommand, CommandStore, GuildDeparture, GuildUsage};
+use crate::commands::{Command, CommandError, Delivery, SlashOptions};
+use crate::greetings::Greeting;
+use crate::health::ShardStatus;
+use crate::list::ListSort;
+use crate::metrics;
+use crate::registration::RegistrationStatus;
+use crate::reminders::Reminder;
+use crate::scheduler::Schedule;
+use crate::scopes::{Scope, ScopeKind};
+
+pub struct MeteredCommandStore {
+    inner: Arc<dyn CommandStore>,
+    backend: &'static str,
+}
+
+impl MeteredCommandStore {
+    pub fn new(inner: Arc<dyn CommandStore>, backend: &'static str) -> MeteredCommandStore {
+        MeteredCommandStore { inner, backend }
+    }
+
+    async fn timed<T>(&self, operation: &str, fut: impl Future<Output = T>) -> T {
+        let start = Instant::now();
+        let result = fut.await;
+        metrics::DB_QUERY_SECONDS
+            .with_label_values(&[self.backend, operation])
+            .observe(start.elapsed().as_secs_f64());
+        result
+    }
+}
+
+#[async_trait]
+impl CommandStore for MeteredCommandStore {
+    async fn ping(&self) -> Result<(), String> {
+        self.timed("ping", self.inner.ping()).await
+    }
+
+    async fn close(&self) {
+        self.inner.close().await
+    }
+
+    async fn get_command(&self, guild_id: i64, name: &str) -> Result<Option<Command>, CommandError> {
+        self.timed("get_command", self.inner.get_command(guild_id, name)).await
+    }
+
+    async fn list_commands(&self, guild_id: i64, filter: Option<&str>, sort: ListSort) -> Result<Vec<Command>, CommandError> {
+        self.timed("list_commands", self.inner.list_commands(guild_id, filter, sort)).await
+    }
+
+    async fn list_slash_commands(&self, guild_id: i64) -> Result<Vec<Command>, CommandError> {
+        self.timed("list_slash_commands", self.inner.list_slash_commands(guild_id)).await
+    }
+
+    async fn list_guild_ids(&self) -> Result<Vec<i64>, CommandError> {
+        self.timed("list_guild_ids", self.inner.list_guild_ids()).await
+    }
+
+    async fn add_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError> {
+        self.timed("add_command", self.inner.add_command(guild_id, name, response)).await
+    }
+
+    async fn update_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError> {
+        self.timed("update_command", self.inner.update_command(guild_id, name, response)).await
+    }
+
+    async fn remove_command(&self, guild_id: i64, name: &str) -> Result<(), CommandError> {
+        self.timed("remove_command", self.inner.remove_command(guild_id, name)).await
+    }
+
+    async fn set_delivery(&self, guild_id: i64, name: &str, delivery: &Delivery) -> Result<(), CommandError> {
+        self.timed("set_delivery", self.inner.set_delivery(guild_id, name, delivery)).await
+    }
+
+    async fn set_slash(&self, guild_id: i64, name: &str, options: &SlashOptions) -> Result<(), CommandError> {
+        self.timed("set_slash", self.inner.set_slash(guild_id, name, options)).await
+    }
+
+    async fn get_scopes(&self, guild_id: i64, name: &str) -> Result<Vec<Scope>, CommandError> {
+        self.timed("get_scopes", self.inner.get_scopes(guild_id, name)).await
+    }
+
+    async fn list_guild_scopes(&self, guild_id: i64) -> Result<Vec<Scope>, CommandError> {
+        self.timed("list_guild_scopes", self.inner.list_guild_scopes(guild_id)).await
+    }
+
+    async fn set_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64, allow: bool) -> Result<(), CommandError> {
+        self.timed("set_scope", self.inner.set_scope(guild_id, name, kind, target_id, allow)).await
+    }
+
+    async fn remove_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64) -> Result<(), CommandError> {
+        self.timed("remove_scope", self.inner.remove_scope(guild_id, name, kind, target_id)).await
+    }
+
+    async fn clear_scopes(&self, guild_id: i64, name: &str) -> Result<(), CommandError> {
+        self.timed("clear_scopes", self.inner.clear_scopes(guild_id, name)).await
+    }
+
+    async fn get_registration(&self, guild_id: i64) -> Result<Option<RegistrationStatus>, CommandError> {
+        self.timed("get_registration", self.inner.get_registration(guild_id)).await
+    }
+
+    async fn list_registrations(&self) -> Result<Vec<RegistrationStatus>, CommandError> {
+        self.timed("list_registrations", self.inner.list_registrations()).await
+    }
+
+    async fn record_registration(&self, guild_id: i64, hash: Option<&str>, error: Option<&str>) -> Result<(), CommandError> {
+        self.timed("record_registration", self.inner.record_registration(guild_id, hash, error)).await
+    }
+
+    async fn record_shard_status(&self, status: &ShardStatus) -> Result<(), CommandError> {
+        self.timed("record_shard_status", self.inner.record_shard_status(status)).await
+    }
+
+    async fn list_shard_statuses(&self) -> Result<Vec<ShardStatus>, CommandError> {
+        self.timed("list_shard_statuses", self.inner.list_shard_statuses()).await
+    }
+
+    async fn list_guild_usage(&self) -> Result<Vec<GuildUsage>, CommandError> {
+        self.timed("list_guild_usage", self.inner.list_guild_usage()).await
+    }
+
+    async fn database_size(&self) -> Result<Option<i64>, CommandError> {
+        self.timed("database_size", self.inner.database_size()).await
+    }
+
+    async fn purge_guild(&self, guild_id: i64) -> Result<u64, CommandError> {
+        self.timed("purge_guild", self.inner.purge_guild(guild_id)).await
+    }
+
+    async fn record_guild_departure(&self, guild_id: i64, left_at: i64, purge_at: i64) -> Result<(), CommandError> {
+        self.timed("record_guild_departure", self.inner.record_guild_departure(guild_id, left_at, purge_at)).await
+    }
+
+    async fn cancel_guild_departure(&self, guild_id: i64) -> Result<bool, CommandError> {
+        self.timed("cancel_guild_departure", self.inner.cancel_guild_departure(guild_id)).await
+    }
+
+    async fn list_guild_departures(&self) -> Result<Vec<GuildDeparture>, CommandError> {
+        self.timed("list_guild_departures", self.inner.list_guild_departures()).await
+    }
+
+    async fn record_author(&self, guild_id: i64, name: &str, user_id: i64) -> Result<(), CommandError> {
+        self.timed("record_author", self.inner.record_author(guild_id, name, user_id)).await
+    }
+
+    async fn list_authored_commands(&self, user_id: i64) -> Result<Vec<AuthoredCommand>, CommandError> {
+        self.timed("list_authored_commands", self.inner.list_authored_commands(user_id)).await
+    }
+
+    async fn forget_user(&self, user_id: i64) -> Result<u64, CommandError> {
+        self.timed("forget_user", self.inner.forget_user(user_id)).await
+    }
+
+    async fn add_schedule(&self, schedule: &Schedule) -> Result<i64, CommandError> {
+        self.timed("add_schedule", self.inner.add_schedule(schedule)).await
+    }
+
+    async fn list_schedules(&self, guild_id: i64) -> Result<Vec<Schedule>, CommandError> {
+        self.timed("list_schedules", self.inner.list_schedules(guild_id)).await
+    }
+
+    async fn remove_schedule(&self, guild_id: i64, id: i64) -> Result<bool, CommandError> {
+        self.timed("remove_schedule", self.inner.remove_schedule(guild_id, id)).await
+    }
+
+    async fn due_schedules(&self, now: i64) -> Result<Vec<Schedule>, CommandError> {
+        self.timed("due_schedules", self.inner.due_schedules(now)).await
+    }
+
+    async fn claim_schedule(&self, id: i64, run_at: i64, next: Option<i64>, now: i64) -> Result<bool, CommandError> {
+        self.timed("claim_schedule", self.inner.claim_schedule(id, run_at, next, now)).await
+    }
+
+    async fn record_schedule_result(&self, id: i64, error: Option<&str>) -> Result<(), CommandError> {
+        self.timed("record_schedule_result", self.inner.record_schedule_result(id, error)).await
+    }
+
+    async fn add_reminder(&self, reminder: &Reminder) -> Result<i64, CommandError> {
+        self.timed("add_reminder", self.inner.add_reminder(reminder)).await
+    }
+
+    async fn list_reminders(&self, user_id: i64) -> Result<Vec<Reminder>, CommandError> {
+        self.timed("list_reminders", self.inner.list_reminders(user_id)).await
+    }
+
+    async fn cancel_reminder(&self, user_id: i64, id: i64) -> Result<bool, CommandError> {
+        self.timed("cancel_reminder", self.inner.cancel_reminder(user_id, id)).await
+    }
+
+    async fn due_reminders(&self, now: i64) -> Result<Vec<Reminder>, CommandError> {
+        self.timed("due_reminders", self.inner.due_reminders(now)).await
+    }
+
+    async fn claim_reminder(&self, id: i64) -> Result<bool, CommandError> {
+        self.timed("claim_reminder", self.inner.claim_reminder(id)).await
+    }
+
+    async fn get_user_timezone(&self, user_id: i64) -> Result<Option<String>, CommandError> {
+        self.timed("get_user_timezone", self.inner.get_user_timezone(user_id)).await
+    }
+
+    async fn set_user_timezone(&self, user_id: i64, timezone: &str) -> Result<(), CommandError> {
+        self.timed("set_user_timezone", self.inner.set_user_timezone(user_id, timezone)).await
+    }
+
+    async fn get_greeting(&self, guild_id: i64) -> Result<Option<Greeting>, CommandError> {
+        self.timed("get_greeting", self.inner.get_greeting(guild_id)).await
+    }
+
+    async fn set_greeting(&self, greeting: &Greeting) -> Result<(), CommandError> {
+        self.timed("set_greeting", self.inner.set_greeting(greeting)).await
+    }
+}
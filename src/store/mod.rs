@@ -0,0 +1,188 @@
+pub mod memory;
+pub mod metered;
+pub mod postgres;
+pub mod sqlite;
+#[cfg(test)]
+mod tests;
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serenity::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::PgPool;
+
+use crate::commands::{Command, CommandError, Delivery, SlashOptions};
+use crate::greetings::Greeting;
+use crate::health::ShardStatus;
+use crate::list::ListSort;
+use crate::registration::RegistrationStatus;
+use crate::reminders::Reminder;
+use crate::scheduler::Schedule;
+use crate::scopes::{Scope, ScopeKind};
+
+pub use memory::MemoryCommandStore;
+pub use metered::MeteredCommandStore;
+pub use postgres::PgCommandStore;
+pub use sqlite::SqliteCommandStore;
+
+// 部分一致の LIKE パターン。入力中の % と _ はワイルドカードにしない (ESCAPE '\' と組み合わせて使う)
+fn like_pattern(filter: &str) -> String {
+    let escaped = filter.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+// DATABASE_URL のスキームに応じてストアを開き、マイグレーションを実行する
+// - postgres://... : Postgres (migrations/)
+// - sqlite:...     : SQLite (migrations_sqlite/。ファイルがなければ作成する)
+// - memory:        : DB を使わずメモリ上に保持する
+// DB を使うストアは操作ごとの所要時間をメトリクスに記録する
+pub async fn connect(database_url: &str) -> Result<Arc<dyn CommandStore>, String> {
+    if database_url.starts_with("memory:") {
+        tracing::info!("Using in-memory command store; data will be lost on restart");
+        return Ok(Arc::new(MemoryCommandStore::new()));
+    }
+
+    if database_url.starts_with("sqlite:") {
+        let options = SqliteConnectOptions::from_str(database_url)
+            .map_err(|e| format!("Invalid SQLite url: {}", e))?
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(|e| format!("DB接続失敗: {}", e))?;
+        tracing::info!("Running database migrations (SQLite)...");
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .map_err(|e| format!("Migration failed: {}", e))?;
+        tracing::info!("Migrations completed successfully!");
+        return Ok(Arc::new(MeteredCommandStore::new(Arc::new(SqliteCommandStore::new(pool)), "sqlite")));
+    }
+
+    let pool = PgPool::connect(database_url).await.map_err(|e| format!("DB接続失敗: {}", e))?;
+    tracing::info!("Running database migrations...");
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .map_err(|e| format!("Migration failed: {}", e))?;
+    tracing::info!("Migrations completed successfully!");
+    Ok(Arc::new(MeteredCommandStore::new(Arc::new(PgCommandStore::new(pool)), "postgres")))
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct GuildUsage {
+    pub guild_id: i64,
+    pub commands: i64,
+    pub scopes: i64,
+    // コマンド名と返答のバイト数の合計
+    pub bytes: i64,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct GuildDeparture {
+    pub guild_id: i64,
+    pub left_at: i64,
+    pub purge_at: i64,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AuthoredCommand {
+    pub guild_id: i64,
+    pub name: String,
+    // このユーザが追加した
+    pub created: bool,
+    // このユーザが最後に更新した
+    pub updated: bool,
+}
+
+// コマンドとその付随データ (スコープ、スラッシュコマンドの登録状況) の保存先
+// Bot と Web はすべてこのトレイト経由でアクセスする
+// 失敗はすべて Err(CommandError) で返し、ストアの中では記録しない (呼び出し元で log / report する)
+#[async_trait]
+pub trait CommandStore: Send + Sync {
+    // 保存先に接続できるか (/healthz 用)
+    async fn ping(&self) -> Result<(), String>;
+    // 接続を閉じる (終了処理の最後に呼ぶ。実行中のクエリの完了を待つ)
+    async fn close(&self);
+
+    // 存在しない場合は Ok(None)
+    async fn get_command(&self, guild_id: i64, name: &str) -> Result<Option<Command>, CommandError>;
+    // filter はコマンド名または返答の部分一致 (大文字小文字を区別しない)
+    async fn list_commands(&self, guild_id: i64, filter: Option<&str>, sort: ListSort) -> Result<Vec<Command>, CommandError>;
+    async fn list_slash_commands(&self, guild_id: i64) -> Result<Vec<Command>, CommandError>;
+    // コマンドが 1 つ以上登録されているギルド
+    async fn list_guild_ids(&self) -> Result<Vec<i64>, CommandError>;
+    async fn add_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError>;
+    async fn update_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError>;
+    // コマンドのスコープ設定も合わせて削除される
+    async fn remove_command(&self, guild_id: i64, name: &str) -> Result<(), CommandError>;
+    async fn set_delivery(&self, guild_id: i64, name: &str, delivery: &Delivery) -> Result<(), CommandError>;
+    async fn set_slash(&self, guild_id: i64, name: &str, options: &SlashOptions) -> Result<(), CommandError>;
+
+    async fn get_scopes(&self, guild_id: i64, name: &str) -> Result<Vec<Scope>, CommandError>;
+    async fn list_guild_scopes(&self, guild_id: i64) -> Result<Vec<Scope>, CommandError>;
+    // 同じ対象が既にあれば allow/deny を上書きする
+    async fn set_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64, allow: bool) -> Result<(), CommandError>;
+    async fn remove_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64) -> Result<(), CommandError>;
+    async fn clear_scopes(&self, guild_id: i64, name: &str) -> Result<(), CommandError>;
+
+    async fn get_registration(&self, guild_id: i64) -> Result<Option<RegistrationStatus>, CommandError>;
+    async fn list_registrations(&self) -> Result<Vec<RegistrationStatus>, CommandError>;
+    // hash が None の場合は前回のハッシュを残す
+    async fn record_registration(&self, guild_id: i64, hash: Option<&str>, error: Option<&str>) -> Result<(), CommandError>;
+
+    // ギルドごとのコマンド数・スコープ数・データ量 (管理画面用)
+    async fn list_guild_usage(&self) -> Result<Vec<GuildUsage>, CommandError>;
+    // DB 全体の大きさ (バイト)。求められない場合は None
+    async fn database_size(&self) -> Result<Option<i64>, CommandError>;
+    // ギルドのコマンド・スコープ・登録状況・予約投稿・リマインダー・入退室メッセージ・削除予定をすべて削除し、削除したコマンド数を返す
+    async fn purge_guild(&self, guild_id: i64) -> Result<u64, CommandError>;
+
+    // Bot が退出したギルドを記録する (purge_at 以降にデータを削除する。時刻は UNIX 秒)
+    async fn record_guild_departure(&self, guild_id: i64, left_at: i64, purge_at: i64) -> Result<(), CommandError>;
+    // 再招待された場合に削除の予定を取り消す。予定があった場合は true
+    async fn cancel_guild_departure(&self, guild_id: i64) -> Result<bool, CommandError>;
+    async fn list_guild_departures(&self) -> Result<Vec<GuildDeparture>, CommandError>;
+
+    // コマンドを追加・更新したユーザを記録する (追加したユーザは最初の記録を残す)
+    async fn record_author(&self, guild_id: i64, name: &str, user_id: i64) -> Result<(), CommandError>;
+    // ユーザが追加・更新したコマンド
+    async fn list_authored_commands(&self, user_id: i64) -> Result<Vec<AuthoredCommand>, CommandError>;
+    // ユーザの記録 (予約投稿の作成者を含む)・リマインダー・設定を消し、記録を消したコマンド数を返す (コマンド自体はギルドのものなので残す)
+    async fn forget_user(&self, user_id: i64) -> Result<u64, CommandError>;
+
+    // 予約投稿を追加し、振られた ID を返す (schedule.id は無視する)
+    async fn add_schedule(&self, schedule: &Schedule) -> Result<i64, CommandError>;
+    async fn list_schedules(&self, guild_id: i64) -> Result<Vec<Schedule>, CommandError>;
+    // 削除した場合は true
+    async fn remove_schedule(&self, guild_id: i64, id: i64) -> Result<bool, CommandError>;
+    // next_run_at が now 以前の予約 (古い順に最大 100 件)
+    async fn due_schedules(&self, now: i64) -> Result<Vec<Schedule>, CommandError>;
+    // next_run_at がまだ run_at のままなら次回を next に進め (None なら削除し)、true を返す
+    // 複数のプロセスで同じ回を送らないよう、true を返したプロセスだけが送る
+    async fn claim_schedule(&self, id: i64, run_at: i64, next: Option<i64>, now: i64) -> Result<bool, CommandError>;
+    // 送信結果を記録する (error が None なら前回のエラーを消す)
+    async fn record_schedule_result(&self, id: i64, error: Option<&str>) -> Result<(), CommandError>;
+
+    // リマインダーを追加し、振られた ID を返す (reminder.id は無視する)
+    async fn add_reminder(&self, reminder: &Reminder) -> Result<i64, CommandError>;
+    async fn list_reminders(&self, user_id: i64) -> Result<Vec<Reminder>, CommandError>;
+    // 本人のリマインダーを取り消す。取り消した場合は true
+    async fn cancel_reminder(&self, user_id: i64, id: i64) -> Result<bool, CommandError>;
+    // remind_at が now 以前のリマインダー (古い順に最大 100 件)
+    async fn due_reminders(&self, now: i64) -> Result<Vec<Reminder>, CommandError>;
+    // リマインダーを削除して送る権利を得る。他のプロセスが先に削除していれば false
+    async fn claim_reminder(&self, id: i64) -> Result<bool, CommandError>;
+    async fn get_user_timezone(&self, user_id: i64) -> Result<Option<String>, CommandError>;
+    async fn set_user_timezone(&self, user_id: i64, timezone: &str) -> Result<(), CommandError>;
+
+    // 入退室メッセージの設定 (未設定なら None)
+    async fn get_greeting(&self, guild_id: i64) -> Result<Option<Greeting>, CommandError>;
+    // 設定を上書きする
+    async fn set_greeting(&self, greeting: &Greeting) -> Result<(), CommandError>;
+
+    // シャードの状態を上書きする。シャード数が減った場合に残った古いシャードも削除する
+    async fn record_shard_status(&self, status: &ShardStatus) -> Result<(), CommandError>;
+    async fn list_shard_statuses(&self) -> Result<Vec<ShardStatus>, CommandError>;
+}
@@ -0,0 +1,493 @@
+use serenity::async_trait;
+use sqlx::PgPool;
+
+use super::{AuthoredCommand, CommandStore, GuildDeparture, GuildUsage};
+use crate::commands::{Command, CommandError, Delivery, SlashOptions};
+use crate::greetings::Greeting;
+use crate::health::ShardStatus;
+use crate::list::ListSort;
+use crate::registration::RegistrationStatus;
+use crate::reminders::Reminder;
+use crate::scheduler::Schedule;
+use crate::scopes::{Scope, ScopeKind};
+
+const COMMAND_COLUMNS: &str = "guild_id, name, response, reply_mode, target_channel_id, delete_trigger, delete_after, slash, description, arguments";
+const GREETING_COLUMNS: &str = "guild_id, welcome_enabled, welcome_channel_id, welcome_message, welcome_dm, auto_role_id, farewell_enabled, farewell_channel_id, farewell_message";
+const REMINDER_COLUMNS: &str = "id, user_id, guild_id, channel_id, content, remind_at";
+const SCHEDULE_COLUMNS: &str = "id, guild_id, channel_id, cron, timezone, content, command_name, next_run_at, last_run_at, last_error, created_by";
+
+pub struct PgCommandStore {
+    pool: PgPool,
+}
+
+impl PgCommandStore {
+    pub fn new(pool: PgPool) -> PgCommandStore {
+        PgCommandStore { pool }
+    }
+}
+
+// 更新系クエリの影響行数が 0 の場合は存在しないものとして扱う
+fn expect_affected(result: sqlx::postgres::PgQueryResult, err: CommandError) -> Result<(), CommandError> {
+    if result.rows_affected() == 0 { Err(err) } else { Ok(()) }
+}
+
+#[async_trait]
+impl CommandStore for PgCommandStore {
+    async fn ping(&self) -> Result<(), String> {
+        sqlx::query("SELECT 1").execute(&self.pool).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    async fn get_command(&self, guild_id: i64, name: &str) -> Result<Option<Command>, CommandError> {
+        let command = sqlx::query_as::<_, Command>(&format!("SELECT {COMMAND_COLUMNS} FROM commands WHERE guild_id = $1 AND name = $2"))
+            .bind(guild_id)
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(command)
+    }
+
+    async fn list_commands(&self, guild_id: i64, filter: Option<&str>, sort: ListSort) -> Result<Vec<Command>, CommandError> {
+        let order_by = match sort {
+            ListSort::Name => "name",
+            ListSort::Newest => "created_at DESC, name",
+            ListSort::Oldest => "created_at ASC, name",
+        };
+        let commands = match filter {
+            Some(f) => sqlx::query_as::<_, Command>(&format!(
+                "SELECT {COMMAND_COLUMNS} FROM commands WHERE guild_id = $1 AND (name ILIKE $2 ESCAPE '\\' OR response ILIKE $2 ESCAPE '\\') ORDER BY {order_by}"
+            ))
+            .bind(guild_id)
+            .bind(super::like_pattern(f))
+            .fetch_all(&self.pool)
+            .await?,
+            None => sqlx::query_as::<_, Command>(&format!("SELECT {COMMAND_COLUMNS} FROM commands WHERE guild_id = $1 ORDER BY {order_by}"))
+                .bind(guild_id)
+                .fetch_all(&self.pool)
+                .await?,
+        };
+        Ok(commands)
+    }
+
+    async fn list_slash_commands(&self, guild_id: i64) -> Result<Vec<Command>, CommandError> {
+        let commands = sqlx::query_as::<_, Command>(&format!("SELECT {COMMAND_COLUMNS} FROM commands WHERE guild_id = $1 AND slash ORDER BY name"))
+            .bind(guild_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(commands)
+    }
+
+    async fn list_guild_ids(&self) -> Result<Vec<i64>, CommandError> {
+        let rows = sqlx::query_scalar::<_, i64>("SELECT DISTINCT guild_id FROM commands")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn add_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError> {
+        let result = sqlx::query("INSERT INTO commands (guild_id, name, response) VALUES ($1, $2, $3) ON CONFLICT (guild_id, name) DO NOTHING")
+            .bind(guild_id)
+            .bind(name)
+            .bind(response)
+            .execute(&self.pool)
+            .await?;
+        expect_affected(result, CommandError::AlreadyExists)
+    }
+
+    async fn update_command(&self, guild_id: i64, name: &str, response: &str) -> Result<(), CommandError> {
+        let result = sqlx::query("UPDATE commands SET response = $3 WHERE guild_id = $1 AND name = $2")
+            .bind(guild_id)
+            .bind(name)
+            .bind(response)
+            .execute(&self.pool)
+            .await?;
+        expect_affected(result, CommandError::NotFound)
+    }
+
+    async fn remove_command(&self, guild_id: i64, name: &str) -> Result<(), CommandError> {
+        // スコープは外部キーの ON DELETE CASCADE で削除される
+        let result = sqlx::query("DELETE FROM commands WHERE guild_id = $1 AND name = $2")
+            .bind(guild_id)
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        expect_affected(result, CommandError::NotFound)
+    }
+
+    async fn set_delivery(&self, guild_id: i64, name: &str, delivery: &Delivery) -> Result<(), CommandError> {
+        let result = sqlx::query("UPDATE commands SET reply_mode = $3, target_channel_id = $4, delete_trigger = $5, delete_after = $6 WHERE guild_id = $1 AND name = $2")
+            .bind(guild_id)
+            .bind(name)
+            .bind(delivery.reply_mode.as_str())
+            .bind(delivery.target_channel_id)
+            .bind(delivery.delete_trigger)
+            .bind(delivery.delete_after)
+            .execute(&self.pool)
+            .await?;
+        expect_affected(result, CommandError::NotFound)
+    }
+
+    async fn set_slash(&self, guild_id: i64, name: &str, options: &SlashOptions) -> Result<(), CommandError> {
+        let result = sqlx::query("UPDATE commands SET slash = $3, description = $4, arguments = $5 WHERE guild_id = $1 AND name = $2")
+            .bind(guild_id)
+            .bind(name)
+            .bind(options.enabled)
+            .bind(options.description.as_deref())
+            .bind(&options.arguments)
+            .execute(&self.pool)
+            .await?;
+        expect_affected(result, CommandError::NotFound)
+    }
+
+    async fn get_scopes(&self, guild_id: i64, name: &str) -> Result<Vec<Scope>, CommandError> {
+        let rows = sqlx::query_as::<_, Scope>("SELECT guild_id, name, kind, target_id, allow FROM command_scopes WHERE guild_id = $1 AND name = $2 ORDER BY kind, target_id")
+            .bind(guild_id)
+            .bind(name)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn list_guild_scopes(&self, guild_id: i64) -> Result<Vec<Scope>, CommandError> {
+        let rows = sqlx::query_as::<_, Scope>("SELECT guild_id, name, kind, target_id, allow FROM command_scopes WHERE guild_id = $1 ORDER BY name, kind, target_id")
+            .bind(guild_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn set_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64, allow: bool) -> Result<(), CommandError> {
+        let result = sqlx::query("INSERT INTO command_scopes (guild_id, name, kind, target_id, allow) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (guild_id, name, kind, target_id) DO UPDATE SET allow = EXCLUDED.allow")
+            .bind(guild_id)
+            .bind(name)
+            .bind(kind.as_str())
+            .bind(target_id)
+            .bind(allow)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            // コマンドが存在しない場合は外部キー制約違反になる
+            Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => Err(CommandError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn remove_scope(&self, guild_id: i64, name: &str, kind: ScopeKind, target_id: i64) -> Result<(), CommandError> {
+        let result = sqlx::query("DELETE FROM command_scopes WHERE guild_id = $1 AND name = $2 AND kind = $3 AND target_id = $4")
+            .bind(guild_id)
+            .bind(name)
+            .bind(kind.as_str())
+            .bind(target_id)
+            .execute(&self.pool)
+            .await?;
+        expect_affected(result, CommandError::NotFound)
+    }
+
+    async fn clear_scopes(&self, guild_id: i64, name: &str) -> Result<(), CommandError> {
+        sqlx::query("DELETE FROM command_scopes WHERE guild_id = $1 AND name = $2")
+            .bind(guild_id)
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_registration(&self, guild_id: i64) -> Result<Option<RegistrationStatus>, CommandError> {
+        let row = sqlx::query_as::<_, RegistrationStatus>("SELECT guild_id, command_hash, status, error, to_char(updated_at, 'YYYY-MM-DD HH24:MI:SS') AS updated_at FROM guild_registrations WHERE guild_id = $1")
+            .bind(guild_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    async fn list_registrations(&self) -> Result<Vec<RegistrationStatus>, CommandError> {
+        let rows = sqlx::query_as::<_, RegistrationStatus>("SELECT guild_id, command_hash, status, error, to_char(updated_at, 'YYYY-MM-DD HH24:MI:SS') AS updated_at FROM guild_registrations ORDER BY guild_id")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn record_registration(&self, guild_id: i64, hash: Option<&str>, error: Option<&str>) -> Result<(), CommandError> {
+        let status = if error.is_some() { "error" } else { "ok" };
+        sqlx::query(
+            "INSERT INTO guild_registrations (guild_id, command_hash, status, error, updated_at) VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP) \
+             ON CONFLICT (guild_id) DO UPDATE SET command_hash = COALESCE(EXCLUDED.command_hash, guild_registrations.command_hash), status = EXCLUDED.status, error = EXCLUDED.error, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(guild_id)
+        .bind(hash)
+        .bind(status)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_shard_status(&self, status: &ShardStatus) -> Result<(), CommandError> {
+        sqlx::query(
+            "INSERT INTO gateway_shards (shard_id, shard_total, stage, latency_ms, guilds, updated_at) VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP) \
+             ON CONFLICT (shard_id) DO UPDATE SET shard_total = EXCLUDED.shard_total, stage = EXCLUDED.stage, latency_ms = EXCLUDED.latency_ms, guilds = EXCLUDED.guilds, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(status.shard_id)
+        .bind(status.shard_total)
+        .bind(&status.stage)
+        .bind(status.latency_ms)
+        .bind(status.guilds)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("DELETE FROM gateway_shards WHERE shard_id >= $1").bind(status.shard_total).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn list_shard_statuses(&self) -> Result<Vec<ShardStatus>, CommandError> {
+        let rows = sqlx::query_as::<_, ShardStatus>("SELECT shard_id, shard_total, stage, latency_ms, guilds, to_char(updated_at, 'YYYY-MM-DD HH24:MI:SS') AS updated_at FROM gateway_shards ORDER BY shard_id")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn list_guild_usage(&self) -> Result<Vec<GuildUsage>, CommandError> {
+        let rows = sqlx::query_as::<_, GuildUsage>(
+            "SELECT c.guild_id, COUNT(*) AS commands, \
+             (SELECT COUNT(*) FROM command_scopes s WHERE s.guild_id = c.guild_id) AS scopes, \
+             COALESCE(SUM(octet_length(c.name) + octet_length(c.response)), 0)::BIGINT AS bytes \
+             FROM commands c GROUP BY c.guild_id ORDER BY c.guild_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn database_size(&self) -> Result<Option<i64>, CommandError> {
+        let size = sqlx::query_scalar::<_, i64>("SELECT pg_database_size(current_database())").fetch_one(&self.pool).await?;
+        Ok(Some(size))
+    }
+
+    async fn purge_guild(&self, guild_id: i64) -> Result<u64, CommandError> {
+        // スコープは外部キーの ON DELETE CASCADE で削除される
+        let mut tx = self.pool.begin().await?;
+        let removed = sqlx::query("DELETE FROM commands WHERE guild_id = $1").bind(guild_id).execute(&mut *tx).await?.rows_affected();
+        sqlx::query("DELETE FROM guild_registrations WHERE guild_id = $1").bind(guild_id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM guild_departures WHERE guild_id = $1").bind(guild_id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM scheduled_messages WHERE guild_id = $1").bind(guild_id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM reminders WHERE guild_id = $1").bind(guild_id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM greetings WHERE guild_id = $1").bind(guild_id).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    async fn record_guild_departure(&self, guild_id: i64, left_at: i64, purge_at: i64) -> Result<(), CommandError> {
+        sqlx::query(
+            "INSERT INTO guild_departures (guild_id, left_at, purge_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (guild_id) DO UPDATE SET left_at = EXCLUDED.left_at, purge_at = EXCLUDED.purge_at",
+        )
+        .bind(guild_id)
+        .bind(left_at)
+        .bind(purge_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn cancel_guild_departure(&self, guild_id: i64) -> Result<bool, CommandError> {
+        let result = sqlx::query("DELETE FROM guild_departures WHERE guild_id = $1")
+            .bind(guild_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_guild_departures(&self) -> Result<Vec<GuildDeparture>, CommandError> {
+        let rows = sqlx::query_as::<_, GuildDeparture>("SELECT guild_id, left_at, purge_at FROM guild_departures ORDER BY purge_at, guild_id")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn record_author(&self, guild_id: i64, name: &str, user_id: i64) -> Result<(), CommandError> {
+        sqlx::query("UPDATE commands SET created_by = COALESCE(created_by, $3), updated_by = $3 WHERE guild_id = $1 AND name = $2")
+            .bind(guild_id)
+            .bind(name)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_authored_commands(&self, user_id: i64) -> Result<Vec<AuthoredCommand>, CommandError> {
+        let rows = sqlx::query_as::<_, AuthoredCommand>(
+            "SELECT guild_id, name, COALESCE(created_by = $1, FALSE) AS created, COALESCE(updated_by = $1, FALSE) AS updated \
+             FROM commands WHERE created_by = $1 OR updated_by = $1 ORDER BY guild_id, name",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+
+    async fn forget_user(&self, user_id: i64) -> Result<u64, CommandError> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE commands SET \
+             created_by = CASE WHEN created_by = $1 THEN NULL ELSE created_by END, \
+             updated_by = CASE WHEN updated_by = $1 THEN NULL ELSE updated_by END \
+             WHERE created_by = $1 OR updated_by = $1",
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE scheduled_messages SET created_by = NULL WHERE created_by = $1").bind(user_id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM reminders WHERE user_id = $1").bind(user_id).execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM user_settings WHERE user_id = $1").bind(user_id).execute(&mut *tx).await?;
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn add_schedule(&self, schedule: &Schedule) -> Result<i64, CommandError> {
+        let id = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO scheduled_messages (guild_id, channel_id, cron, timezone, content, command_name, next_run_at, created_by) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+        )
+        .bind(schedule.guild_id)
+        .bind(schedule.channel_id)
+        .bind(&schedule.cron)
+        .bind(&schedule.timezone)
+        .bind(&schedule.content)
+        .bind(&schedule.command_name)
+        .bind(schedule.next_run_at)
+        .bind(schedule.created_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn list_schedules(&self, guild_id: i64) -> Result<Vec<Schedule>, CommandError> {
+        let rows = sqlx::query_as::<_, Schedule>(&format!("SELECT {} FROM scheduled_messages WHERE guild_id = $1 ORDER BY next_run_at, id", SCHEDULE_COLUMNS))
+            .bind(guild_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn remove_schedule(&self, guild_id: i64, id: i64) -> Result<bool, CommandError> {
+        let result = sqlx::query("DELETE FROM scheduled_messages WHERE guild_id = $1 AND id = $2").bind(guild_id).bind(id).execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn due_schedules(&self, now: i64) -> Result<Vec<Schedule>, CommandError> {
+        let rows = sqlx::query_as::<_, Schedule>(&format!("SELECT {} FROM scheduled_messages WHERE next_run_at <= $1 ORDER BY next_run_at, id LIMIT 100", SCHEDULE_COLUMNS))
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn claim_schedule(&self, id: i64, run_at: i64, next: Option<i64>, now: i64) -> Result<bool, CommandError> {
+        let result = match next {
+            Some(next) => {
+                sqlx::query("UPDATE scheduled_messages SET next_run_at = $3, last_run_at = $4 WHERE id = $1 AND next_run_at = $2")
+                    .bind(id)
+                    .bind(run_at)
+                    .bind(next)
+                    .bind(now)
+                    .execute(&self.pool)
+                    .await?
+            }
+            None => sqlx::query("DELETE FROM scheduled_messages WHERE id = $1 AND next_run_at = $2").bind(id).bind(run_at).execute(&self.pool).await?,
+        };
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn record_schedule_result(&self, id: i64, error: Option<&str>) -> Result<(), CommandError> {
+        sqlx::query("UPDATE scheduled_messages SET last_error = $2 WHERE id = $1").bind(id).bind(error).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn add_reminder(&self, reminder: &Reminder) -> Result<i64, CommandError> {
+        let id = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO reminders (user_id, guild_id, channel_id, content, remind_at) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+        )
+        .bind(reminder.user_id)
+        .bind(reminder.guild_id)
+        .bind(reminder.channel_id)
+        .bind(&reminder.content)
+        .bind(reminder.remind_at)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn list_reminders(&self, user_id: i64) -> Result<Vec<Reminder>, CommandError> {
+        let rows = sqlx::query_as::<_, Reminder>(&format!("SELECT {} FROM reminders WHERE user_id = $1 ORDER BY remind_at, id", REMINDER_COLUMNS))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn cancel_reminder(&self, user_id: i64, id: i64) -> Result<bool, CommandError> {
+        let result = sqlx::query("DELETE FROM reminders WHERE user_id = $1 AND id = $2").bind(user_id).bind(id).execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn due_reminders(&self, now: i64) -> Result<Vec<Reminder>, CommandError> {
+        let rows = sqlx::query_as::<_, Reminder>(&format!("SELECT {} FROM reminders WHERE remind_at <= $1 ORDER BY remind_at, id LIMIT 100", REMINDER_COLUMNS))
+            .bind(now)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    async fn claim_reminder(&self, id: i64) -> Result<bool, CommandError> {
+        let result = sqlx::query("DELETE FROM reminders WHERE id = $1").bind(id).execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_user_timezone(&self, user_id: i64) -> Result<Option<String>, CommandError> {
+        let row = sqlx::query_scalar::<_, String>("SELECT timezone FROM user_settings WHERE user_id = $1").bind(user_id).fetch_optional(&self.pool).await?;
+        Ok(row)
+    }
+
+    async fn set_user_timezone(&self, user_id: i64, timezone: &str) -> Result<(), CommandError> {
+        sqlx::query("INSERT INTO user_settings (user_id, timezone) VALUES ($1, $2) ON CONFLICT (user_id) DO UPDATE SET timezone = EXCLUDED.timezone")
+            .bind(user_id)
+            .bind(timezone)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_greeting(&self, guild_id: i64) -> Result<Option<Greeting>, CommandError> {
+        let row = sqlx::query_as::<_, Greeting>(&format!("SELECT {} FROM greetings WHERE guild_id = $1", GREETING_COLUMNS))
+            .bind(guild_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    async fn set_greeting(&self, greeting: &Greeting) -> Result<(), CommandError> {
+        sqlx::query(&format!(
+            "INSERT INTO greetings ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) \
+             ON CONFLICT (guild_id) DO UPDATE SET welcome_enabled = EXCLUDED.welcome_enabled, welcome_channel_id = EXCLUDED.welcome_channel_id, \
+             welcome_message = EXCLUDED.welcome_message, welcome_dm = EXCLUDED.welcome_dm, auto_role_id = EXCLUDED.auto_role_id, \
+             farewell_enabled = EXCLUDED.farewell_enabled, farewell_channel_id = EXCLUDED.farewell_channel_id, \
+             farewell_message = EXCLUDED.farewell_message, updated_at = CURRENT_TIMESTAMP",
+            GREETING_COLUMNS
+        ))
+        .bind(greeting.guild_id)
+        .bind(greeting.welcome_enabled)
+        .bind(greeting.welcome_channel_id)
+        .bind(&greeting.welcome_message)
+        .bind(greeting.welcome_dm)
+        .bind(greeting.auto_role_id)
+        .bind(greeting.farewell_enabled)
+        .bind(greeting.farewell_channel_id)
+        .bind(&greeting.farewell_message)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
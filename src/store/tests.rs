@@ -0,0 +1,416 @@
+// 各バックエンドが同じ振る舞いをすることを確認するテスト
+// Postgres は TEST_DATABASE_URL が設定されている場合のみ実行する
+
+use sqlx::sqlite::SqlitePoolOptions;
+
+use super::*;
+use crate::commands::ReplyMode;
+use crate::greetings::Greeting;
+use crate::reminders::Reminder;
+use crate::scheduler::Schedule;
+
+// Postgres ではテスト間で DB を共有するため、ギルド ID を実行ごとに変える
+fn guild_id() -> i64 {
+    rand::random::<u32>() as i64 + 1
+}
+
+fn names(commands: &[Command]) -> Vec<&str> {
+    commands.iter().map(|c| c.name.as_str()).collect()
+}
+
+async fn command_crud(store: &dyn CommandStore) {
+    let guild = guild_id();
+    store.ping().await.unwrap();
+    assert!(store.get_command(guild, "hello").await.unwrap().is_none());
+
+    store.add_command(guild, "hello", "world").await.unwrap();
+    assert!(matches!(store.add_command(guild, "hello", "again").await, Err(CommandError::AlreadyExists)));
+    let command = store.get_command(guild, "hello").await.unwrap().unwrap();
+    assert_eq!(command.response, "world");
+    assert_eq!(command.reply_mode(), ReplyMode::Reply);
+    assert!(!command.slash);
+    assert!(command.arguments.is_empty());
+
+    store.update_command(guild, "hello", "updated").await.unwrap();
+    assert_eq!(store.get_command(guild, "hello").await.unwrap().unwrap().response, "updated");
+    assert!(matches!(store.update_command(guild, "missing", "x").await, Err(CommandError::NotFound)));
+
+    // 別ギルドの同名コマンドとは独立している
+    let other = guild + 1;
+    store.add_command(other, "hello", "other").await.unwrap();
+    assert_eq!(store.get_command(guild, "hello").await.unwrap().unwrap().response, "updated");
+    let guilds = store.list_guild_ids().await.unwrap();
+    assert!(guilds.contains(&guild) && guilds.contains(&other));
+
+    store.remove_command(guild, "hello").await.unwrap();
+    assert!(store.get_command(guild, "hello").await.unwrap().is_none());
+    assert!(matches!(store.remove_command(guild, "hello").await, Err(CommandError::NotFound)));
+    assert!(!store.list_guild_ids().await.unwrap().contains(&guild));
+    store.remove_command(other, "hello").await.unwrap();
+}
+
+async fn list_filter_and_sort(store: &dyn CommandStore) {
+    let guild = guild_id();
+    store.add_command(guild, "beta", "second").await.unwrap();
+    store.add_command(guild, "alpha", "Hello there").await.unwrap();
+    store.add_command(guild, "gamma", "third").await.unwrap();
+
+    assert_eq!(names(&store.list_commands(guild, None, ListSort::Name).await.unwrap()), ["alpha", "beta", "gamma"]);
+    assert_eq!(names(&store.list_commands(guild, None, ListSort::Oldest).await.unwrap()), ["beta", "alpha", "gamma"]);
+    assert_eq!(names(&store.list_commands(guild, None, ListSort::Newest).await.unwrap()), ["gamma", "alpha", "beta"]);
+
+    // コマンド名・返答の部分一致 (大文字小文字を区別しない)
+    assert_eq!(names(&store.list_commands(guild, Some("HELLO"), ListSort::Name).await.unwrap()), ["alpha"]);
+    assert_eq!(names(&store.list_commands(guild, Some("ta"), ListSort::Name).await.unwrap()), ["beta"]);
+    assert!(store.list_commands(guild, Some("nothing"), ListSort::Name).await.unwrap().is_empty());
+    assert!(store.list_commands(guild + 1, None, ListSort::Name).await.unwrap().is_empty());
+
+    // % と _ はワイルドカードではなく文字として探す
+    store.add_command(guild, "sale", "100% off").await.unwrap();
+    store.add_command(guild, "snake_case", "x").await.unwrap();
+    assert_eq!(names(&store.list_commands(guild, Some("%"), ListSort::Name).await.unwrap()), ["sale"]);
+    assert_eq!(names(&store.list_commands(guild, Some("_"), ListSort::Name).await.unwrap()), ["snake_case"]);
+    assert!(store.list_commands(guild, Some("\\"), ListSort::Name).await.unwrap().is_empty());
+}
+
+async fn delivery_and_slash(store: &dyn CommandStore) {
+    let guild = guild_id();
+    store.add_command(guild, "greet", "hi {user}").await.unwrap();
+    store.add_command(guild, "plain", "text").await.unwrap();
+
+    let delivery = Delivery { reply_mode: ReplyMode::Channel, target_channel_id: Some(42), delete_trigger: true, delete_after: Some(30) };
+    store.set_delivery(guild, "greet", &delivery).await.unwrap();
+    assert!(matches!(store.set_delivery(guild, "missing", &delivery).await, Err(CommandError::NotFound)));
+    let command = store.get_command(guild, "greet").await.unwrap().unwrap();
+    assert_eq!(command.reply_mode(), ReplyMode::Channel);
+    assert_eq!(command.target_channel_id, Some(42));
+    assert!(command.delete_trigger);
+    assert_eq!(command.delete_after, Some(30));
+
+    let options = SlashOptions { enabled: true, description: Some("挨拶".to_string()), arguments: vec!["user".to_string(), "place".to_string()] };
+    store.set_slash(guild, "greet", &options).await.unwrap();
+    assert!(matches!(store.set_slash(guild, "missing", &options).await, Err(CommandError::NotFound)));
+    let slash = store.list_slash_commands(guild).await.unwrap();
+    assert_eq!(names(&slash), ["greet"]);
+    assert_eq!(slash[0].description.as_deref(), Some("挨拶"));
+    assert_eq!(slash[0].arguments, ["user", "place"]);
+
+    let disabled = SlashOptions { enabled: false, description: None, arguments: Vec::new() };
+    store.set_slash(guild, "greet", &disabled).await.unwrap();
+    assert!(store.list_slash_commands(guild).await.unwrap().is_empty());
+}
+
+async fn scopes(store: &dyn CommandStore) {
+    let guild = guild_id();
+    assert!(matches!(store.set_scope(guild, "missing", ScopeKind::Channel, 1, true).await, Err(CommandError::NotFound)));
+
+    store.add_command(guild, "a", "a").await.unwrap();
+    store.add_command(guild, "b", "b").await.unwrap();
+    store.set_scope(guild, "a", ScopeKind::Role, 20, true).await.unwrap();
+    store.set_scope(guild, "a", ScopeKind::Channel, 10, true).await.unwrap();
+    store.set_scope(guild, "b", ScopeKind::Category, 30, false).await.unwrap();
+    // 同じ対象は allow/deny を上書きする
+    store.set_scope(guild, "a", ScopeKind::Channel, 10, false).await.unwrap();
+
+    let scopes = store.get_scopes(guild, "a").await.unwrap();
+    let summary: Vec<(&str, i64, bool)> = scopes.iter().map(|s| (s.kind.as_str(), s.target_id, s.allow)).collect();
+    assert_eq!(summary, [("channel", 10, false), ("role", 20, true)]);
+    let all: Vec<String> = store.list_guild_scopes(guild).await.unwrap().into_iter().map(|s| s.name).collect();
+    assert_eq!(all, ["a", "a", "b"]);
+
+    store.remove_scope(guild, "a", ScopeKind::Role, 20).await.unwrap();
+    assert!(matches!(store.remove_scope(guild, "a", ScopeKind::Role, 20).await, Err(CommandError::NotFound)));
+    assert_eq!(store.get_scopes(guild, "a").await.unwrap().len(), 1);
+
+    store.clear_scopes(guild, "a").await.unwrap();
+    assert!(store.get_scopes(guild, "a").await.unwrap().is_empty());
+
+    // コマンドを削除するとスコープも消える
+    store.remove_command(guild, "b").await.unwrap();
+    assert!(store.list_guild_scopes(guild).await.unwrap().is_empty());
+}
+
+async fn registrations(store: &dyn CommandStore) {
+    let guild = guild_id();
+    assert!(store.get_registration(guild).await.unwrap().is_none());
+
+    store.record_registration(guild, Some("abc"), None).await.unwrap();
+    let status = store.get_registration(guild).await.unwrap().unwrap();
+    assert_eq!((status.status.as_str(), status.command_hash.as_deref(), status.error.as_deref()), ("ok", Some("abc"), None));
+
+    // 失敗時はハッシュを残したままエラーを記録する
+    store.record_registration(guild, None, Some("rate limited")).await.unwrap();
+    let status = store.get_registration(guild).await.unwrap().unwrap();
+    assert_eq!((status.status.as_str(), status.command_hash.as_deref(), status.error.as_deref()), ("error", Some("abc"), Some("rate limited")));
+    assert!(store.list_registrations().await.unwrap().iter().any(|s| s.guild_id == guild));
+}
+
+async fn usage_and_purge(store: &dyn CommandStore) {
+    let guild = guild_id();
+    let other = guild_id();
+    store.add_command(guild, "a", "xyz").await.unwrap();
+    // バイト数で数える (文字数ではない)
+    store.add_command(guild, "b", "あ").await.unwrap();
+    store.set_scope(guild, "a", ScopeKind::Channel, 10, true).await.unwrap();
+    store.add_command(other, "c", "c").await.unwrap();
+    store.record_registration(guild, Some("abc"), None).await.unwrap();
+
+    let usage = store.list_guild_usage().await.unwrap();
+    let find = |id: i64| usage.iter().find(|u| u.guild_id == id).cloned();
+    assert_eq!(find(guild), Some(GuildUsage { guild_id: guild, commands: 2, scopes: 1, bytes: 4 + 4 }));
+    assert!(store.database_size().await.unwrap().is_none_or(|size| size > 0));
+
+    assert_eq!(store.purge_guild(guild).await.unwrap(), 2);
+    assert!(store.list_commands(guild, None, ListSort::Name).await.unwrap().is_empty());
+    assert!(store.list_guild_scopes(guild).await.unwrap().is_empty());
+    assert!(store.get_registration(guild).await.unwrap().is_none());
+    assert!(store.list_guild_usage().await.unwrap().iter().all(|u| u.guild_id != guild));
+    // 他のギルドには影響しない
+    assert!(store.get_command(other, "c").await.unwrap().is_some());
+    assert_eq!(store.purge_guild(guild).await.unwrap(), 0);
+}
+
+async fn departures(store: &dyn CommandStore) {
+    let guild = guild_id();
+    store.add_command(guild, "a", "a").await.unwrap();
+    store.record_guild_departure(guild, 100, 200).await.unwrap();
+    // 再度退出した場合は上書きする
+    store.record_guild_departure(guild, 150, 250).await.unwrap();
+    let find = |departures: Vec<GuildDeparture>| departures.into_iter().find(|d| d.guild_id == guild);
+    assert_eq!(find(store.list_guild_departures().await.unwrap()), Some(GuildDeparture { guild_id: guild, left_at: 150, purge_at: 250 }));
+
+    assert!(store.cancel_guild_departure(guild).await.unwrap());
+    assert!(!store.cancel_guild_departure(guild).await.unwrap());
+    assert!(find(store.list_guild_departures().await.unwrap()).is_none());
+
+    // データを削除すると削除予定も消える
+    store.record_guild_departure(guild, 100, 200).await.unwrap();
+    store.purge_guild(guild).await.unwrap();
+    assert!(find(store.list_guild_departures().await.unwrap()).is_none());
+}
+
+async fn authors(store: &dyn CommandStore) {
+    let guild = guild_id();
+    let (alice, bob) = (guild_id(), guild_id());
+    store.add_command(guild, "a", "a").await.unwrap();
+    store.add_command(guild, "b", "b").await.unwrap();
+    store.record_author(guild, "a", alice).await.unwrap();
+    store.record_author(guild, "a", bob).await.unwrap();
+    store.record_author(guild, "b", alice).await.unwrap();
+    // 存在しないコマンドは無視する
+    store.record_author(guild, "missing", alice).await.unwrap();
+
+    let summary = |user: i64| async move {
+        store.list_authored_commands(user).await.unwrap().into_iter().map(|c| (c.name, c.created, c.updated)).collect::<Vec<_>>()
+    };
+    assert_eq!(summary(alice).await, [("a".to_string(), true, false), ("b".to_string(), true, true)]);
+    assert_eq!(summary(bob).await, [("a".to_string(), false, true)]);
+
+    assert_eq!(store.forget_user(alice).await.unwrap(), 2);
+    assert!(summary(alice).await.is_empty());
+    assert_eq!(summary(bob).await, [("a".to_string(), false, true)]);
+    assert_eq!(store.get_command(guild, "a").await.unwrap().unwrap().response, "a");
+
+    // コマンドを削除すると記録も消える
+    store.remove_command(guild, "a").await.unwrap();
+    assert!(summary(bob).await.is_empty());
+}
+
+async fn schedules(store: &dyn CommandStore) {
+    let guild = guild_id();
+    let user = guild_id();
+    let schedule = |next_run_at: i64, cron: Option<&str>| Schedule {
+        id: 0,
+        guild_id: guild,
+        channel_id: 10,
+        cron: cron.map(str::to_string),
+        timezone: "Asia/Tokyo".to_string(),
+        content: Some("hello".to_string()),
+        command_name: None,
+        next_run_at,
+        last_run_at: None,
+        last_error: None,
+        created_by: Some(user),
+    };
+    let once = store.add_schedule(&schedule(100, None)).await.unwrap();
+    let daily = store.add_schedule(&schedule(50, Some("0 9 * * *"))).await.unwrap();
+    assert_ne!(once, daily);
+    let listed = store.list_schedules(guild).await.unwrap();
+    assert_eq!(listed.iter().map(|s| s.id).collect::<Vec<_>>(), [daily, once]);
+    assert_eq!(listed[0], Schedule { id: daily, ..schedule(50, Some("0 9 * * *")) });
+    assert!(store.list_schedules(guild + 1).await.unwrap().is_empty());
+
+    // 期限が来たものだけ返す (Postgres では他のテストの予約も含まれうるのでギルドで絞る)
+    let due = |now: i64| async move { store.due_schedules(now).await.unwrap().into_iter().filter(|s| s.guild_id == guild).map(|s| s.id).collect::<Vec<_>>() };
+    assert_eq!(due(60).await, [daily]);
+
+    // 同じ回を取れるのは 1 回だけ
+    assert!(store.claim_schedule(daily, 50, Some(200), 60).await.unwrap());
+    assert!(!store.claim_schedule(daily, 50, Some(200), 60).await.unwrap());
+    store.record_schedule_result(daily, Some("Missing Access")).await.unwrap();
+    let claimed = store.list_schedules(guild).await.unwrap().into_iter().find(|s| s.id == daily).unwrap();
+    assert_eq!((claimed.next_run_at, claimed.last_run_at, claimed.last_error.as_deref()), (200, Some(60), Some("Missing Access")));
+    store.record_schedule_result(daily, None).await.unwrap();
+    assert!(store.list_schedules(guild).await.unwrap().iter().all(|s| s.last_error.is_none()));
+
+    // 1 回だけのものは取ると消える
+    assert!(store.claim_schedule(once, 100, None, 100).await.unwrap());
+    assert!(!store.claim_schedule(once, 100, None, 100).await.unwrap());
+    assert_eq!(due(300).await, [daily]);
+
+    // 他のギルドの予約は削除できない
+    assert!(!store.remove_schedule(guild + 1, daily).await.unwrap());
+    store.forget_user(user).await.unwrap();
+    assert_eq!(store.list_schedules(guild).await.unwrap()[0].created_by, None);
+    assert!(store.remove_schedule(guild, daily).await.unwrap());
+    assert!(!store.remove_schedule(guild, daily).await.unwrap());
+
+    // ギルドのデータを削除すると予約も消える
+    store.add_schedule(&schedule(100, None)).await.unwrap();
+    store.purge_guild(guild).await.unwrap();
+    assert!(store.list_schedules(guild).await.unwrap().is_empty());
+}
+
+async fn reminders(store: &dyn CommandStore) {
+    let guild = guild_id();
+    let (user, other) = (guild_id(), guild_id());
+    let reminder = |user_id: i64, channel_id: Option<i64>, remind_at: i64| Reminder {
+        id: 0,
+        user_id,
+        guild_id: Some(guild),
+        channel_id,
+        content: "tea".to_string(),
+        remind_at,
+    };
+    let later = store.add_reminder(&reminder(user, None, 200)).await.unwrap();
+    let sooner = store.add_reminder(&reminder(user, Some(10), 100)).await.unwrap();
+    store.add_reminder(&reminder(other, None, 100)).await.unwrap();
+    let listed = store.list_reminders(user).await.unwrap();
+    assert_eq!(listed.iter().map(|r| r.id).collect::<Vec<_>>(), [sooner, later]);
+    assert_eq!(listed[0], Reminder { id: sooner, ..reminder(user, Some(10), 100) });
+
+    // 本人のものだけ取り消せる
+    assert!(!store.cancel_reminder(other, later).await.unwrap());
+    assert!(store.cancel_reminder(user, later).await.unwrap());
+    assert!(!store.cancel_reminder(user, later).await.unwrap());
+
+    // Postgres では他のテストのリマインダーも含まれうるのでユーザで絞る
+    let due = |now: i64| async move { store.due_reminders(now).await.unwrap().into_iter().filter(|r| r.user_id == user).map(|r| r.id).collect::<Vec<_>>() };
+    assert!(due(99).await.is_empty());
+    assert_eq!(due(100).await, [sooner]);
+    assert!(store.claim_reminder(sooner).await.unwrap());
+    assert!(!store.claim_reminder(sooner).await.unwrap());
+    assert!(due(100).await.is_empty());
+
+    assert_eq!(store.get_user_timezone(user).await.unwrap(), None);
+    store.set_user_timezone(user, "UTC").await.unwrap();
+    store.set_user_timezone(user, "Asia/Tokyo").await.unwrap();
+    assert_eq!(store.get_user_timezone(user).await.unwrap().as_deref(), Some("Asia/Tokyo"));
+
+    // ユーザのデータを消すとリマインダーと設定も消える
+    store.add_reminder(&reminder(user, None, 300)).await.unwrap();
+    store.forget_user(user).await.unwrap();
+    assert!(store.list_reminders(user).await.unwrap().is_empty());
+    assert_eq!(store.get_user_timezone(user).await.unwrap(), None);
+
+    // ギルドのデータを削除するとそのギルドで作ったリマインダーも消える
+    store.purge_guild(guild).await.unwrap();
+    assert!(store.list_reminders(other).await.unwrap().is_empty());
+}
+
+async fn greetings(store: &dyn CommandStore) {
+    let guild = guild_id();
+    assert_eq!(store.get_greeting(guild).await.unwrap(), None);
+    let mut greeting = Greeting {
+        guild_id: guild,
+        welcome_enabled: true,
+        welcome_channel_id: Some(10),
+        welcome_message: "{user} さん、ようこそ".to_string(),
+        welcome_dm: true,
+        auto_role_id: Some(20),
+        farewell_enabled: false,
+        farewell_channel_id: None,
+        farewell_message: "さようなら".to_string(),
+    };
+    store.set_greeting(&greeting).await.unwrap();
+    assert_eq!(store.get_greeting(guild).await.unwrap().as_ref(), Some(&greeting));
+
+    // 上書きする
+    greeting.welcome_dm = false;
+    greeting.auto_role_id = None;
+    greeting.farewell_enabled = true;
+    greeting.farewell_channel_id = Some(11);
+    store.set_greeting(&greeting).await.unwrap();
+    assert_eq!(store.get_greeting(guild).await.unwrap().as_ref(), Some(&greeting));
+
+    store.purge_guild(guild).await.unwrap();
+    assert_eq!(store.get_greeting(guild).await.unwrap(), None);
+}
+
+async fn shard_statuses(store: &dyn CommandStore) {
+    let status = |shard_id: i64, shard_total: i64, stage: &str| ShardStatus {
+        shard_id,
+        shard_total,
+        stage: stage.to_string(),
+        latency_ms: Some(40),
+        guilds: 3,
+        updated_at: None,
+    };
+    let listed = |statuses: Vec<ShardStatus>| statuses.into_iter().map(|s| (s.shard_id, s.shard_total, s.stage)).collect::<Vec<_>>();
+
+    // シャード数 1 で記録すると他のシャードは消える (前回のテストの残りも含めて)
+    store.record_shard_status(&status(0, 1, "connecting")).await.unwrap();
+    store.record_shard_status(&status(2, 3, "connected")).await.unwrap();
+    store.record_shard_status(&status(0, 3, "connected")).await.unwrap();
+    assert_eq!(listed(store.list_shard_statuses().await.unwrap()), [(0, 3, "connected".to_string()), (2, 3, "connected".to_string())]);
+    let first = &store.list_shard_statuses().await.unwrap()[0];
+    assert_eq!((first.latency_ms, first.guilds), (Some(40), 3));
+
+    // シャード数を減らすと範囲外のシャードは削除される
+    store.record_shard_status(&status(0, 2, "resuming")).await.unwrap();
+    assert_eq!(listed(store.list_shard_statuses().await.unwrap()), [(0, 2, "resuming".to_string())]);
+}
+
+async fn run_all(store: &dyn CommandStore) {
+    command_crud(store).await;
+    list_filter_and_sort(store).await;
+    delivery_and_slash(store).await;
+    scopes(store).await;
+    registrations(store).await;
+    usage_and_purge(store).await;
+    departures(store).await;
+    authors(store).await;
+    schedules(store).await;
+    reminders(store).await;
+    greetings(store).await;
+    shard_statuses(store).await;
+}
+
+#[tokio::test]
+async fn memory_store() {
+    run_all(&MemoryCommandStore::new()).await;
+}
+
+#[tokio::test]
+async fn sqlite_store() {
+    // インメモリ DB は接続ごとに別物になるため、接続を 1 本に固定する
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations_sqlite").run(&pool).await.unwrap();
+    run_all(&SqliteCommandStore::new(pool)).await;
+}
+
+#[tokio::test]
+async fn postgres_store() {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL is not set; skipping Postgres store tests");
+        return;
+    };
+    let store = connect(&url).await.unwrap();
+    run_all(store.as_ref()).await;
+}
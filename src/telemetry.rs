@@ -0,0 +1,114 @@
+// ログ出力 (tracing) の初期化と、ログに載せる値の伏せ字処理
+//
+// - RUST_LOG: 出力レベル (例: `info`, `nkmzbot=debug,serenity=warn`。省略時は `info`)
+// - LOG_FORMAT: `json` で 1 行 1 JSON の構造化ログにする (省略時は人間向けのテキスト)
+// - LOG_MESSAGE_CONTENT: `1` でメッセージ本文をそのままログに出す (既定では伏せ字)
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt as subscriber_fmt, EnvFilter};
+
+static LOG_CONTENT: AtomicBool = AtomicBool::new(false);
+
+// 管理画面に表示する直近の警告・エラーの件数
+const RECENT_CAPACITY: usize = 50;
+static RECENT: Mutex<VecDeque<RecentLog>> = Mutex::new(VecDeque::new());
+
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = std::env::var("LOG_FORMAT").map(|v| v.eq_ignore_ascii_case("json")).unwrap_or(false);
+    LOG_CONTENT.store(
+        std::env::var("LOG_MESSAGE_CONTENT").map(|v| v == "1" || v.eq_ignore_ascii_case("true")).unwrap_or(false),
+        Ordering::Relaxed,
+    );
+
+    // 標準出力は CLI のサブコマンド (export など) の出力に使うため、ログは標準エラーに出す
+    let builder = subscriber_fmt().with_env_filter(filter).with_writer(std::io::stderr);
+    if json {
+        builder.json().with_current_span(true).with_span_list(false).finish().with(RecentLayer).init();
+    } else {
+        builder.finish().with(RecentLayer).init();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecentLog {
+    pub at: Instant,
+    pub level: Level,
+    pub target: String,
+    // メッセージとフィールドを 1 行にしたもの
+    pub message: String,
+}
+
+// 直近の警告・エラー (新しい順)
+pub fn recent() -> Vec<RecentLog> {
+    RECENT.lock().unwrap().iter().rev().cloned().collect()
+}
+
+// WARN 以上のログを RECENT に残す
+struct RecentLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > Level::WARN {
+            return;
+        }
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+        let log = RecentLog { at: Instant::now(), level: *metadata.level(), target: metadata.target().to_string(), message: visitor.0 };
+        let mut recent = RECENT.lock().unwrap();
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(log);
+    }
+}
+
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            self.0.push_str(&format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+// ユーザのメッセージ本文など。既定では長さだけを出す
+pub struct Content<'a>(&'a str);
+
+pub fn content(s: &str) -> Content<'_> {
+    Content(s)
+}
+
+impl fmt::Display for Content<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if LOG_CONTENT.load(Ordering::Relaxed) {
+            f.write_str(self.0)
+        } else {
+            write!(f, "<redacted {} chars>", self.0.chars().count())
+        }
+    }
+}
+
+// パスが /guilds/:guild_id/... の形ならギルド ID を取り出す
+pub fn guild_from_path(path: &str) -> Option<&str> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    match (segments.next(), segments.next()) {
+        (Some("guilds"), Some(id)) if !id.is_empty() => Some(id),
+        _ => None,
+    }
+}
@@ -0,0 +1,175 @@
+// Bot のオーナー向けの管理画面
+// admin.owner_ids に含まれる Discord ユーザだけが使える。それ以外には存在自体を見せないため 404 を返す
+use std::collections::{BTreeMap, HashMap};
+
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::{Form, Router};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use serenity::model::id::GuildId;
+
+use super::templates::{AdminGuild, AdminLog, AdminTemplate};
+use super::{session, AppState};
+use crate::events::Event;
+use crate::registration::{self, Registration};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/admin", get(admin_page))
+        // POST 用のパスに GET でアクセスした場合は管理画面に戻す
+        .route("/admin/guilds/:guild_id/register", get(redirect_to_admin).post(register))
+        .route("/admin/guilds/:guild_id/leave", get(redirect_to_admin).post(leave))
+        .route("/admin/guilds/:guild_id/purge", get(redirect_to_admin).post(purge))
+}
+
+// ログイン中のユーザがオーナーならユーザ ID を返す。そうでなければそのまま返すレスポンス
+async fn require_owner(state: &AppState, jar: &CookieJar) -> Result<u64, Response> {
+    if state.owner_ids.is_empty() {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    }
+    let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Err(Redirect::to("/").into_response()); };
+    // ユーザ ID はログイン時に確認してセッションに封印してあるので、Discord には問い合わせない
+    let Some((user_id, _)) = session::open_session(&state.session_key, &sealed) else { return Err(Redirect::to("/").into_response()); };
+    if !state.owner_ids.contains(&user_id) {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    }
+    Ok(user_id)
+}
+
+async fn admin_page(State(state): State<AppState>, jar: CookieJar) -> Response {
+    if let Err(response) = require_owner(&state, &jar).await {
+        return response;
+    }
+
+    // Bot が参加しているギルドと、DB にデータが残っているギルドの和集合
+    let (usages, departures, statuses, database_size) = match (
+        state.store.list_guild_usage().await,
+        state.store.list_guild_departures().await,
+        state.store.list_registrations().await,
+        state.store.database_size().await,
+    ) {
+        (Ok(usages), Ok(departures), Ok(statuses), Ok(database_size)) => (usages, departures, statuses, database_size),
+        (Err(e), ..) | (_, Err(e), ..) | (_, _, Err(e), _) | (.., Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    let mut guilds: BTreeMap<i64, AdminGuild> = BTreeMap::new();
+    for (id, name) in state.guilds.bot_guilds().await {
+        guilds.insert(id, AdminGuild { id: id.to_string(), name, joined: true, ..AdminGuild::default() });
+    }
+    for usage in usages {
+        let guild = guilds.entry(usage.guild_id).or_insert_with(|| AdminGuild { id: usage.guild_id.to_string(), ..AdminGuild::default() });
+        guild.commands = usage.commands;
+        guild.scopes = usage.scopes;
+        guild.size = format_bytes(usage.bytes);
+    }
+    // 退出済みで削除を待っているギルド
+    let now = crate::retention::now();
+    for departure in departures {
+        let guild = guilds.entry(departure.guild_id).or_insert_with(|| AdminGuild { id: departure.guild_id.to_string(), ..AdminGuild::default() });
+        let days = (departure.purge_at - now).max(0) / (24 * 60 * 60);
+        guild.scheduled_purge = format!("{} 日後に削除", days);
+    }
+    let statuses: HashMap<i64, _> = statuses.into_iter().map(|s| (s.guild_id, s)).collect();
+    for (id, guild) in guilds.iter_mut() {
+        if let Some(status) = statuses.get(id) {
+            guild.registration_status = status.status.clone();
+            guild.registration_error = status.error.clone().unwrap_or_default();
+            guild.registration_updated_at = status.updated_at.clone().unwrap_or_default();
+        }
+    }
+
+    let logs = crate::telemetry::recent()
+        .into_iter()
+        .map(|log| AdminLog {
+            ago: format!("{} 秒前", log.at.elapsed().as_secs()),
+            level: log.level.to_string(),
+            target: log.target,
+            message: log.message,
+        })
+        .collect();
+    let tpl = AdminTemplate {
+        username: jar.get("username").map(|c| c.value().to_string()),
+        csrf: jar.get("csrf").map(|c| c.value().to_string()).unwrap_or_default(),
+        guilds: guilds.into_values().collect(),
+        database_size: database_size.map(format_bytes).unwrap_or_else(|| "-".to_string()),
+        logs,
+    };
+    Html(tpl.render().unwrap()).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionForm { csrf: String }
+
+// CSRF とオーナーを確認する
+async fn authorize_action(state: &AppState, jar: &CookieJar, form: &ActionForm) -> Result<u64, Response> {
+    if jar.get("csrf").map(|c| c.value()) != Some(form.csrf.as_str()) {
+        return Err((StatusCode::BAD_REQUEST, "invalid csrf").into_response());
+    }
+    require_owner(state, jar).await
+}
+
+// 前回から変更がなくてもスラッシュコマンドを登録し直す
+async fn register(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>, Form(f): Form<ActionForm>) -> Response {
+    let user = match authorize_action(&state, &jar, &f).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    tracing::info!(user, guild_id, "admin: re-registering slash commands");
+    match registration::sync_guild_commands(&state.http, state.store.as_ref(), GuildId(guild_id as u64), true).await {
+        Ok(Registration::Registered | Registration::Unchanged) => Redirect::to("/admin").into_response(),
+        Err(msg) => (StatusCode::BAD_GATEWAY, msg).into_response(),
+    }
+}
+
+// ギルドから退出する (データは残す。消す場合は purge を使う)
+async fn leave(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>, Form(f): Form<ActionForm>) -> Response {
+    let user = match authorize_action(&state, &jar, &f).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    tracing::info!(user, guild_id, "admin: leaving guild");
+    if let Err(e) = state.http.leave_guild(guild_id as u64).await {
+        return (StatusCode::BAD_GATEWAY, format!("failed to leave guild: {e}")).into_response();
+    }
+    state.events.publish(Event::GuildChanged { guild_id }).await;
+    Redirect::to("/admin").into_response()
+}
+
+// ギルドのコマンド・スコープ・登録状況を削除する
+async fn purge(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>, Form(f): Form<ActionForm>) -> Response {
+    let user = match authorize_action(&state, &jar, &f).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    match state.store.purge_guild(guild_id).await {
+        Ok(removed) => {
+            tracing::info!(user, guild_id, removed, "admin: purged guild data");
+            // Bot が登録済みのスラッシュコマンドも取り下げる
+            state.events.publish(Event::CommandsChanged { guild_id }).await;
+            Redirect::to("/admin").into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    }
+}
+
+async fn redirect_to_admin() -> Redirect {
+    Redirect::to("/admin")
+}
+
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
@@ -0,0 +1,189 @@
+// ギルドの入退室メッセージの設定・プレビュー・テスト送信
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::{Form, Router};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+
+use super::router::{scope_pickers, ScopePickers};
+use super::templates::GreetingsTemplate;
+use super::{oauth, session, AppState};
+use crate::greetings::{self, Greeting, Kind, MemberInfo};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/guilds/:guild_id/greetings", get(greetings_page))
+        // POST 用のパスに GET でアクセスした場合は設定画面に戻す
+        .route("/guilds/:guild_id/greetings/save", get(redirect_to_greetings).post(save))
+        .route("/guilds/:guild_id/greetings/test", get(redirect_to_greetings).post(test_send))
+}
+
+// ログイン中のユーザがギルドを管理できるか (オーナー・管理者・サーバー管理) 確認し、プレビューとテスト送信に使うユーザとギルドの情報を返す
+// 管理できなければそのまま返すレスポンス
+async fn require_manager(state: &AppState, jar: &CookieJar, guild_id: i64) -> Result<MemberInfo, Response> {
+    let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Err(Redirect::to("/").into_response()); };
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Err(Redirect::to("/").into_response()); };
+    let (user, guilds) = tokio::join!(
+        oauth::fetch_user(&state.discord_api_base, &access_token),
+        oauth::fetch_user_guilds(&state.discord_api_base, &access_token)
+    );
+    let guild = match guilds {
+        Ok(gs) => match gs.into_iter().find(|g| g.id.parse::<i64>().ok() == Some(guild_id)) {
+            Some(guild) => guild,
+            None => return Err(Redirect::to("/").into_response()),
+        },
+        Err(_) => return Err((StatusCode::BAD_GATEWAY, "Failed to fetch guilds").into_response()),
+    };
+    // 自動ロールでメンバーに権限を与えられるため、メンバー全員には開放しない
+    if !guild.can_manage() {
+        return Err((StatusCode::FORBIDDEN, "サーバー管理の権限が必要です。").into_response());
+    }
+    let Some((user_id, user)) = user.ok().and_then(|u| Some((u.id.parse::<i64>().ok()?, u))) else {
+        return Err((StatusCode::BAD_GATEWAY, "Failed to fetch user").into_response());
+    };
+    Ok(MemberInfo {
+        user_id,
+        username: user.global_name.unwrap_or(user.username),
+        server: guild.name,
+        // Web からはメンバー数を取得しない
+        member_count: None,
+    })
+}
+
+// CSRF とギルドを管理できるかを確認する
+async fn authorize_action(state: &AppState, jar: &CookieJar, guild_id: i64, csrf: &str) -> Result<MemberInfo, Response> {
+    if jar.get("csrf").map(|c| c.value()) != Some(csrf) {
+        return Err((StatusCode::BAD_REQUEST, "invalid csrf").into_response());
+    }
+    require_manager(state, jar, guild_id).await
+}
+
+// チャンネルとロールがこのギルドのものか確認する (別のギルドのチャンネルに送ったり、連携のロールを付けたりしない)
+fn check_targets(greeting: &Greeting, pickers: &ScopePickers) -> Result<(), String> {
+    for id in [greeting.welcome_channel_id, greeting.farewell_channel_id].into_iter().flatten() {
+        if !pickers.has_channel(id) {
+            return Err("送信先のチャンネルが見つかりません。".to_string());
+        }
+    }
+    if let Some(id) = greeting.auto_role_id {
+        if !pickers.has_assignable_role(id) {
+            return Err("このロールは自動で付けられません。".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn id_or_empty(id: Option<i64>) -> String {
+    id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+async fn greetings_page(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>) -> Response {
+    let member = match require_manager(&state, &jar, guild_id).await {
+        Ok(member) => member,
+        Err(response) => return response,
+    };
+    let pickers = scope_pickers(&state, guild_id).await;
+    let greeting = match greetings::settings(state.store.as_ref(), guild_id).await {
+        Ok(greeting) => greeting,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    let tpl = GreetingsTemplate {
+        guild_id,
+        csrf: jar.get("csrf").map(|c| c.value().to_string()).unwrap_or_default(),
+        channels: pickers.channels,
+        roles: pickers.assignable_roles,
+        degraded: state.health.degraded().iter().any(|f| f == "greetings"),
+        placeholders: greetings::PLACEHOLDERS.to_vec(),
+        welcome_enabled: greeting.welcome_enabled,
+        welcome_channel_id: id_or_empty(greeting.welcome_channel_id),
+        welcome_dm: greeting.welcome_dm,
+        auto_role_id: id_or_empty(greeting.auto_role_id),
+        farewell_enabled: greeting.farewell_enabled,
+        farewell_channel_id: id_or_empty(greeting.farewell_channel_id),
+        welcome_preview: greetings::preview(&greeting.welcome_message, &member),
+        farewell_preview: greetings::preview(&greeting.farewell_message, &member),
+        welcome_message: greeting.welcome_message,
+        farewell_message: greeting.farewell_message,
+        server: member.server,
+    };
+    Html(tpl.render().unwrap()).into_response()
+}
+
+// チェックボックスは未チェックなら送られず、チャンネル・ロールの「なし」は空文字になる
+#[derive(Debug, Deserialize)]
+struct GreetingForm {
+    welcome_enabled: Option<String>,
+    welcome_channel_id: Option<String>,
+    #[serde(default)]
+    welcome_message: String,
+    welcome_dm: Option<String>,
+    auto_role_id: Option<String>,
+    farewell_enabled: Option<String>,
+    farewell_channel_id: Option<String>,
+    #[serde(default)]
+    farewell_message: String,
+    csrf: String,
+}
+
+fn parse_id(v: &Option<String>) -> Option<i64> {
+    v.as_deref().and_then(|v| v.parse::<i64>().ok())
+}
+
+async fn save(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>, Form(f): Form<GreetingForm>) -> Response {
+    if let Err(response) = authorize_action(&state, &jar, guild_id, &f.csrf).await {
+        return response;
+    }
+    let greeting = Greeting {
+        guild_id,
+        welcome_enabled: f.welcome_enabled.is_some(),
+        welcome_channel_id: parse_id(&f.welcome_channel_id),
+        welcome_message: f.welcome_message.trim().to_string(),
+        welcome_dm: f.welcome_dm.is_some(),
+        auto_role_id: parse_id(&f.auto_role_id),
+        farewell_enabled: f.farewell_enabled.is_some(),
+        farewell_channel_id: parse_id(&f.farewell_channel_id),
+        farewell_message: f.farewell_message.trim().to_string(),
+    };
+    if let Err(msg) = check_targets(&greeting, &scope_pickers(&state, guild_id).await) {
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    match greetings::save(state.store.as_ref(), &greeting).await {
+        Ok(()) => Redirect::to(&format!("/guilds/{guild_id}/greetings")).into_response(),
+        Err(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TestForm { kind: String, csrf: String }
+
+// 保存済みの設定で、ログイン中のユーザが参加・退出した場合のメッセージを送る (自動ロールは付けない)
+async fn test_send(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>, Form(f): Form<TestForm>) -> Response {
+    let member = match authorize_action(&state, &jar, guild_id, &f.csrf).await {
+        Ok(member) => member,
+        Err(response) => return response,
+    };
+    let Some(kind) = Kind::parse(&f.kind) else { return (StatusCode::BAD_REQUEST, "invalid kind").into_response() };
+    let greeting = match greetings::settings(state.store.as_ref(), guild_id).await {
+        Ok(greeting) => greeting,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    // 保存後にチャンネルが削除・移動されている場合もあるので送る前にも確認する
+    let checked = match greeting.check_sendable(kind) {
+        Ok(()) => check_targets(&greeting, &scope_pickers(&state, guild_id).await),
+        Err(msg) => Err(msg),
+    };
+    if let Err(msg) = checked {
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    match greetings::send(&greeting, kind, state.http.as_ref(), &member).await {
+        Ok(()) => Redirect::to(&format!("/guilds/{guild_id}/greetings")).into_response(),
+        Err(msg) => (StatusCode::BAD_GATEWAY, format!("送信に失敗しました: {}", msg)).into_response(),
+    }
+}
+
+async fn redirect_to_greetings(Path(guild_id): Path<i64>) -> Redirect {
+    Redirect::to(&format!("/guilds/{guild_id}/greetings"))
+}
@@ -0,0 +1,122 @@
+// スコープのピッカーに出すギルドのチャンネルとロール
+// Bot と同じプロセスでは Gateway のキャッシュを使い、web-only では REST API で取得して一定時間保持する
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serenity::cache::Cache;
+use serenity::http::{GuildPagination, Http};
+use serenity::model::channel::GuildChannel;
+use serenity::model::guild::Role;
+use serenity::model::id::GuildId;
+use tokio::sync::broadcast;
+
+use crate::events::Event;
+
+// REST で取得したデータを使い回す時間 (Bot から GuildChanged が届けばその時点で捨てる)
+const FETCHED_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Default)]
+pub struct GuildData {
+    pub channels: Vec<GuildChannel>,
+    pub roles: Vec<Role>,
+}
+
+pub enum GuildDirectory {
+    Gateway(Arc<Cache>),
+    Rest { http: Arc<Http>, fetched: Mutex<HashMap<i64, (Instant, GuildData)>> },
+}
+
+impl GuildDirectory {
+    pub fn gateway(cache: Arc<Cache>) -> GuildDirectory {
+        GuildDirectory::Gateway(cache)
+    }
+
+    pub fn rest(http: Arc<Http>) -> GuildDirectory {
+        GuildDirectory::Rest { http, fetched: Mutex::new(HashMap::new()) }
+    }
+
+    // 取得できない場合 (Bot がギルドにいない等) は空を返す
+    pub async fn get(&self, guild_id: i64) -> GuildData {
+        let gid = GuildId(guild_id as u64);
+        match self {
+            GuildDirectory::Gateway(cache) => GuildData {
+                channels: cache.guild_channels(gid).map(|m| m.iter().map(|e| e.value().clone()).collect()).unwrap_or_default(),
+                roles: cache.guild_roles(gid).map(|m| m.into_values().collect()).unwrap_or_default(),
+            },
+            GuildDirectory::Rest { http, fetched } => {
+                if let Some((at, data)) = fetched.lock().unwrap().get(&guild_id) {
+                    if at.elapsed() < FETCHED_TTL {
+                        return data.clone();
+                    }
+                }
+                let (channels, roles) = tokio::join!(http.get_channels(gid.0), http.get_guild_roles(gid.0));
+                let data = match (channels, roles) {
+                    (Ok(channels), Ok(roles)) => GuildData { channels, roles },
+                    (Err(e), _) | (_, Err(e)) => {
+                        tracing::warn!(guild_id, error = %e, "failed to fetch guild channels/roles");
+                        return GuildData::default();
+                    }
+                };
+                fetched.lock().unwrap().insert(guild_id, (Instant::now(), data.clone()));
+                data
+            }
+        }
+    }
+
+    // Bot が参加しているギルドの ID と名前 (管理画面用)
+    pub async fn bot_guilds(&self) -> Vec<(i64, String)> {
+        match self {
+            GuildDirectory::Gateway(cache) => cache
+                .guilds()
+                .into_iter()
+                .map(|id| (id.0 as i64, cache.guild_field(id, |g| g.name.clone()).unwrap_or_default()))
+                .collect(),
+            GuildDirectory::Rest { http, .. } => {
+                // 1 回に取得できるのは 200 件まで
+                let mut guilds = Vec::new();
+                let mut after = None;
+                loop {
+                    let page = match http.get_guilds(after.as_ref(), Some(200)).await {
+                        Ok(page) => page,
+                        Err(e) => {
+                            tracing::warn!(error = %e, "failed to fetch bot guilds");
+                            break;
+                        }
+                    };
+                    let full = page.len() == 200;
+                    after = page.last().map(|g| GuildPagination::After(g.id));
+                    guilds.extend(page.into_iter().map(|g| (g.id.0 as i64, g.name)));
+                    if !full {
+                        break;
+                    }
+                }
+                guilds
+            }
+        }
+    }
+
+    pub fn invalidate(&self, guild_id: Option<i64>) {
+        if let GuildDirectory::Rest { fetched, .. } = self {
+            let mut fetched = fetched.lock().unwrap();
+            match guild_id {
+                Some(guild_id) => {
+                    fetched.remove(&guild_id);
+                }
+                None => fetched.clear(),
+            }
+        }
+    }
+}
+
+// Bot からの通知を受けてキャッシュを捨てる
+pub async fn follow_events(directory: Arc<GuildDirectory>, mut events: broadcast::Receiver<Event>) {
+    loop {
+        match events.recv().await {
+            Ok(Event::GuildChanged { guild_id }) => directory.invalidate(Some(guild_id)),
+            Ok(Event::Resync) | Err(broadcast::error::RecvError::Lagged(_)) => directory.invalidate(None),
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
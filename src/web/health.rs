@@ -0,0 +1,33 @@
+// 死活監視用のエンドポイント
+// - /healthz: DB に接続できない、または Gateway の未接続が長く続いている場合に 503
+// - /readyz:  DB に接続でき、すべてのシャードが接続済みでハートビートが途切れていない場合のみ 200
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::{json, Value};
+
+use super::AppState;
+
+async fn report(state: &AppState) -> (bool, Value) {
+    let database = state.store.ping().await;
+    let body = json!({
+        "database": match &database {
+            Ok(()) => json!({ "ok": true }),
+            Err(e) => json!({ "ok": false, "error": e }),
+        },
+        "gateway": state.health.gateway_report(),
+        // 動かない機能があっても異常とはしない (設定や開発者ポータルの見直しが必要なだけ)
+        "degraded_features": state.health.degraded(),
+    });
+    (database.is_ok(), body)
+}
+
+pub async fn healthz(State(state): State<AppState>) -> impl IntoResponse {
+    let (database_ok, body) = report(&state).await;
+    let status = if database_ok && state.health.gateway_alive() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(body))
+}
+
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let (database_ok, body) = report(&state).await;
+    let status = if database_ok && state.health.gateway_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(body))
+}
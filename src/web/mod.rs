@@ -1,27 +1,98 @@
 pub mod router;
+pub mod admin;
+pub mod schedules;
+pub mod reminders;
+pub mod greetings;
+pub mod health;
+pub mod guilds;
 pub mod oauth;
 pub mod session;
 pub mod templates;
+#[cfg(test)]
+mod tests;
 
 use std::sync::Arc;
-use axum::{Router, extract::FromRef};
-use sqlx::PgPool;
+use axum::{Router, extract::FromRef, http::{header, Request}, response::IntoResponse, routing::get};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use serenity::http::Http;
+
+use crate::events::EventBus;
+use crate::health::Health;
+use guilds::GuildDirectory;
+use crate::store::CommandStore;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub pool: Arc<PgPool>,
+    pub store: Arc<dyn CommandStore>,
+    // チャンネル/ロールのピッカー用 (Bot のギルドキャッシュ、web-only では REST API)
+    pub guilds: Arc<GuildDirectory>,
+    // スラッシュコマンドの再登録に使う
+    pub http: Arc<Http>,
     pub discord_client_id: String,
     pub discord_client_secret: String,
     pub discord_redirect_uri: String,
+    // Discord API のベース URL (OAuth2 とユーザ情報の取得に使う)
+    pub discord_api_base: String,
     pub session_key: [u8; 32],
+    // /healthz と /readyz で Gateway の接続状態を返すのに使う
+    pub health: Arc<Health>,
+    // コマンドの変更を Bot に知らせる
+    pub events: EventBus,
+    // 管理画面 (/admin) を使える Discord ユーザ ID
+    pub owner_ids: Vec<u64>,
 }
 
-impl FromRef<AppState> for Arc<PgPool> {
-    fn from_ref(state: &AppState) -> Arc<PgPool> {
-        state.pool.clone()
+impl FromRef<AppState> for Arc<dyn CommandStore> {
+    fn from_ref(state: &AppState) -> Arc<dyn CommandStore> {
+        state.store.clone()
     }
 }
 
 pub fn build_router(state: AppState) -> Router {
-    router::create_router(state)
+    with_layers(router::create_router(state))
+}
+
+// features.web = false の場合に使う。ダッシュボードは公開せず、死活監視のエンドポイントだけを返す
+pub fn build_health_router(state: AppState) -> Router {
+    with_layers(
+        Router::new()
+            .route("/healthz", get(health::healthz))
+            .route("/readyz", get(health::readyz))
+            .with_state(state),
+    )
+}
+
+fn with_layers(router: Router) -> Router {
+    // レイヤーは下から順に適用される: リクエスト ID の付与 → スパン作成 → レスポンスへの ID 付与
+    router
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+// Prometheus 用の /metrics。METRICS_BIND を指定した場合は別ポートで公開する
+pub fn metrics_router() -> Router {
+    Router::new().route("/metrics", get(metrics))
+}
+
+async fn metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], crate::metrics::render())
+}
+
+// HTTP リクエストごとのスパン。クエリ文字列 (OAuth の code/state) は記録しない
+fn request_span<B>(req: &Request<B>) -> tracing::Span {
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or("-");
+    let path = req.uri().path();
+    tracing::info_span!(
+        "http",
+        request_id,
+        method = %req.method(),
+        path,
+        guild_id = crate::telemetry::guild_from_path(path),
+    )
 }
@@ -5,10 +5,10 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
 use super::AppState;
+use crate::metrics;
 
-const DISCORD_AUTH_URL: &str = "https://discord.com/api/oauth2/authorize";
-const DISCORD_TOKEN_URL: &str = "https://discord.com/api/oauth2/token";
-const DISCORD_API_BASE: &str = "https://discord.com/api";
+// AppState::discord_api_base の既定値 (テストではモックサーバの URL に差し替える)
+pub const DEFAULT_DISCORD_API_BASE: &str = "https://discord.com/api";
 
 #[derive(Debug, Deserialize)]
 pub struct AuthQuery {
@@ -30,6 +30,21 @@ pub struct DiscordGuild {
     pub id: String,
     pub name: String,
     pub owner: Option<bool>,
+    // ログイン中のユーザのギルドでの権限 (ビットフィールドの文字列)
+    pub permissions: Option<String>,
+}
+
+impl DiscordGuild {
+    // オーナーか、管理者・サーバー管理の権限があるか
+    pub fn can_manage(&self) -> bool {
+        let permissions = self
+            .permissions
+            .as_deref()
+            .and_then(|p| p.parse::<u64>().ok())
+            .map(serenity::model::permissions::Permissions::from_bits_truncate)
+            .unwrap_or_default();
+        self.owner == Some(true) || permissions.administrator() || permissions.manage_guild()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -55,8 +70,8 @@ pub async fn login(State(state): State<super::AppState>, jar: CookieJar) -> impl
     let jar = jar.add(cookie);
 
     let url = format!(
-        "{}?client_id={}&response_type=code&scope=identify%20guilds&redirect_uri={}&state={}",
-        DISCORD_AUTH_URL,
+        "{}/oauth2/authorize?client_id={}&response_type=code&scope=identify%20guilds&redirect_uri={}&state={}",
+        state.discord_api_base,
         urlencoding::encode(&state.discord_client_id),
         urlencoding::encode(&state.discord_redirect_uri),
         urlencoding::encode(&state_token)
@@ -95,11 +110,10 @@ pub async fn oauth_callback(
         // Discord では scope は省略可能だが、念のため明示する
         ("scope", "identify guilds"),
     ];
-    let resp = match client.post(DISCORD_TOKEN_URL).form(&form).send().await {
-        Ok(r) => r,
-        Err(e) => return (StatusCode::BAD_GATEWAY, format!("token exchange failed: {e}")).into_response(),
+    let token_request = async {
+        client.post(format!("{}/oauth2/token", state.discord_api_base)).form(&form).send().await?.error_for_status()
     };
-    let resp = match resp.error_for_status() {
+    let resp = match metrics::time_discord("oauth2/token", token_request).await {
         Ok(r) => r,
         Err(e) => return (StatusCode::BAD_GATEWAY, format!("token exchange failed: {e}")).into_response(),
     };
@@ -109,16 +123,15 @@ pub async fn oauth_callback(
     };
 
     // ユーザ情報取得
-    let resp = match client
-        .get(format!("{}/users/@me", DISCORD_API_BASE))
-        .bearer_auth(&token_res.access_token)
-        .send()
-        .await
-    {
-        Ok(r) => r,
-        Err(e) => return (StatusCode::BAD_GATEWAY, format!("get user failed: {e}")).into_response(),
+    let user_request = async {
+        client
+            .get(format!("{}/users/@me", state.discord_api_base))
+            .bearer_auth(&token_res.access_token)
+            .send()
+            .await?
+            .error_for_status()
     };
-    let resp = match resp.error_for_status() {
+    let resp = match metrics::time_discord("users/@me", user_request).await {
         Ok(r) => r,
         Err(e) => return (StatusCode::BAD_GATEWAY, format!("get user failed: {e}")).into_response(),
     };
@@ -127,8 +140,11 @@ pub async fn oauth_callback(
         Err(e) => return (StatusCode::BAD_GATEWAY, format!("user parse failed: {e}")).into_response(),
     };
 
-    // アクセストークンをセッションCookieに格納
-    let value = crate::web::session::seal_token(&state.session_key, &token_res.access_token);
+    let Ok(user_id) = user.id.parse::<u64>() else {
+        return (StatusCode::BAD_GATEWAY, "invalid user id").into_response();
+    };
+    // ユーザ ID とアクセストークンをセッションCookieに格納
+    let value = crate::web::session::seal_session(&state.session_key, user_id, &token_res.access_token);
     let mut sess = Cookie::new("session", value);
     sess.set_http_only(true);
     sess.set_same_site(SameSite::Lax);
@@ -155,10 +171,29 @@ pub async fn logout(jar: CookieJar) -> impl IntoResponse {
     (jar, Redirect::to("/"))
 }
 
-pub async fn fetch_user_guilds(access_token: &str) -> Result<Vec<DiscordGuild>, reqwest::Error> {
+// 管理画面で Bot のオーナーか確認するのに使う
+pub async fn fetch_user(api_base: &str, access_token: &str) -> Result<DiscordUser, reqwest::Error> {
+    let request = async {
+        Client::new()
+            .get(format!("{}/users/@me", api_base))
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<DiscordUser>()
+            .await
+    };
+    metrics::time_discord("users/@me", request).await
+}
+
+pub async fn fetch_user_guilds(api_base: &str, access_token: &str) -> Result<Vec<DiscordGuild>, reqwest::Error> {
+    metrics::time_discord("users/@me/guilds", request_user_guilds(api_base, access_token)).await
+}
+
+async fn request_user_guilds(api_base: &str, access_token: &str) -> Result<Vec<DiscordGuild>, reqwest::Error> {
     let client = Client::new();
     let res = client
-        .get(format!("{}/users/@me/guilds", DISCORD_API_BASE))
+        .get(format!("{}/users/@me/guilds", api_base))
         .bearer_auth(access_token)
         .header(reqwest::header::USER_AGENT, "nkmzbot/1.0 (+https://github.com/susu3304/nkmzbot)")
         .header(reqwest::header::ACCEPT, "application/json")
@@ -169,7 +204,7 @@ pub async fn fetch_user_guilds(access_token: &str) -> Result<Vec<DiscordGuild>,
     let res = match res.error_for_status() {
         Ok(r) => r,
         Err(e) => {
-            eprintln!("[oauth] fetch_user_guilds failed: status={} err={}", status, e);
+            tracing::warn!(%status, error = %e, "fetch_user_guilds failed");
             return Err(e);
         }
     };
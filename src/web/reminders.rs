@@ -0,0 +1,111 @@
+// ログイン中のユーザのリマインダーの一覧・取り消しとタイムゾーンの設定
+use askama::Template;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::{Form, Router};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+
+use super::templates::{ReminderRow, RemindersTemplate};
+use super::{oauth, session, AppState};
+use crate::reminders;
+use crate::scheduler::cron;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/reminders", get(reminders_page))
+        // POST 用のパスに GET でアクセスした場合は一覧に戻す
+        .route("/reminders/cancel", get(redirect_to_reminders).post(cancel))
+        .route("/reminders/timezone", get(redirect_to_reminders).post(set_timezone))
+}
+
+// ログイン中のユーザ ID を返す。未ログインならそのまま返すレスポンス
+async fn require_user(state: &AppState, jar: &CookieJar) -> Result<i64, Response> {
+    let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Err(Redirect::to("/").into_response()); };
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Err(Redirect::to("/").into_response()); };
+    let user = match oauth::fetch_user(&state.discord_api_base, &access_token).await {
+        Ok(user) => user,
+        Err(_) => return Err((StatusCode::BAD_GATEWAY, "Failed to fetch user").into_response()),
+    };
+    user.id.parse::<i64>().map_err(|_| (StatusCode::BAD_GATEWAY, "Failed to fetch user").into_response())
+}
+
+// CSRF とログインを確認する
+async fn authorize_action(state: &AppState, jar: &CookieJar, csrf: &str) -> Result<i64, Response> {
+    if jar.get("csrf").map(|c| c.value()) != Some(csrf) {
+        return Err((StatusCode::BAD_REQUEST, "invalid csrf").into_response());
+    }
+    require_user(state, jar).await
+}
+
+async fn reminders_page(State(state): State<AppState>, jar: CookieJar) -> Response {
+    let user_id = match require_user(&state, &jar).await {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+    let (timezone, offset) = match reminders::user_timezone(state.store.as_ref(), user_id).await {
+        Ok(timezone) => timezone,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    let list = match state.store.list_reminders(user_id).await {
+        Ok(list) => list,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    let mut rows = Vec::new();
+    for r in list {
+        // チャンネル名はギルドのキャッシュ (web-only では REST API) から引く
+        let place = match (r.guild_id, r.channel_id) {
+            (_, None) => "DM".to_string(),
+            (Some(guild_id), Some(channel_id)) => {
+                let channels = state.guilds.get(guild_id).await.channels;
+                match channels.iter().find(|c| c.id.0 as i64 == channel_id) {
+                    Some(c) => format!("#{}", c.name),
+                    None => format!("チャンネル {}", channel_id),
+                }
+            }
+            (None, Some(channel_id)) => format!("チャンネル {}", channel_id),
+        };
+        rows.push(ReminderRow { id: r.id, remind_at: cron::format_local(r.remind_at, offset), place, content: r.content });
+    }
+    let tpl = RemindersTemplate {
+        username: jar.get("username").map(|c| c.value().to_string()),
+        csrf: jar.get("csrf").map(|c| c.value().to_string()).unwrap_or_default(),
+        timezone,
+        reminders: rows,
+    };
+    Html(tpl.render().unwrap()).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelForm { id: i64, csrf: String }
+
+async fn cancel(State(state): State<AppState>, jar: CookieJar, Form(f): Form<CancelForm>) -> Response {
+    let user_id = match authorize_action(&state, &jar, &f.csrf).await {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+    match state.store.cancel_reminder(user_id, f.id).await {
+        Ok(_) => Redirect::to("/reminders").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TimezoneForm { timezone: String, csrf: String }
+
+async fn set_timezone(State(state): State<AppState>, jar: CookieJar, Form(f): Form<TimezoneForm>) -> Response {
+    let user_id = match authorize_action(&state, &jar, &f.csrf).await {
+        Ok(user_id) => user_id,
+        Err(response) => return response,
+    };
+    match reminders::set_timezone(state.store.as_ref(), user_id, &f.timezone).await {
+        Ok(()) => Redirect::to("/reminders").into_response(),
+        Err(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
+    }
+}
+
+async fn redirect_to_reminders() -> Redirect {
+    Redirect::to("/reminders")
+}
@@ -1,12 +1,10 @@
-use std::sync::Arc;
 
 use axum::{routing::get, Router, extract::{Path, Query, State}, response::{Html, IntoResponse, Redirect}, Form, http::StatusCode};
 use askama::Template;
 use serde::Deserialize;
-use sqlx::Row;
 
 use super::{AppState};
-use crate::web::{oauth, session};
+use crate::web::{health, oauth, session};
 
 pub fn create_router(state: AppState) -> Router {
     Router::new()
@@ -14,6 +12,8 @@ pub fn create_router(state: AppState) -> Router {
         .route("/login", get(oauth::login))
         .route("/oauth/callback", get(oauth::oauth_callback))
         .route("/logout", get(oauth::logout))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
         .route("/dashboard", get(dashboard))
         .route("/guilds/:guild_id/commands", get(commands_page))
         // For POST endpoints, also accept GET and redirect back to the list to avoid 405 on reload/direct access
@@ -29,6 +29,26 @@ pub fn create_router(state: AppState) -> Router {
             "/guilds/:guild_id/commands/bulk-delete",
             get(redirect_to_commands).post(bulk_delete_commands),
         )
+        .route(
+            "/guilds/:guild_id/commands/slash",
+            get(redirect_to_commands).post(update_slash),
+        )
+        .route(
+            "/guilds/:guild_id/commands/delivery",
+            get(redirect_to_commands).post(update_delivery),
+        )
+        .route(
+            "/guilds/:guild_id/commands/scopes/add",
+            get(redirect_to_commands).post(add_scope),
+        )
+        .route(
+            "/guilds/:guild_id/commands/scopes/remove",
+            get(redirect_to_commands).post(remove_scope),
+        )
+        .merge(crate::web::schedules::routes())
+        .merge(crate::web::reminders::routes())
+        .merge(crate::web::greetings::routes())
+        .merge(crate::web::admin::routes())
         .with_state(state)
 }
 
@@ -53,28 +73,20 @@ async fn dashboard(State(state): State<AppState>, jar: axum_extra::extract::cook
     let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else {
         return Redirect::to("/").into_response();
     };
-    let Some(access_token) = session::open_token(&state.session_key, &sealed) else {
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else {
         return Redirect::to("/").into_response();
     };
 
     // ユーザGuild取得
-    let guilds = match oauth::fetch_user_guilds(&access_token).await {
+    let guilds = match oauth::fetch_user_guilds(&state.discord_api_base, &access_token).await {
         Ok(v) => v,
         Err(_) => return (StatusCode::BAD_GATEWAY, "Failed to fetch guilds").into_response(),
     };
     // DB登録済みguild_idの集合
-    let pool: Arc<sqlx::PgPool> = state.pool.clone();
-    let rows = match sqlx::query("SELECT DISTINCT guild_id FROM commands")
-        .fetch_all(&*pool)
-        .await
-    {
-        Ok(r) => r,
-        Err(_) => vec![],
+    let db_guilds: std::collections::HashSet<i64> = match state.store.list_guild_ids().await {
+        Ok(ids) => ids.into_iter().collect(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
     };
-    let db_guilds: std::collections::HashSet<i64> = rows
-        .into_iter()
-        .filter_map(|row| row.try_get::<i64, _>("guild_id").ok())
-        .collect();
 
     // フィルタリング
     let filtered: Vec<crate::web::oauth::DiscordGuild> = guilds
@@ -82,8 +94,42 @@ async fn dashboard(State(state): State<AppState>, jar: axum_extra::extract::cook
         .filter(|g| db_guilds.contains(&g.id.parse::<i64>().unwrap_or_default()))
         .collect();
 
+    // スラッシュコマンドの登録状況
+    let statuses: std::collections::HashMap<i64, crate::registration::RegistrationStatus> = match state.store.list_registrations().await {
+        Ok(statuses) => statuses.into_iter().map(|s| (s.guild_id, s)).collect(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    let filtered = filtered
+        .into_iter()
+        .map(|g| {
+            let status = g.id.parse::<i64>().ok().and_then(|id| statuses.get(&id));
+            crate::web::templates::DashboardGuild {
+                registration_status: status.map(|s| s.status.clone()).unwrap_or_else(|| "pending".to_string()),
+                registration_error: status.and_then(|s| s.error.clone()).unwrap_or_default(),
+                registration_updated_at: status.and_then(|s| s.updated_at.clone()).unwrap_or_default(),
+                id: g.id,
+                name: g.name,
+            }
+        })
+        .collect();
+
     let username = jar.get("username").map(|c| c.value().to_string());
-    let tpl = crate::web::templates::DashboardTemplate { username, guilds: filtered };
+    let shards = match state.store.list_shard_statuses().await {
+        Ok(shards) => shards,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    let shards = shards
+        .into_iter()
+        .map(|s| crate::web::templates::DashboardShard {
+            id: s.shard_id,
+            total: s.shard_total,
+            stage: s.stage,
+            latency: s.latency_ms.map(|ms| format!("{} ms", ms)).unwrap_or_else(|| "-".to_string()),
+            guilds: s.guilds,
+            updated_at: s.updated_at.unwrap_or_default(),
+        })
+        .collect();
+    let tpl = crate::web::templates::DashboardTemplate { username, guilds: filtered, shards };
     Html(tpl.render().unwrap()).into_response()
 }
 
@@ -98,9 +144,9 @@ async fn commands_page(
 ) -> impl IntoResponse {
     // 認証チェック
     let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Redirect::to("/").into_response(); };
-    let Some(access_token) = session::open_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
     // 所属ギルドか検証
-    match oauth::fetch_user_guilds(&access_token).await {
+    match oauth::fetch_user_guilds(&state.discord_api_base, &access_token).await {
         Ok(gs) => {
             let ok = gs.iter().any(|g| g.id.parse::<i64>().ok() == Some(guild_id));
             if !ok { return Redirect::to("/").into_response(); }
@@ -108,40 +154,139 @@ async fn commands_page(
         Err(_) => return (StatusCode::BAD_GATEWAY, "Failed to fetch guilds").into_response(),
     }
 
-    let pattern = q.clone().unwrap_or_default();
-    let like = if pattern.is_empty() { None } else { Some(format!("%{}%", pattern)) };
-
-    #[derive(sqlx::FromRow, Clone)]
-    struct RowCmd { name: String, response: String }
-
-    let sql = if like.is_some() {
-        "SELECT name, response FROM commands WHERE guild_id=$1 AND (name ILIKE $2 OR response ILIKE $2) ORDER BY name"
-    } else {
-        "SELECT name, response FROM commands WHERE guild_id=$1 ORDER BY name"
-    };
-
-    let pool = state.pool.clone();
-    let cmds: Vec<RowCmd> = if let Some(like_s) = like.as_deref() {
-        sqlx::query_as::<_, RowCmd>(sql)
-            .bind(guild_id)
-            .bind(like_s)
-            .fetch_all(&*pool)
-            .await
-            .unwrap_or_default()
-    } else {
-        sqlx::query_as::<_, RowCmd>(sql)
-            .bind(guild_id)
-            .fetch_all(&*pool)
-            .await
-            .unwrap_or_default()
+    let filter = q.as_deref().filter(|q| !q.is_empty());
+    let (cmds, all_scopes) = match (state.store.list_commands(guild_id, filter, crate::list::ListSort::Name).await, state.store.list_guild_scopes(guild_id).await) {
+        (Ok(cmds), Ok(all_scopes)) => (cmds, all_scopes),
+        (Err(e), _) | (_, Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
     };
 
     let csrf = jar.get("csrf").map(|c| c.value().to_string()).unwrap_or_default();
-    let converted = cmds.into_iter().map(|c| crate::web::templates::CmdRow { name: c.name, response: c.response }).collect();
-    let tpl = crate::web::templates::CommandsTemplate { guild_id, q: q.unwrap_or_default(), commands: converted, csrf };
+    let pickers = scope_pickers(&state, guild_id).await;
+    let converted = cmds
+        .into_iter()
+        .map(|c| {
+            let scopes = all_scopes
+                .iter()
+                .filter(|s| s.name == c.name)
+                .map(|s| crate::web::templates::ScopeRow {
+                    kind: s.kind.clone(),
+                    target_id: s.target_id.to_string(),
+                    label: pickers.label(s),
+                    allow: s.allow,
+                })
+                .collect();
+            crate::web::templates::CmdRow {
+                name: c.name,
+                response: c.response,
+                scopes,
+                reply_mode: c.reply_mode,
+                target_channel_id: c.target_channel_id.map(|id| id.to_string()).unwrap_or_default(),
+                delete_trigger: c.delete_trigger,
+                delete_after: c.delete_after.map(|s| s.to_string()).unwrap_or_default(),
+                slash: c.slash,
+                description: c.description.unwrap_or_default(),
+                arguments: c.arguments.join(" "),
+            }
+        })
+        .collect();
+    let tpl = crate::web::templates::CommandsTemplate {
+        guild_id,
+        q: q.unwrap_or_default(),
+        commands: converted,
+        csrf,
+        channels: pickers.channels,
+        categories: pickers.categories,
+        roles: pickers.roles,
+        reply_modes: crate::commands::ReplyMode::ALL
+            .iter()
+            .map(|m| crate::web::templates::PickerOption { id: m.as_str().to_string(), name: m.label().to_string() })
+            .collect(),
+    };
     Html(tpl.render().unwrap()).into_response()
 }
 
+// Bot のギルドキャッシュ (web-only では REST API) から作るチャンネル/カテゴリ/ロールの選択肢
+pub(super) struct ScopePickers {
+    pub(super) channels: Vec<crate::web::templates::PickerOption>,
+    categories: Vec<crate::web::templates::PickerOption>,
+    pub(super) roles: Vec<crate::web::templates::PickerOption>,
+    // メンバーに付けられるロール (@everyone と連携で管理されるロールを除く)
+    pub(super) assignable_roles: Vec<crate::web::templates::PickerOption>,
+}
+
+impl ScopePickers {
+    // 入力されたチャンネルがこのギルドのテキストチャンネルか
+    pub(super) fn has_channel(&self, id: i64) -> bool {
+        self.channels.iter().any(|c| c.id == id.to_string())
+    }
+
+    pub(super) fn has_assignable_role(&self, id: i64) -> bool {
+        self.assignable_roles.iter().any(|r| r.id == id.to_string())
+    }
+
+    fn label(&self, scope: &crate::scopes::Scope) -> String {
+        let id = scope.target_id.to_string();
+        let (list, prefix) = match scope.kind() {
+            Some(crate::scopes::ScopeKind::Channel) => (&self.channels, "#"),
+            Some(crate::scopes::ScopeKind::Category) => (&self.categories, "カテゴリ "),
+            Some(crate::scopes::ScopeKind::Role) => (&self.roles, "@"),
+            None => return id,
+        };
+        let name = list.iter().find(|o| o.id == id).map(|o| o.name.clone()).unwrap_or(id);
+        format!("{prefix}{name}")
+    }
+}
+
+pub(super) async fn scope_pickers(state: &AppState, guild_id: i64) -> ScopePickers {
+    use serenity::model::channel::ChannelType;
+    let data = state.guilds.get(guild_id).await;
+
+    let mut channels = Vec::new();
+    let mut categories = Vec::new();
+    let mut all = data.channels;
+    all.sort_by_key(|c| (c.position, c.id.0));
+    for c in all {
+        let opt = crate::web::templates::PickerOption { id: c.id.0.to_string(), name: c.name.clone() };
+        match c.kind {
+            ChannelType::Text | ChannelType::News => channels.push(opt),
+            ChannelType::Category => categories.push(opt),
+            _ => {}
+        }
+    }
+
+    let mut all = data.roles;
+    all.sort_by_key(|r| std::cmp::Reverse(r.position));
+    let mut roles = Vec::new();
+    let mut assignable_roles = Vec::new();
+    for r in all {
+        let opt = crate::web::templates::PickerOption { id: r.id.0.to_string(), name: r.name };
+        // @everyone は ID がギルド ID と同じ
+        if !r.managed && r.id.0 as i64 != guild_id {
+            assignable_roles.push(opt.clone());
+        }
+        roles.push(opt);
+    }
+
+    ScopePickers { channels, categories, roles, assignable_roles }
+}
+
+// ログイン中のユーザがギルドのメンバーか確認する。そうでなければそのまま返すレスポンス
+// Discord からギルド一覧を取得できない場合も確認できないので通さない
+pub(super) async fn require_guild_member(state: &AppState, jar: &axum_extra::extract::cookie::CookieJar, guild_id: i64) -> Result<(), axum::response::Response> {
+    let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Err(Redirect::to("/").into_response()); };
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Err(Redirect::to("/").into_response()); };
+    match oauth::fetch_user_guilds(&state.discord_api_base, &access_token).await {
+        Ok(gs) if gs.iter().any(|g| g.id.parse::<i64>().ok() == Some(guild_id)) => Ok(()),
+        Ok(_) => Err(Redirect::to("/").into_response()),
+        Err(_) => Err((StatusCode::BAD_GATEWAY, "Failed to fetch guilds").into_response()),
+    }
+}
+
+// コマンドを変更したことを Bot に知らせる (別プロセスの Bot もスラッシュコマンドを同期する)
+async fn commands_changed(state: &AppState, guild_id: i64) {
+    state.events.publish(crate::events::Event::CommandsChanged { guild_id }).await;
+}
+
 #[derive(Debug, Deserialize)]
 struct AddForm { name: String, response: String, csrf: String }
 
@@ -149,14 +294,18 @@ async fn add_command(State(state): State<AppState>, jar: axum_extra::extract::co
     if jar.get("csrf").map(|c| c.value()) != Some(f.csrf.as_str()) { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
     // 認可チェック
     let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Redirect::to("/").into_response(); };
-    let Some(access_token) = session::open_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
-    if let Ok(gs) = oauth::fetch_user_guilds(&access_token).await {
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
+    if let Ok(gs) = oauth::fetch_user_guilds(&state.discord_api_base, &access_token).await {
         let ok = gs.iter().any(|g| g.id.parse::<i64>().ok() == Some(guild_id));
         if !ok { return Redirect::to("/").into_response(); }
     }
-    let ok = crate::commands::add_command(&state.pool, guild_id, &f.name, &f.response).await;
-    let to = format!("/guilds/{guild_id}/commands");
-    if ok { Redirect::to(&to).into_response() } else { (StatusCode::BAD_REQUEST, "failed to add").into_response() }
+    match state.store.add_command(guild_id, &f.name, &f.response).await {
+        Ok(()) => {
+            commands_changed(&state, guild_id).await;
+            Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
+        }
+        Err(e) => command_error_response(e),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -165,35 +314,197 @@ struct UpdateForm { name: String, response: String, csrf: String }
 async fn update_command(State(state): State<AppState>, jar: axum_extra::extract::cookie::CookieJar, Path(guild_id): Path<i64>, Form(f): Form<UpdateForm>) -> impl IntoResponse {
     if jar.get("csrf").map(|c| c.value()) != Some(f.csrf.as_str()) { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
     let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Redirect::to("/").into_response(); };
-    let Some(access_token) = session::open_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
-    if let Ok(gs) = oauth::fetch_user_guilds(&access_token).await {
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
+    if let Ok(gs) = oauth::fetch_user_guilds(&state.discord_api_base, &access_token).await {
         let ok = gs.iter().any(|g| g.id.parse::<i64>().ok() == Some(guild_id));
         if !ok { return Redirect::to("/").into_response(); }
     }
-    let ok = crate::commands::update_command(&state.pool, guild_id, &f.name, &f.response).await;
-    let to = format!("/guilds/{guild_id}/commands");
-    if ok { Redirect::to(&to).into_response() } else { (StatusCode::BAD_REQUEST, "failed to update").into_response() }
+    match state.store.update_command(guild_id, &f.name, &f.response).await {
+        Ok(()) => {
+            commands_changed(&state, guild_id).await;
+            Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
+        }
+        Err(e) => command_error_response(e),
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct BulkDeleteForm { names: Option<Vec<String>>, csrf: String }
-
-async fn bulk_delete_commands(State(state): State<AppState>, jar: axum_extra::extract::cookie::CookieJar, Path(guild_id): Path<i64>, Form(f): Form<BulkDeleteForm>) -> impl IntoResponse {
-    if jar.get("csrf").map(|c| c.value()) != Some(f.csrf.as_str()) { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
+// チェックボックスの names は複数回送られるため、キーと値の組で受け取る
+async fn bulk_delete_commands(State(state): State<AppState>, jar: axum_extra::extract::cookie::CookieJar, Path(guild_id): Path<i64>, Form(pairs): Form<Vec<(String, String)>>) -> impl IntoResponse {
+    let csrf = pairs.iter().find(|(k, _)| k == "csrf").map(|(_, v)| v.as_str());
+    if csrf.is_none() || jar.get("csrf").map(|c| c.value()) != csrf { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
     let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Redirect::to("/").into_response(); };
-    let Some(access_token) = session::open_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
-    if let Ok(gs) = oauth::fetch_user_guilds(&access_token).await {
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Redirect::to("/").into_response(); };
+    if let Ok(gs) = oauth::fetch_user_guilds(&state.discord_api_base, &access_token).await {
         let ok = gs.iter().any(|g| g.id.parse::<i64>().ok() == Some(guild_id));
         if !ok { return Redirect::to("/").into_response(); }
     }
-    if let Some(names) = f.names {
+    let names: Vec<String> = pairs.into_iter().filter(|(k, _)| k == "names").map(|(_, v)| v).collect();
+    if !names.is_empty() {
+        let mut removed_slash = false;
         for name in names {
-            let _ = crate::commands::remove_command(&state.pool, guild_id, &name).await;
+            let was_slash = match state.store.get_command(guild_id, &name).await {
+                Ok(command) => command.is_some_and(|c| c.slash),
+                Err(e) => return command_error_response(e),
+            };
+            match state.store.remove_command(guild_id, &name).await {
+                Ok(()) => removed_slash |= was_slash,
+                // 既に削除済みのものは無視する
+                Err(crate::commands::CommandError::NotFound) => {}
+                Err(e) => return command_error_response(e),
+            }
+        }
+        // 公開中のスラッシュコマンドを削除した場合は登録し直す
+        if removed_slash {
+            let _ = crate::registration::register_guild_commands(&state.http, state.store.as_ref(), serenity::model::id::GuildId(guild_id as u64)).await;
+        }
+        commands_changed(&state, guild_id).await;
+    }
+    Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SlashForm {
+    name: String,
+    enabled: Option<String>,
+    description: Option<String>,
+    arguments: Option<String>,
+    csrf: String,
+}
+
+async fn update_slash(State(state): State<AppState>, jar: axum_extra::extract::cookie::CookieJar, Path(guild_id): Path<i64>, Form(f): Form<SlashForm>) -> impl IntoResponse {
+    if jar.get("csrf").map(|c| c.value()) != Some(f.csrf.as_str()) { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
+    if let Err(response) = require_guild_member(&state, &jar, guild_id).await {
+        return response;
+    }
+    let options = crate::commands::SlashOptions {
+        enabled: f.enabled.is_some(),
+        description: f.description.filter(|d| !d.trim().is_empty()),
+        arguments: f.arguments.as_deref().map(crate::registration::parse_arguments).unwrap_or_default(),
+    };
+    if let Err(msg) = crate::registration::validate_slash_options(state.store.as_ref(), guild_id, &f.name, &options).await {
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    let previous = match state.store.get_command(guild_id, &f.name).await {
+        Ok(Some(command)) => command.slash_options(),
+        Ok(None) => return command_error_response(crate::commands::CommandError::NotFound),
+        Err(e) => return command_error_response(e),
+    };
+    if let Err(e) = state.store.set_slash(guild_id, &f.name, &options).await {
+        return command_error_response(e);
+    }
+    // 登録の失敗をその場で表示するため、Bot を待たずに登録する
+    let registered = crate::registration::register_guild_commands(&state.http, state.store.as_ref(), serenity::model::id::GuildId(guild_id as u64)).await;
+    if let Err(msg) = registered {
+        // Discord に登録されている内容と食い違わないよう元の設定に戻す
+        let msg = match state.store.set_slash(guild_id, &f.name, &previous).await {
+            Ok(()) => format!("{msg}\n公開設定は変更していません。"),
+            Err(e) => format!("{msg}\n公開設定を元に戻せませんでした。{}", e.report()),
+        };
+        return (StatusCode::BAD_GATEWAY, msg).into_response();
+    }
+    commands_changed(&state, guild_id).await;
+    Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct DeliveryForm {
+    name: String,
+    reply_mode: String,
+    target_channel_id: Option<String>,
+    delete_trigger: Option<String>,
+    delete_after: Option<String>,
+    csrf: String,
+}
+
+async fn update_delivery(State(state): State<AppState>, jar: axum_extra::extract::cookie::CookieJar, Path(guild_id): Path<i64>, Form(f): Form<DeliveryForm>) -> impl IntoResponse {
+    if jar.get("csrf").map(|c| c.value()) != Some(f.csrf.as_str()) { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
+    if let Err(response) = require_guild_member(&state, &jar, guild_id).await {
+        return response;
+    }
+    let Some(reply_mode) = crate::commands::ReplyMode::parse(&f.reply_mode) else { return (StatusCode::BAD_REQUEST, "invalid reply mode").into_response(); };
+    let target_channel_id = f.target_channel_id.as_deref().and_then(|v| v.parse::<i64>().ok());
+    if reply_mode == crate::commands::ReplyMode::Channel && target_channel_id.is_none() {
+        return (StatusCode::BAD_REQUEST, "target channel is required").into_response();
+    }
+    // 別のギルドのチャンネルには送らない
+    if let Some(id) = target_channel_id {
+        if !scope_pickers(&state, guild_id).await.has_channel(id) {
+            return (StatusCode::BAD_REQUEST, "unknown target channel").into_response();
+        }
+    }
+    let delivery = crate::commands::Delivery {
+        reply_mode,
+        target_channel_id,
+        // チェックボックスは未チェック時に送信されない
+        delete_trigger: f.delete_trigger.is_some(),
+        delete_after: f
+            .delete_after
+            .as_deref()
+            .and_then(|v| v.parse::<i32>().ok())
+            .filter(|secs| *secs > 0)
+            .map(|secs| secs.min(crate::commands::MAX_DELETE_AFTER_SECS)),
+    };
+    match state.store.set_delivery(guild_id, &f.name, &delivery).await {
+        Ok(()) => {
+            commands_changed(&state, guild_id).await;
+            Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
         }
+        Err(e) => command_error_response(e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AddScopeForm { name: String, target: String, mode: String, csrf: String }
+
+async fn add_scope(State(state): State<AppState>, jar: axum_extra::extract::cookie::CookieJar, Path(guild_id): Path<i64>, Form(f): Form<AddScopeForm>) -> impl IntoResponse {
+    if jar.get("csrf").map(|c| c.value()) != Some(f.csrf.as_str()) { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
+    if let Err(response) = require_guild_member(&state, &jar, guild_id).await {
+        return response;
+    }
+    // target は "<kind>:<id>" 形式
+    let parsed = f
+        .target
+        .split_once(':')
+        .and_then(|(k, id)| Some((crate::scopes::ScopeKind::parse(k)?, id.parse::<i64>().ok()?)));
+    let Some((kind, target_id)) = parsed else { return (StatusCode::BAD_REQUEST, "invalid target").into_response(); };
+    match state.store.set_scope(guild_id, &f.name, kind, target_id, f.mode != "deny").await {
+        Ok(()) => {
+            commands_changed(&state, guild_id).await;
+            Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
+        }
+        Err(e) => command_error_response(e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveScopeForm { name: String, kind: String, target_id: i64, csrf: String }
+
+async fn remove_scope(State(state): State<AppState>, jar: axum_extra::extract::cookie::CookieJar, Path(guild_id): Path<i64>, Form(f): Form<RemoveScopeForm>) -> impl IntoResponse {
+    if jar.get("csrf").map(|c| c.value()) != Some(f.csrf.as_str()) { return (StatusCode::BAD_REQUEST, "invalid csrf").into_response(); }
+    if let Err(response) = require_guild_member(&state, &jar, guild_id).await {
+        return response;
+    }
+    let Some(kind) = crate::scopes::ScopeKind::parse(&f.kind) else { return (StatusCode::BAD_REQUEST, "invalid kind").into_response(); };
+    match state.store.remove_scope(guild_id, &f.name, kind, f.target_id).await {
+        Ok(()) => commands_changed(&state, guild_id).await,
+        // 既に削除済みのものは無視する
+        Err(crate::commands::CommandError::NotFound) => {}
+        Err(e) => return command_error_response(e),
     }
     Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
 }
 
+// コマンド操作のエラーを HTTP レスポンスに変換する
+fn command_error_response(e: crate::commands::CommandError) -> axum::response::Response {
+    use crate::commands::CommandError;
+    let status = match e {
+        CommandError::AlreadyExists => StatusCode::CONFLICT,
+        CommandError::NotFound => StatusCode::NOT_FOUND,
+        CommandError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.report()).into_response()
+}
+
 // Redirect handler for accidental GET access to POST endpoints
 async fn redirect_to_commands(Path(guild_id): Path<i64>) -> impl IntoResponse {
     Redirect::to(&format!("/guilds/{guild_id}/commands")).into_response()
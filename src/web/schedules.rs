@@ -0,0 +1,135 @@
+// ギルドの予約投稿の一覧・追加・削除
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::get;
+use axum::{Form, Router};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+
+use super::router::scope_pickers;
+use super::templates::{ScheduleRow, SchedulesTemplate};
+use super::{oauth, session, AppState};
+use crate::list::ListSort;
+use crate::scheduler::{self, NewSchedule};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/guilds/:guild_id/schedules", get(schedules_page))
+        // POST 用のパスに GET でアクセスした場合は一覧に戻す
+        .route("/guilds/:guild_id/schedules/add", get(redirect_to_schedules).post(add_schedule))
+        .route("/guilds/:guild_id/schedules/remove", get(redirect_to_schedules).post(remove_schedule))
+}
+
+// ログイン中のユーザがギルドを管理できるか (オーナー・管理者・サーバー管理) 確認する。そうでなければそのまま返すレスポンス
+// 予約投稿は Bot の権限でどのチャンネルにも送れるため、メンバー全員には開放しない
+async fn require_manager(state: &AppState, jar: &CookieJar, guild_id: i64) -> Result<(), Response> {
+    let Some(sealed) = jar.get("session").map(|c| c.value().to_string()) else { return Err(Redirect::to("/").into_response()); };
+    let Some(access_token) = session::access_token(&state.session_key, &sealed) else { return Err(Redirect::to("/").into_response()); };
+    match oauth::fetch_user_guilds(&state.discord_api_base, &access_token).await {
+        Ok(gs) => match gs.into_iter().find(|g| g.id.parse::<i64>().ok() == Some(guild_id)) {
+            Some(guild) if guild.can_manage() => Ok(()),
+            Some(_) => Err((StatusCode::FORBIDDEN, "サーバー管理の権限が必要です。").into_response()),
+            None => Err(Redirect::to("/").into_response()),
+        },
+        Err(_) => Err((StatusCode::BAD_GATEWAY, "Failed to fetch guilds").into_response()),
+    }
+}
+
+// CSRF とギルドを管理できるかを確認する
+async fn authorize_action(state: &AppState, jar: &CookieJar, guild_id: i64, csrf: &str) -> Result<(), Response> {
+    if jar.get("csrf").map(|c| c.value()) != Some(csrf) {
+        return Err((StatusCode::BAD_REQUEST, "invalid csrf").into_response());
+    }
+    require_manager(state, jar, guild_id).await
+}
+
+async fn schedules_page(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>) -> Response {
+    if let Err(response) = require_manager(&state, &jar, guild_id).await {
+        return response;
+    }
+    let (schedules, commands) = match (state.store.list_schedules(guild_id).await, state.store.list_commands(guild_id, None, ListSort::Name).await) {
+        (Ok(schedules), Ok(commands)) => (schedules, commands),
+        (Err(e), _) | (_, Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    };
+    let pickers = scope_pickers(&state, guild_id).await;
+    let schedules = schedules
+        .into_iter()
+        .map(|s| {
+            let id = s.channel_id.to_string();
+            let channel = pickers.channels.iter().find(|o| o.id == id).map(|o| format!("#{}", o.name)).unwrap_or(id);
+            ScheduleRow {
+                id: s.id,
+                channel,
+                next_run: s.next_run_label(),
+                repeat: s.repeat_label(),
+                content: s.content_label(),
+                last_error: s.last_error.clone().unwrap_or_default(),
+            }
+        })
+        .collect();
+    let commands = commands.into_iter().map(|c| c.name).collect();
+    let tpl = SchedulesTemplate {
+        guild_id,
+        csrf: jar.get("csrf").map(|c| c.value().to_string()).unwrap_or_default(),
+        channels: pickers.channels,
+        commands,
+        schedules,
+    };
+    Html(tpl.render().unwrap()).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct AddScheduleForm {
+    channel_id: i64,
+    when: String,
+    #[serde(default)]
+    timezone: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    command_name: String,
+    csrf: String,
+}
+
+async fn add_schedule(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>, Form(f): Form<AddScheduleForm>) -> Response {
+    if let Err(response) = authorize_action(&state, &jar, guild_id, &f.csrf).await {
+        return response;
+    }
+    // 別のギルドのチャンネルには送らない
+    if !scope_pickers(&state, guild_id).await.has_channel(f.channel_id) {
+        return (StatusCode::BAD_REQUEST, "送信先のチャンネルが見つかりません。").into_response();
+    }
+    let new = NewSchedule {
+        guild_id,
+        channel_id: f.channel_id,
+        when: &f.when,
+        timezone: Some(&f.timezone),
+        content: Some(&f.content),
+        command_name: Some(&f.command_name),
+        // Web UI からの変更はユーザを記録しない (コマンドと同じ)
+        created_by: None,
+    };
+    match scheduler::create(state.store.as_ref(), new, crate::retention::now()).await {
+        Ok(_) => Redirect::to(&format!("/guilds/{guild_id}/schedules")).into_response(),
+        Err(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveScheduleForm { id: i64, csrf: String }
+
+async fn remove_schedule(State(state): State<AppState>, jar: CookieJar, Path(guild_id): Path<i64>, Form(f): Form<RemoveScheduleForm>) -> Response {
+    if let Err(response) = authorize_action(&state, &jar, guild_id, &f.csrf).await {
+        return response;
+    }
+    match state.store.remove_schedule(guild_id, f.id).await {
+        Ok(_) => Redirect::to(&format!("/guilds/{guild_id}/schedules")).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.report()).into_response(),
+    }
+}
+
+async fn redirect_to_schedules(Path(guild_id): Path<i64>) -> Redirect {
+    Redirect::to(&format!("/guilds/{guild_id}/schedules"))
+}
@@ -24,6 +24,21 @@ pub fn open_token(key_bytes: &[u8; 32], sealed: &str) -> Option<String> {
     String::from_utf8(msg.to_vec()).ok()
 }
 
+// セッション Cookie にはログイン時に確認したユーザ ID とアクセストークンを "<ユーザ ID>:<トークン>" で入れる
+pub fn seal_session(key_bytes: &[u8; 32], user_id: u64, access_token: &str) -> String {
+    seal_token(key_bytes, &format!("{}:{}", user_id, access_token))
+}
+
+pub fn open_session(key_bytes: &[u8; 32], sealed: &str) -> Option<(u64, String)> {
+    let value = open_token(key_bytes, sealed)?;
+    let (user_id, access_token) = value.split_once(':')?;
+    Some((user_id.parse().ok()?, access_token.to_string()))
+}
+
+pub fn access_token(key_bytes: &[u8; 32], sealed: &str) -> Option<String> {
+    open_session(key_bytes, sealed).map(|(_, access_token)| access_token)
+}
+
 pub fn derive_key_from_env(secret: &str) -> [u8; 32] {
     use sha2::Digest;
     let mut hasher = Sha256::new();
@@ -1,7 +1,5 @@
 use askama::Template;
 
-use crate::web::oauth::DiscordGuild;
-
 #[derive(Template)]
 #[template(source = r#"
 <!doctype html>
@@ -123,6 +121,8 @@ pub struct HomeTemplate {}
       main.container { max-width: 1024px; }
       .grid { grid-template-columns: repeat(auto-fill, minmax(200px, 1fr)); }
       article > header { font-weight: 600; }
+      .muted { color: var(--muted-color); }
+      .error { color: var(--del-color, #c62828); }
       h2 { font-size: 1.25rem; }
       header.container { padding: .25rem 0; }
       nav { margin: .25rem 0; }
@@ -135,6 +135,7 @@ pub struct HomeTemplate {}
         <ul>
           <li><a href='/' class='contrast'><strong>nkmzbot</strong></a></li>
           <li><a href='/dashboard'>Dashboard</a></li>
+          <li><a href='/reminders'>Reminders</a></li>
         </ul>
         <ul>
           <li>{{ username.as_deref().unwrap_or("") }}</li>
@@ -155,6 +156,13 @@ pub struct HomeTemplate {}
           <li>
             <article>
               <header>{{ g.name }}</header>
+              {% if g.registration_status == "ok" %}
+                <small class='muted' title='{{ g.registration_updated_at }}'>スラッシュコマンド: 登録済み</small>
+              {% else if g.registration_status == "error" %}
+                <small class='error' title='{{ g.registration_error }}'>スラッシュコマンド: 登録失敗 ({{ g.registration_updated_at }})</small>
+              {% else %}
+                <small class='muted'>スラッシュコマンド: 登録待ち</small>
+              {% endif %}
               <footer>
                 <a href="/guilds/{{ g.id }}/commands" role='button' class='primary'>管理する</a>
               </footer>
@@ -163,6 +171,27 @@ pub struct HomeTemplate {}
         {% endfor %}
         </ul>
       {% endif %}
+      {% if shards.len() > 0 %}
+        <h2>Gateway</h2>
+        <figure>
+          <table class='striped'>
+            <thead>
+              <tr><th>シャード</th><th>状態</th><th>レイテンシ</th><th>ギルド数</th><th>更新</th></tr>
+            </thead>
+            <tbody>
+            {% for s in shards %}
+              <tr>
+                <td>{{ s.id }} / {{ s.total }}</td>
+                <td>{% if s.stage == "connected" %}{{ s.stage }}{% else %}<span class='error'>{{ s.stage }}</span>{% endif %}</td>
+                <td>{{ s.latency }}</td>
+                <td>{{ s.guilds }}</td>
+                <td><small class='muted'>{{ s.updated_at }}</small></td>
+              </tr>
+            {% endfor %}
+            </tbody>
+          </table>
+        </figure>
+      {% endif %}
     </main>
     <script>
       // Shared SPA navigation (same as on Home)
@@ -219,7 +248,26 @@ pub struct HomeTemplate {}
 "#, ext = "html" )]
 pub struct DashboardTemplate {
     pub username: Option<String>,
-    pub guilds: Vec<DiscordGuild>,
+    pub guilds: Vec<DashboardGuild>,
+    pub shards: Vec<DashboardShard>,
+}
+
+// Bot プロセスが記録したシャードの状態
+pub struct DashboardShard {
+    pub id: i64,
+    pub total: i64,
+    pub stage: String,
+    pub latency: String,
+    pub guilds: i64,
+    pub updated_at: String,
+}
+
+pub struct DashboardGuild {
+    pub id: String,
+    pub name: String,
+    pub registration_status: String,
+    pub registration_error: String,
+    pub registration_updated_at: String,
 }
 
 #[derive(askama::Template)]
@@ -243,6 +291,10 @@ pub struct DashboardTemplate {
       .table-wrap { overflow-x: auto; }
       .muted { color: var(--muted-color); }
       table th, table td { padding: .4rem .5rem; }
+      ul.scopes { padding-left: 0; margin-bottom: .25rem; }
+      ul.scopes li { list-style: none; }
+      ul.scopes form { display: flex; gap: .25rem; align-items: center; margin: 0; }
+      ul.scopes button { padding: 0 .4rem; margin: 0; width: auto; }
       button, [role='button'], input, select, textarea { font-size: .95rem; }
       header.container { padding: .25rem 0; }
       nav { margin: .25rem 0; }
@@ -262,6 +314,7 @@ pub struct DashboardTemplate {
     </header>
     <main id='app' class='container'>
       <h2>Guild {{ guild_id }} のコマンド</h2>
+      <p><a href='/guilds/{{ guild_id }}/schedules'>予約投稿 &rarr;</a> <a href='/guilds/{{ guild_id }}/greetings'>入退室メッセージ &rarr;</a></p>
 
       <form method='get' class='toolbar'>
         <input type='text' name='q' placeholder='キーワードで検索' value='{{ q }}'>
@@ -290,7 +343,7 @@ pub struct DashboardTemplate {
       <div class='table-wrap'>
         <table>
           <thead>
-            <tr><th style='width:4rem'><input type='checkbox' id='select-all'></th><th>name</th><th>response</th><th style='width:8rem'>更新</th></tr>
+            <tr><th style='width:4rem'><input type='checkbox' id='select-all'></th><th>name</th><th>response</th><th style='width:16rem'>スコープ</th></tr>
           </thead>
           <tbody>
           {% for c in commands %}
@@ -308,8 +361,108 @@ pub struct DashboardTemplate {
                   </details>
                   <button type='submit'>更新</button>
                 </form>
+                <details>
+                  <summary>スラッシュコマンド{% if c.slash %} (公開中){% endif %}</summary>
+                  <form method='post' action='/guilds/{{ guild_id }}/commands/slash'>
+                    <input type='hidden' name='csrf' value='{{ csrf }}'>
+                    <input type='hidden' name='name' value='{{ c.name }}'>
+                    <label>
+                      <input type='checkbox' name='enabled' value='1' {% if c.slash %}checked{% endif %}>
+                      <code>/{{ c.name }}</code> として公開する
+                    </label>
+                    <div class='grid'>
+                      <label>
+                        説明
+                        <input name='description' maxlength='100' value='{{ c.description }}' placeholder='カスタムコマンド'>
+                      </label>
+                      <label>
+                        引数 (スペース区切り)
+                        <input name='arguments' value='{{ c.arguments }}' placeholder='例: user reason'>
+                      </label>
+                    </div>
+                    <small class='muted'>返答中の <code>{引数名}</code> が入力値に置き換わります。</small>
+                    <button type='submit'>保存</button>
+                  </form>
+                </details>
+                <details>
+                  <summary>送信方法</summary>
+                  <form method='post' action='/guilds/{{ guild_id }}/commands/delivery'>
+                    <input type='hidden' name='csrf' value='{{ csrf }}'>
+                    <input type='hidden' name='name' value='{{ c.name }}'>
+                    <div class='grid'>
+                      <label>
+                        送り方
+                        <select name='reply_mode'>
+                        {% for m in reply_modes %}
+                          <option value='{{ m.id }}' {% if m.id == c.reply_mode %}selected{% endif %}>{{ m.name }}</option>
+                        {% endfor %}
+                        </select>
+                      </label>
+                      <label>
+                        送信先チャンネル
+                        <select name='target_channel_id'>
+                          <option value=''>(なし)</option>
+                        {% for o in channels %}
+                          <option value='{{ o.id }}' {% if o.id == c.target_channel_id %}selected{% endif %}>#{{ o.name }}</option>
+                        {% endfor %}
+                        </select>
+                      </label>
+                      <label>
+                        自動削除 (秒)
+                        <input type='number' name='delete_after' min='0' max='86400' value='{{ c.delete_after }}' placeholder='0 で無効'>
+                      </label>
+                    </div>
+                    <label>
+                      <input type='checkbox' name='delete_trigger' value='1' {% if c.delete_trigger %}checked{% endif %}>
+                      実行したメッセージを削除する
+                    </label>
+                    <button type='submit'>保存</button>
+                  </form>
+                </details>
+              </td>
+              <td>
+                {% if c.scopes.len() == 0 %}
+                  <small class='muted'>すべてのチャンネルで有効</small>
+                {% else %}
+                  <ul class='scopes'>
+                  {% for s in c.scopes %}
+                    <li>
+                      <form method='post' action='/guilds/{{ guild_id }}/commands/scopes/remove'>
+                        <input type='hidden' name='csrf' value='{{ csrf }}'>
+                        <input type='hidden' name='name' value='{{ c.name }}'>
+                        <input type='hidden' name='kind' value='{{ s.kind }}'>
+                        <input type='hidden' name='target_id' value='{{ s.target_id }}'>
+                        <small>{% if s.allow %}許可{% else %}拒否{% endif %}: {{ s.label }}</small>
+                        <button type='submit' class='secondary outline' title='削除'>&times;</button>
+                      </form>
+                    </li>
+                  {% endfor %}
+                  </ul>
+                {% endif %}
+                <details>
+                  <summary>スコープを追加</summary>
+                  <form method='post' action='/guilds/{{ guild_id }}/commands/scopes/add'>
+                    <input type='hidden' name='csrf' value='{{ csrf }}'>
+                    <input type='hidden' name='name' value='{{ c.name }}'>
+                    <select name='mode'>
+                      <option value='allow'>許可</option>
+                      <option value='deny'>拒否</option>
+                    </select>
+                    <select name='target' required>
+                      <optgroup label='チャンネル'>
+                      {% for o in channels %}<option value='channel:{{ o.id }}'>#{{ o.name }}</option>{% endfor %}
+                      </optgroup>
+                      <optgroup label='カテゴリ'>
+                      {% for o in categories %}<option value='category:{{ o.id }}'>{{ o.name }}</option>{% endfor %}
+                      </optgroup>
+                      <optgroup label='ロール'>
+                      {% for o in roles %}<option value='role:{{ o.id }}'>@{{ o.name }}</option>{% endfor %}
+                      </optgroup>
+                    </select>
+                    <button type='submit'>追加</button>
+                  </form>
+                </details>
               </td>
-              <td></td>
             </tr>
           {% endfor %}
           </tbody>
@@ -386,7 +539,563 @@ pub struct CommandsTemplate {
     pub q: String,
     pub commands: Vec<CmdRow>,
     pub csrf: String,
+    pub channels: Vec<PickerOption>,
+    pub categories: Vec<PickerOption>,
+    pub roles: Vec<PickerOption>,
+    pub reply_modes: Vec<PickerOption>,
 }
 
 #[derive(Clone)]
-pub struct CmdRow { pub name: String, pub response: String }
+pub struct CmdRow {
+    pub name: String,
+    pub response: String,
+    pub scopes: Vec<ScopeRow>,
+    pub reply_mode: String,
+    pub target_channel_id: String,
+    pub delete_trigger: bool,
+    pub delete_after: String,
+    pub slash: bool,
+    pub description: String,
+    pub arguments: String,
+}
+
+#[derive(Clone)]
+pub struct ScopeRow { pub kind: String, pub target_id: String, pub label: String, pub allow: bool }
+
+#[derive(Clone)]
+pub struct PickerOption { pub id: String, pub name: String }
+
+#[derive(Template)]
+#[template(source = r#"
+<!doctype html>
+<html lang='ja'>
+  <head>
+    <meta charset='utf-8'>
+    <meta name='viewport' content='width=device-width, initial-scale=1'>
+    <title>Admin - nkmzbot</title>
+    <link rel='preconnect' href='https://cdn.jsdelivr.net'>
+    <link rel='stylesheet' href='https://cdn.jsdelivr.net/npm/@picocss/pico@2/css/pico.min.css'>
+    <style>
+      html { font-size: 15px; }
+      @media (min-width: 1200px) { html { font-size: 16px; } }
+      body { line-height: 1.45; }
+      main.container { max-width: 1200px; }
+      .muted { color: var(--muted-color); }
+      .error { color: var(--del-color, #c62828); }
+      h2 { font-size: 1.25rem; }
+      table th, table td { padding: .4rem .5rem; }
+      td.actions form { display: inline; margin: 0; }
+      td.actions button { padding: 0 .4rem; margin: 0; width: auto; }
+      header.container { padding: .25rem 0; }
+      nav { margin: .25rem 0; }
+      button, [role='button'], input, select, textarea { font-size: .95rem; }
+    </style>
+  </head>
+  <body>
+    <header class='container'>
+      <nav>
+        <ul>
+          <li><a href='/' class='contrast'><strong>nkmzbot</strong></a></li>
+          <li><a href='/dashboard'>Dashboard</a></li>
+          <li><a href='/admin'>Admin</a></li>
+        </ul>
+        <ul>
+          <li>{{ username.as_deref().unwrap_or("") }}</li>
+          <li><a href='/logout' role='button' class='secondary'>Logout</a></li>
+        </ul>
+      </nav>
+    </header>
+    <main id='app' class='container'>
+      <h2>ギルド</h2>
+      <p class='muted'>DB の大きさ: {{ database_size }}</p>
+      <figure>
+        <table class='striped'>
+          <thead>
+            <tr><th>ギルド</th><th>コマンド</th><th>スコープ</th><th>データ量</th><th>スラッシュコマンド</th><th></th></tr>
+          </thead>
+          <tbody>
+          {% for g in guilds %}
+            <tr>
+              <td>
+                {% if g.name.is_empty() %}{{ g.id }}{% else %}{{ g.name }} <small class='muted'>{{ g.id }}</small>{% endif %}
+                {% if !g.joined %}<br><small class='muted'>未参加 (データのみ)</small>{% endif %}
+                {% if !g.scheduled_purge.is_empty() %}<br><small class='error'>{{ g.scheduled_purge }}</small>{% endif %}
+              </td>
+              <td>{{ g.commands }}</td>
+              <td>{{ g.scopes }}</td>
+              <td>{{ g.size }}</td>
+              <td>
+                {% if g.registration_status == "ok" %}
+                  <small class='muted' title='{{ g.registration_updated_at }}'>登録済み</small>
+                {% else if g.registration_status == "error" %}
+                  <small class='error' title='{{ g.registration_updated_at }}'>登録失敗: {{ g.registration_error }}</small>
+                {% else %}
+                  <small class='muted'>登録待ち</small>
+                {% endif %}
+              </td>
+              <td class='actions'>
+                <form method='post' action='/admin/guilds/{{ g.id }}/register'>
+                  <input type='hidden' name='csrf' value='{{ csrf }}'>
+                  <button type='submit' class='secondary'>再登録</button>
+                </form>
+                {% if g.joined %}
+                <form method='post' action='/admin/guilds/{{ g.id }}/leave' onsubmit="return confirm('ギルドから退出しますか？')">
+                  <input type='hidden' name='csrf' value='{{ csrf }}'>
+                  <button type='submit' class='secondary'>退出</button>
+                </form>
+                {% endif %}
+                <form method='post' action='/admin/guilds/{{ g.id }}/purge' onsubmit="return confirm('このギルドのデータをすべて削除しますか？')">
+                  <input type='hidden' name='csrf' value='{{ csrf }}'>
+                  <button type='submit' class='contrast'>データ削除</button>
+                </form>
+              </td>
+            </tr>
+          {% endfor %}
+          </tbody>
+        </table>
+      </figure>
+      <h2>最近の警告・エラー</h2>
+      {% if logs.len() == 0 %}
+        <p class='muted'>ありません</p>
+      {% else %}
+      <figure>
+        <table class='striped'>
+          <thead>
+            <tr><th>時刻</th><th>レベル</th><th>発生元</th><th>内容</th></tr>
+          </thead>
+          <tbody>
+          {% for l in logs %}
+            <tr>
+              <td><small class='muted'>{{ l.ago }}</small></td>
+              <td>{% if l.level == "ERROR" %}<span class='error'>{{ l.level }}</span>{% else %}{{ l.level }}{% endif %}</td>
+              <td><small>{{ l.target }}</small></td>
+              <td>{{ l.message }}</td>
+            </tr>
+          {% endfor %}
+          </tbody>
+        </table>
+      </figure>
+      {% endif %}
+    </main>
+  </body>
+</html>
+"#, ext = "html" )]
+pub struct AdminTemplate {
+    pub username: Option<String>,
+    pub csrf: String,
+    pub guilds: Vec<AdminGuild>,
+    pub database_size: String,
+    pub logs: Vec<AdminLog>,
+}
+
+#[derive(Default)]
+pub struct AdminGuild {
+    pub id: String,
+    // Bot が参加していないギルドは空
+    pub name: String,
+    // Bot が参加しているか (false ならデータだけが残っている)
+    pub joined: bool,
+    pub commands: i64,
+    pub scopes: i64,
+    pub size: String,
+    pub registration_status: String,
+    pub registration_error: String,
+    pub registration_updated_at: String,
+    // 退出後の削除予定 (予定がなければ空)
+    pub scheduled_purge: String,
+}
+
+pub struct AdminLog {
+    pub ago: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Template)]
+#[template(source = r#"
+<!doctype html>
+<html lang='ja'>
+  <head>
+    <meta charset='utf-8'>
+    <meta name='viewport' content='width=device-width, initial-scale=1'>
+    <title>Schedules - nkmzbot</title>
+    <link rel='preconnect' href='https://cdn.jsdelivr.net'>
+    <link rel='stylesheet' href='https://cdn.jsdelivr.net/npm/@picocss/pico@2/css/pico.min.css'>
+    <style>
+      html { font-size: 15px; }
+      @media (min-width: 1200px) { html { font-size: 16px; } }
+      body { line-height: 1.45; }
+      main.container { max-width: 1100px; }
+      textarea { min-height: 4.5rem; }
+      .muted { color: var(--muted-color); }
+      .error { color: var(--del-color, #c62828); }
+      table th, table td { padding: .4rem .5rem; }
+      td.actions form { margin: 0; }
+      td.actions button { padding: 0 .4rem; margin: 0; width: auto; }
+      header.container { padding: .25rem 0; }
+      nav { margin: .25rem 0; }
+      button, [role='button'], input, select, textarea { font-size: .95rem; }
+    </style>
+  </head>
+  <body>
+    <header class='container'>
+      <nav>
+        <ul>
+          <li><a href='/' class='contrast'><strong>nkmzbot</strong></a></li>
+          <li><a href='/dashboard'>Dashboard</a></li>
+        </ul>
+        <ul>
+          <li><a href='/guilds/{{ guild_id }}/commands'>&larr; コマンド</a></li>
+        </ul>
+      </nav>
+    </header>
+    <main id='app' class='container'>
+      <h2>Guild {{ guild_id }} の予約投稿</h2>
+
+      <article>
+        <header>追加</header>
+        <form method='post' action='/guilds/{{ guild_id }}/schedules/add'>
+          <input type='hidden' name='csrf' value='{{ csrf }}'>
+          <div class='grid'>
+            <label>
+              送信先チャンネル
+              <select name='channel_id' required>
+              {% for o in channels %}
+                <option value='{{ o.id }}'>#{{ o.name }}</option>
+              {% endfor %}
+              </select>
+            </label>
+            <label>
+              日時または cron 式
+              <input name='when' required placeholder='例: 2025-01-31 09:00 / 0 9 * * 1-5'>
+            </label>
+            <label>
+              タイムゾーン
+              <input name='timezone' value='UTC' placeholder='例: Asia/Tokyo, +09:00'>
+            </label>
+          </div>
+          <div class='grid'>
+            <label>
+              送る内容
+              <textarea name='content' maxlength='2000' placeholder='例: 今週の定例は 10 時からです'></textarea>
+            </label>
+            <label>
+              またはコマンド
+              <select name='command_name'>
+                <option value=''>(なし)</option>
+              {% for name in commands %}
+                <option value='{{ name }}'>!{{ name }}</option>
+              {% endfor %}
+              </select>
+            </label>
+          </div>
+          <small class='muted'>cron 式は「分 時 日 月 曜日」です。夏時間のあるタイムゾーンには対応していません。</small>
+          <button type='submit' class='primary'>追加</button>
+        </form>
+      </article>
+
+      <h3>一覧</h3>
+      {% if schedules.len() == 0 %}
+        <p class='muted'>予約はありません</p>
+      {% else %}
+      <figure>
+        <table class='striped'>
+          <thead>
+            <tr><th>ID</th><th>チャンネル</th><th>次回</th><th>繰り返し</th><th>内容</th><th></th></tr>
+          </thead>
+          <tbody>
+          {% for s in schedules %}
+            <tr>
+              <td>#{{ s.id }}</td>
+              <td>{{ s.channel }}</td>
+              <td>{{ s.next_run }}</td>
+              <td><small>{{ s.repeat }}</small></td>
+              <td>
+                {{ s.content }}
+                {% if !s.last_error.is_empty() %}<br><small class='error'>前回失敗: {{ s.last_error }}</small>{% endif %}
+              </td>
+              <td class='actions'>
+                <form method='post' action='/guilds/{{ guild_id }}/schedules/remove' onsubmit="return confirm('この予約を削除しますか？')">
+                  <input type='hidden' name='csrf' value='{{ csrf }}'>
+                  <input type='hidden' name='id' value='{{ s.id }}'>
+                  <button type='submit' class='secondary'>削除</button>
+                </form>
+              </td>
+            </tr>
+          {% endfor %}
+          </tbody>
+        </table>
+      </figure>
+      {% endif %}
+    </main>
+  </body>
+</html>
+"#, ext = "html" )]
+pub struct SchedulesTemplate {
+    pub guild_id: i64,
+    pub csrf: String,
+    pub channels: Vec<PickerOption>,
+    // 予約に使えるカスタムコマンド名
+    pub commands: Vec<String>,
+    pub schedules: Vec<ScheduleRow>,
+}
+
+pub struct ScheduleRow {
+    pub id: i64,
+    pub channel: String,
+    pub next_run: String,
+    pub repeat: String,
+    pub content: String,
+    // 前回送れなかった場合の理由 (なければ空)
+    pub last_error: String,
+}
+
+#[derive(Template)]
+#[template(source = r#"
+<!doctype html>
+<html lang='ja'>
+  <head>
+    <meta charset='utf-8'>
+    <meta name='viewport' content='width=device-width, initial-scale=1'>
+    <title>Greetings - nkmzbot</title>
+    <link rel='preconnect' href='https://cdn.jsdelivr.net'>
+    <link rel='stylesheet' href='https://cdn.jsdelivr.net/npm/@picocss/pico@2/css/pico.min.css'>
+    <style>
+      html { font-size: 15px; }
+      @media (min-width: 1200px) { html { font-size: 16px; } }
+      body { line-height: 1.45; }
+      main.container { max-width: 1100px; }
+      textarea { min-height: 4.5rem; }
+      .muted { color: var(--muted-color); }
+      .error { color: var(--del-color, #c62828); }
+      blockquote.preview { white-space: pre-wrap; margin: .25rem 0 1rem; }
+      form.inline { display: inline; }
+      form.inline button { width: auto; }
+      header.container { padding: .25rem 0; }
+      nav { margin: .25rem 0; }
+      button, [role='button'], input, select, textarea { font-size: .95rem; }
+    </style>
+  </head>
+  <body>
+    <header class='container'>
+      <nav>
+        <ul>
+          <li><a href='/' class='contrast'><strong>nkmzbot</strong></a></li>
+          <li><a href='/dashboard'>Dashboard</a></li>
+        </ul>
+        <ul>
+          <li><a href='/guilds/{{ guild_id }}/commands'>&larr; コマンド</a></li>
+        </ul>
+      </nav>
+    </header>
+    <main id='app' class='container'>
+      <h2>{{ server }} の入退室メッセージ</h2>
+      {% if degraded %}
+        <p class='error'>開発者ポータルで Server Members Intent が許可されていないため、現在は送信されません。</p>
+      {% else %}
+        <p class='muted'>Bot の設定で <code>features.greetings</code> を有効にし、開発者ポータルで Server Members Intent を許可すると送信されます。</p>
+      {% endif %}
+      <p class='muted'>
+        使えるプレースホルダ:
+        {% for (name, description) in placeholders %}<code>{{ name }}</code> {{ description }}{% if !loop.last %} / {% endif %}{% endfor %}
+      </p>
+
+      <form method='post' action='/guilds/{{ guild_id }}/greetings/save'>
+        <input type='hidden' name='csrf' value='{{ csrf }}'>
+        <article>
+          <header>
+            <label><input type='checkbox' role='switch' name='welcome_enabled' {% if welcome_enabled %}checked{% endif %}> 参加時のメッセージ</label>
+          </header>
+          <div class='grid'>
+            <label>
+              送信先チャンネル
+              <select name='welcome_channel_id'>
+                <option value=''>(チャンネルには送らない)</option>
+              {% for o in channels %}
+                <option value='{{ o.id }}' {% if o.id == welcome_channel_id %}selected{% endif %}>#{{ o.name }}</option>
+              {% endfor %}
+              </select>
+            </label>
+            <label>
+              自動で付けるロール
+              <select name='auto_role_id'>
+                <option value=''>(なし)</option>
+              {% for o in roles %}
+                <option value='{{ o.id }}' {% if o.id == auto_role_id %}selected{% endif %}>@{{ o.name }}</option>
+              {% endfor %}
+              </select>
+            </label>
+          </div>
+          <label>
+            メッセージ
+            <textarea name='welcome_message' maxlength='2000'>{{ welcome_message }}</textarea>
+          </label>
+          <label><input type='checkbox' name='welcome_dm' {% if welcome_dm %}checked{% endif %}> 参加したメンバーに DM でも送る</label>
+          <small class='muted'>自動ロールはメッセージの有効・無効に関係なく付けます (Bot のロールより下のロールのみ)。</small>
+        </article>
+
+        <article>
+          <header>
+            <label><input type='checkbox' role='switch' name='farewell_enabled' {% if farewell_enabled %}checked{% endif %}> 退出時のメッセージ</label>
+          </header>
+          <label>
+            送信先チャンネル
+            <select name='farewell_channel_id'>
+              <option value=''>(選択してください)</option>
+            {% for o in channels %}
+              <option value='{{ o.id }}' {% if o.id == farewell_channel_id %}selected{% endif %}>#{{ o.name }}</option>
+            {% endfor %}
+            </select>
+          </label>
+          <label>
+            メッセージ
+            <textarea name='farewell_message' maxlength='2000'>{{ farewell_message }}</textarea>
+          </label>
+        </article>
+        <button type='submit' class='primary'>保存</button>
+      </form>
+
+      <h3>プレビュー</h3>
+      <p class='muted'>保存済みの設定を、あなたが参加・退出した場合の例で表示します。</p>
+      <h4>参加時</h4>
+      <blockquote class='preview'>{{ welcome_preview }}</blockquote>
+      <form method='post' action='/guilds/{{ guild_id }}/greetings/test' class='inline'>
+        <input type='hidden' name='csrf' value='{{ csrf }}'>
+        <input type='hidden' name='kind' value='welcome'>
+        <button type='submit' class='secondary'>テスト送信</button>
+      </form>
+      <h4>退出時</h4>
+      <blockquote class='preview'>{{ farewell_preview }}</blockquote>
+      <form method='post' action='/guilds/{{ guild_id }}/greetings/test' class='inline'>
+        <input type='hidden' name='csrf' value='{{ csrf }}'>
+        <input type='hidden' name='kind' value='farewell'>
+        <button type='submit' class='secondary'>テスト送信</button>
+      </form>
+      <p><small class='muted'>テスト送信は有効・無効に関係なく、保存済みの送信先にあなた宛てのメッセージとして送ります (DM はあなたに届きます)。</small></p>
+    </main>
+  </body>
+</html>
+"#, ext = "html" )]
+pub struct GreetingsTemplate {
+    pub guild_id: i64,
+    pub server: String,
+    pub csrf: String,
+    pub channels: Vec<PickerOption>,
+    pub roles: Vec<PickerOption>,
+    // 特権インテントが許可されず送信できない
+    pub degraded: bool,
+    pub placeholders: Vec<(&'static str, &'static str)>,
+    pub welcome_enabled: bool,
+    // 未設定なら空
+    pub welcome_channel_id: String,
+    pub welcome_message: String,
+    pub welcome_dm: bool,
+    pub auto_role_id: String,
+    pub farewell_enabled: bool,
+    pub farewell_channel_id: String,
+    pub farewell_message: String,
+    pub welcome_preview: String,
+    pub farewell_preview: String,
+}
+
+#[derive(Template)]
+#[template(source = r#"
+<!doctype html>
+<html lang='ja'>
+  <head>
+    <meta charset='utf-8'>
+    <meta name='viewport' content='width=device-width, initial-scale=1'>
+    <title>Reminders - nkmzbot</title>
+    <link rel='preconnect' href='https://cdn.jsdelivr.net'>
+    <link rel='stylesheet' href='https://cdn.jsdelivr.net/npm/@picocss/pico@2/css/pico.min.css'>
+    <style>
+      html { font-size: 15px; }
+      @media (min-width: 1200px) { html { font-size: 16px; } }
+      body { line-height: 1.45; }
+      main.container { max-width: 1024px; }
+      .muted { color: var(--muted-color); }
+      h2 { font-size: 1.25rem; }
+      table th, table td { padding: .4rem .5rem; }
+      td.actions form { margin: 0; }
+      td.actions button { padding: 0 .4rem; margin: 0; width: auto; }
+      form.toolbar { display: flex; gap: .5rem; align-items: center; }
+      form.toolbar input { flex: 1 1 auto; margin: 0; }
+      form.toolbar button { width: auto; margin: 0; }
+      header.container { padding: .25rem 0; }
+      nav { margin: .25rem 0; }
+      button, [role='button'], input, select, textarea { font-size: .95rem; }
+    </style>
+  </head>
+  <body>
+    <header class='container'>
+      <nav>
+        <ul>
+          <li><a href='/' class='contrast'><strong>nkmzbot</strong></a></li>
+          <li><a href='/dashboard'>Dashboard</a></li>
+          <li><a href='/reminders'>Reminders</a></li>
+        </ul>
+        <ul>
+          <li>{{ username.as_deref().unwrap_or("") }}</li>
+          <li><a href='/logout' role='button' class='secondary'>Logout</a></li>
+        </ul>
+      </nav>
+    </header>
+    <main id='app' class='container'>
+      <h2>リマインダー</h2>
+      <p class='muted'>Discord の <code>/remind</code> で追加できます。</p>
+
+      <form method='post' action='/reminders/timezone' class='toolbar'>
+        <input type='hidden' name='csrf' value='{{ csrf }}'>
+        <label for='timezone'>タイムゾーン</label>
+        <input id='timezone' name='timezone' value='{{ timezone }}' placeholder='例: Asia/Tokyo, +09:00'>
+        <button type='submit'>保存</button>
+      </form>
+
+      {% if reminders.len() == 0 %}
+        <p class='muted'>リマインダーはありません</p>
+      {% else %}
+      <figure>
+        <table class='striped'>
+          <thead>
+            <tr><th>ID</th><th>日時</th><th>お知らせ先</th><th>内容</th><th></th></tr>
+          </thead>
+          <tbody>
+          {% for r in reminders %}
+            <tr>
+              <td>#{{ r.id }}</td>
+              <td>{{ r.remind_at }}</td>
+              <td>{{ r.place }}</td>
+              <td>{{ r.content }}</td>
+              <td class='actions'>
+                <form method='post' action='/reminders/cancel'>
+                  <input type='hidden' name='csrf' value='{{ csrf }}'>
+                  <input type='hidden' name='id' value='{{ r.id }}'>
+                  <button type='submit' class='secondary'>取り消す</button>
+                </form>
+              </td>
+            </tr>
+          {% endfor %}
+          </tbody>
+        </table>
+      </figure>
+      {% endif %}
+    </main>
+  </body>
+</html>
+"#, ext = "html" )]
+pub struct RemindersTemplate {
+    pub username: Option<String>,
+    pub csrf: String,
+    pub timezone: String,
+    pub reminders: Vec<ReminderRow>,
+}
+
+pub struct ReminderRow {
+    pub id: i64,
+    // ユーザのタイムゾーンでの日時
+    pub remind_at: String,
+    // "DM" またはチャンネル ID
+    pub place: String,
+    pub content: String,
+}
@@ -0,0 +1,717 @@
+// Web ルーターの結合テスト
+// Discord API はローカルに立てたモックサーバで置き換え、ストアはメモリ上のものを使う
+
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Form, Json, Router};
+use serde_json::json;
+use serenity::cache::Cache;
+use serenity::http::{Http, HttpBuilder};
+use tower::ServiceExt;
+
+use super::guilds::{self, GuildDirectory};
+use super::{session, AppState};
+use crate::commands::ReplyMode;
+use crate::events::{Event, EventBus};
+use crate::greetings::Greeting;
+use crate::health::{Health, ShardStatus};
+use crate::reminders::Reminder;
+use crate::scopes::ScopeKind;
+use crate::store::{CommandStore, MemoryCommandStore};
+
+const ACCESS_TOKEN: &str = "user-token";
+const CSRF: &str = "csrf-token";
+const SESSION_KEY: [u8; 32] = [7; 32];
+
+// ログインユーザが所属するギルド (1 と 3 のみ Bot にコマンドがある)
+// (ID, 名前, ログイン中のユーザの権限)。Beta ではサーバー管理の権限がない
+const USER_GUILDS: [(&str, &str, &str); 3] = [("1", "Alpha", "32"), ("2", "Beta", "0"), ("3", "Gamma", "8")];
+
+#[derive(Clone, Default)]
+struct MockDiscord {
+    // スラッシュコマンドの登録要求を受けたギルド
+    registered: Arc<Mutex<Vec<String>>>,
+    // チャンネル一覧の取得要求を受けたギルド
+    fetched_channels: Arc<Mutex<Vec<String>>>,
+    // Bot が退出したギルド
+    left: Arc<Mutex<Vec<String>>>,
+    // Bot が送ったメッセージ (チャンネル ID, 内容)
+    posted: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+fn authorized(headers: &HeaderMap) -> bool {
+    headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()) == Some(&format!("Bearer {}", ACCESS_TOKEN))
+}
+
+async fn mock_token(Form(form): Form<Vec<(String, String)>>) -> Response {
+    let code = form.iter().find(|(k, _)| k == "code").map(|(_, v)| v.as_str());
+    if code != Some("good-code") {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant" }))).into_response();
+    }
+    Json(json!({
+        "access_token": ACCESS_TOKEN,
+        "token_type": "Bearer",
+        "expires_in": 604800,
+        "refresh_token": "refresh",
+        "scope": "identify guilds",
+    }))
+    .into_response()
+}
+
+async fn mock_user(headers: HeaderMap) -> Response {
+    if !authorized(&headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    Json(json!({ "id": "10", "username": "tester", "global_name": "Tester", "avatar": null })).into_response()
+}
+
+async fn mock_guilds(headers: HeaderMap) -> Response {
+    if !authorized(&headers) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let guilds: Vec<_> = USER_GUILDS.iter().map(|(id, name, permissions)| json!({ "id": id, "name": name, "owner": false, "permissions": permissions })).collect();
+    Json(guilds).into_response()
+}
+
+async fn mock_register(State(mock): State<MockDiscord>, Path((_app, guild)): Path<(String, String)>) -> Response {
+    // Gamma では登録に失敗する
+    if guild == "3" {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "code": 50035, "message": "Invalid Form Body" }))).into_response();
+    }
+    mock.registered.lock().unwrap().push(guild);
+    Json(json!([])).into_response()
+}
+
+async fn mock_channels(State(mock): State<MockDiscord>, Path(guild): Path<String>) -> Response {
+    mock.fetched_channels.lock().unwrap().push(guild.clone());
+    Json(json!([{ "id": "20", "type": 0, "guild_id": guild, "name": "general", "position": 0, "permission_overwrites": [] }])).into_response()
+}
+
+async fn mock_roles(Path(guild): Path<String>) -> Response {
+    let role = |id: &str, name: &str, managed: bool, position: i64| {
+        json!({ "id": id, "name": name, "color": 0, "hoist": false, "managed": managed, "mentionable": false, "permissions": "0", "position": position })
+    };
+    // @everyone (ID がギルド ID と同じ) と連携で管理されるロールも返す
+    Json(json!([role(&guild, "@everyone", false, 0), role("30", "mods", false, 1), role("31", "integration", true, 2)])).into_response()
+}
+
+async fn mock_post_message(State(mock): State<MockDiscord>, Path(channel): Path<String>, Json(body): Json<serde_json::Value>) -> Response {
+    let content = body["content"].as_str().unwrap_or_default().to_string();
+    mock.posted.lock().unwrap().push((channel.clone(), content.clone()));
+    let author = json!({ "id": "1234", "username": "nkmzbot", "discriminator": "0000", "avatar": null, "bot": true });
+    Json(json!({
+        "id": "500", "channel_id": channel, "author": author, "content": content, "timestamp": "2025-01-06T12:00:00+00:00",
+        "edited_timestamp": null, "tts": false, "mention_everyone": false, "mentions": [], "mention_roles": [],
+        "attachments": [], "embeds": [], "pinned": false, "type": 0
+    }))
+    .into_response()
+}
+
+async fn mock_leave(State(mock): State<MockDiscord>, Path(guild): Path<String>) -> Response {
+    mock.left.lock().unwrap().push(guild);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+struct TestApp {
+    router: Router,
+    store: Arc<dyn CommandStore>,
+    mock: MockDiscord,
+    health: Arc<Health>,
+    http: Arc<Http>,
+    events: EventBus,
+    state: AppState,
+}
+
+async fn setup() -> TestApp {
+    let mock = MockDiscord::default();
+    let mock_router = Router::new()
+        .route("/api/oauth2/token", post(mock_token))
+        .route("/api/users/@me", get(mock_user))
+        .route("/api/users/@me/guilds", get(mock_guilds))
+        .route("/api/v10/applications/:app/guilds/:guild/commands", put(mock_register))
+        .route("/api/v10/guilds/:guild/channels", get(mock_channels))
+        .route("/api/v10/guilds/:guild/roles", get(mock_roles))
+        .route("/api/v10/users/@me/guilds/:guild", delete(mock_leave))
+        .route("/api/v10/channels/:channel/messages", post(mock_post_message))
+        .with_state(mock.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, mock_router).await.unwrap();
+    });
+
+    let store: Arc<dyn CommandStore> = Arc::new(MemoryCommandStore::new());
+    store.add_command(1, "hello", "world").await.unwrap();
+    store.add_command(3, "other", "guild").await.unwrap();
+    // ユーザが所属していないギルド
+    store.add_command(99, "secret", "hidden").await.unwrap();
+
+    // スラッシュコマンドの登録もモックサーバに送る
+    let http = HttpBuilder::new("bot-token")
+        .application_id(1234)
+        .proxy(base.clone())
+        .unwrap()
+        .ratelimiter_disabled(true)
+        .build();
+    let http = Arc::new(http);
+    let health = Arc::new(Health::new());
+    let events = EventBus::local();
+    let state = AppState {
+        store: store.clone(),
+        guilds: Arc::new(GuildDirectory::gateway(Arc::new(Cache::new()))),
+        http: http.clone(),
+        discord_client_id: "client-id".to_string(),
+        discord_client_secret: "client-secret".to_string(),
+        discord_redirect_uri: "http://localhost:3000/oauth/callback".to_string(),
+        discord_api_base: format!("{}/api", base),
+        session_key: SESSION_KEY,
+        health: health.clone(),
+        events: events.clone(),
+        // モックのログインユーザ (mock_user の id)
+        owner_ids: vec![10],
+    };
+    TestApp { router: super::build_router(state.clone()), store, mock, health, http, events, state }
+}
+
+impl TestApp {
+    async fn send(&self, request: Request<Body>) -> (StatusCode, HeaderMap, String) {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    async fn get(&self, uri: &str, cookie: &str) -> (StatusCode, HeaderMap, String) {
+        self.send(Request::get(uri).header(header::COOKIE, cookie).body(Body::empty()).unwrap()).await
+    }
+
+    // ログイン済みのセッションで form を POST する
+    async fn post(&self, uri: &str, form: &str) -> (StatusCode, HeaderMap, String) {
+        let request = Request::post(uri)
+            .header(header::COOKIE, logged_in())
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(form.to_string()))
+            .unwrap();
+        self.send(request).await
+    }
+}
+
+fn logged_in() -> String {
+    format!("session={}; csrf={}", session::seal_session(&SESSION_KEY, 10, ACCESS_TOKEN), CSRF)
+}
+
+fn location(headers: &HeaderMap) -> &str {
+    headers.get(header::LOCATION).and_then(|v| v.to_str().ok()).unwrap_or_default()
+}
+
+fn set_cookies(headers: &HeaderMap) -> Vec<String> {
+    headers.get_all(header::SET_COOKIE).iter().filter_map(|v| v.to_str().ok()).map(|v| v.to_string()).collect()
+}
+
+fn assert_redirect(response: &(StatusCode, HeaderMap, String), to: &str) {
+    assert!(response.0.is_redirection(), "expected a redirect, got {} {}", response.0, response.2);
+    assert_eq!(location(&response.1), to);
+}
+
+#[tokio::test]
+async fn login_redirects_to_discord_with_state_cookie() {
+    let app = setup().await;
+    let (status, headers, _) = app.get("/login", "").await;
+    assert!(status.is_redirection());
+    let location = location(&headers);
+    assert!(location.contains("/api/oauth2/authorize?client_id=client-id"));
+
+    let state = location.split("state=").nth(1).unwrap();
+    assert!(set_cookies(&headers).iter().any(|c| c.starts_with(&format!("oauth_state={};", state))));
+}
+
+#[tokio::test]
+async fn responses_carry_request_id() {
+    let app = setup().await;
+    let (_, headers, _) = app.get("/", "").await;
+    assert!(headers.get("x-request-id").is_some_and(|v| !v.is_empty()));
+
+    // クライアントが付けた ID はそのまま返す
+    let request = Request::get("/").header("x-request-id", "abc-123").body(Body::empty()).unwrap();
+    let (_, headers, _) = app.send(request).await;
+    assert_eq!(headers.get("x-request-id").unwrap(), "abc-123");
+}
+
+#[tokio::test]
+async fn callback_validates_state_and_issues_session() {
+    let app = setup().await;
+    let (status, _, body) = app.get("/oauth/callback?code=good-code&state=abc", "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::BAD_REQUEST, "missing oauth_state cookie"));
+    let (status, _, body) = app.get("/oauth/callback?code=good-code&state=abc", "oauth_state=other").await;
+    assert_eq!((status, body.as_str()), (StatusCode::BAD_REQUEST, "invalid state"));
+    let (status, _, _) = app.get("/oauth/callback?code=bad-code&state=abc", "oauth_state=abc").await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+
+    let response = app.get("/oauth/callback?code=good-code&state=abc", "oauth_state=abc").await;
+    assert_redirect(&response, "/dashboard");
+    let cookies = set_cookies(&response.1);
+    let sealed = cookies
+        .iter()
+        .find_map(|c| c.strip_prefix("session="))
+        .and_then(|c| c.split(';').next())
+        .unwrap();
+    // Set-Cookie の値はパーセントエンコードされている
+    let sealed = urlencoding::decode(sealed).unwrap();
+    assert_eq!(session::open_session(&SESSION_KEY, &sealed), Some((10, ACCESS_TOKEN.to_string())));
+    assert!(cookies.iter().any(|c| c.starts_with("username=Tester;")));
+}
+
+#[tokio::test]
+async fn dashboard_lists_only_member_guilds_with_commands() {
+    let app = setup().await;
+    assert_redirect(&app.get("/dashboard", "").await, "/");
+    assert_redirect(&app.get("/dashboard", "session=forged").await, "/");
+
+    let (status, _, body) = app.get("/dashboard", &logged_in()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("Alpha") && body.contains("Gamma"));
+    // コマンドのないギルドや所属していないギルドは表示しない
+    assert!(!body.contains("Beta"));
+    assert!(!body.contains("/guilds/99/"));
+    // シャードの状態はまだ記録されていない
+    assert!(!body.contains("Gateway"));
+
+    // Bot プロセスが記録したシャードの状態を表示する
+    let shard = ShardStatus { shard_id: 1, shard_total: 2, stage: "resuming".to_string(), latency_ms: Some(42), guilds: 7, updated_at: None };
+    app.store.record_shard_status(&shard).await.unwrap();
+    let (_, _, body) = app.get("/dashboard", &logged_in()).await;
+    assert!(body.contains("1 / 2") && body.contains("resuming") && body.contains("42 ms"));
+
+    let (status, _, body) = app.get("/guilds/1/commands", &logged_in()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("hello"));
+    assert_redirect(&app.get("/guilds/99/commands", &logged_in()).await, "/");
+}
+
+#[tokio::test]
+async fn readiness_follows_gateway_state() {
+    let app = setup().await;
+    // 起動直後はシャードが接続していないので準備未完了だが、生存はしている
+    let (status, _, body) = app.get("/readyz", "").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body.contains(r#""database":{"ok":true}"#));
+    assert_eq!(app.get("/healthz", "").await.0, StatusCode::OK);
+
+    app.health.update_shard(0, "connected", Some(std::time::Duration::from_millis(42)));
+    let (status, _, body) = app.get("/readyz", "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(r#""latency_ms":42"#));
+
+    // 一部のシャードが切断されると準備未完了に戻る
+    app.health.update_shard(1, "disconnected", None);
+    assert_eq!(app.get("/readyz", "").await.0, StatusCode::SERVICE_UNAVAILABLE);
+    app.health.client_restarting();
+    assert_eq!(app.get("/readyz", "").await.0, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn metrics_include_discord_api_calls() {
+    let app = setup().await;
+    app.get("/dashboard", &logged_in()).await;
+
+    let response = super::metrics_router()
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains(r#"nkmzbot_discord_api_duration_seconds_count{endpoint="users/@me/guilds"}"#));
+}
+
+#[tokio::test]
+async fn mutations_reject_invalid_csrf() {
+    let app = setup().await;
+    for (path, form) in [
+        ("add", "name=new&response=x"),
+        ("update", "name=hello&response=x"),
+        ("bulk-delete", "names=hello"),
+        ("slash", "name=hello&enabled=on"),
+        ("delivery", "name=hello&reply_mode=dm"),
+        ("scopes/add", "name=hello&target=channel:5&mode=allow"),
+        ("scopes/remove", "name=hello&kind=channel&target_id=5"),
+    ] {
+        let (status, _, body) = app.post(&format!("/guilds/1/commands/{}", path), &format!("{}&csrf=wrong", form)).await;
+        assert_eq!((status, body.as_str()), (StatusCode::BAD_REQUEST, "invalid csrf"), "{}", path);
+    }
+    let hello = app.store.get_command(1, "hello").await.unwrap().unwrap();
+    assert_eq!(hello.response, "world");
+    assert!(app.store.get_command(1, "new").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn mutations_require_guild_membership() {
+    let app = setup().await;
+    let response = app.post("/guilds/99/commands/update", &format!("name=secret&response=x&csrf={}", CSRF)).await;
+    assert_redirect(&response, "/");
+    assert_eq!(app.store.get_command(99, "secret").await.unwrap().unwrap().response, "hidden");
+}
+
+#[tokio::test]
+async fn mutations_fail_closed_when_guilds_cannot_be_fetched() {
+    let app = setup().await;
+    // モックが受け付けないトークンのセッションでは所属ギルドを取得できない
+    let cookie = format!("session={}; csrf={}", session::seal_session(&SESSION_KEY, 10, "revoked"), CSRF);
+    let post = |uri: &str, form: String| {
+        Request::post(uri)
+            .header(header::COOKIE, cookie.clone())
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(form))
+            .unwrap()
+    };
+    let (status, _, _) = app.send(post("/guilds/1/commands/scopes/add", format!("name=hello&target=role:7&mode=deny&csrf={}", CSRF))).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert!(app.store.get_scopes(1, "hello").await.unwrap().is_empty());
+
+    app.store.set_scope(1, "hello", ScopeKind::Role, 7, false).await.unwrap();
+    let (status, _, _) = app.send(post("/guilds/1/commands/scopes/remove", format!("name=hello&kind=role&target_id=7&csrf={}", CSRF))).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(app.store.get_scopes(1, "hello").await.unwrap().len(), 1);
+
+    let (status, _, _) = app.send(post("/guilds/1/commands/delivery", format!("name=hello&reply_mode=dm&csrf={}", CSRF))).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(app.store.get_command(1, "hello").await.unwrap().unwrap().reply_mode(), ReplyMode::Reply);
+
+    let (status, _, _) = app.send(post("/guilds/1/commands/slash", format!("name=hello&enabled=on&csrf={}", CSRF))).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert!(!app.store.get_command(1, "hello").await.unwrap().unwrap().slash);
+    assert!(app.mock.registered.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn command_mutation_routes() {
+    let mut app = setup().await;
+    // 送信先のチャンネルはモックの REST API から取得する
+    let mut state = app.state.clone();
+    state.guilds = Arc::new(GuildDirectory::rest(app.http.clone()));
+    app.router = super::build_router(state);
+    let back = "/guilds/1/commands";
+
+    assert_redirect(&app.post("/guilds/1/commands/add", &format!("name=new&response=created&csrf={}", CSRF)).await, back);
+    assert_eq!(app.store.get_command(1, "new").await.unwrap().unwrap().response, "created");
+    let (status, _, _) = app.post("/guilds/1/commands/add", &format!("name=new&response=again&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    assert_redirect(&app.post("/guilds/1/commands/update", &format!("name=new&response=changed&csrf={}", CSRF)).await, back);
+    assert_eq!(app.store.get_command(1, "new").await.unwrap().unwrap().response, "changed");
+    let (status, _, _) = app.post("/guilds/1/commands/update", &format!("name=missing&response=x&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let form = format!("name=new&reply_mode=channel&target_channel_id=20&delete_trigger=on&delete_after=10&csrf={}", CSRF);
+    assert_redirect(&app.post("/guilds/1/commands/delivery", &form).await, back);
+    let command = app.store.get_command(1, "new").await.unwrap().unwrap();
+    assert_eq!((command.reply_mode(), command.target_channel_id, command.delete_trigger, command.delete_after), (ReplyMode::Channel, Some(20), true, Some(10)));
+    let (status, _, _) = app.post("/guilds/1/commands/delivery", &format!("name=new&reply_mode=channel&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // このギルドにないチャンネルには送らない
+    let (status, _, _) = app.post("/guilds/1/commands/delivery", &form.replace("target_channel_id=20", "target_channel_id=55")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(app.store.get_command(1, "new").await.unwrap().unwrap().target_channel_id, Some(20));
+    // 自動削除までの秒数は上限に丸める
+    assert_redirect(&app.post("/guilds/1/commands/delivery", &form.replace("delete_after=10", "delete_after=999999")).await, back);
+    assert_eq!(app.store.get_command(1, "new").await.unwrap().unwrap().delete_after, Some(crate::commands::MAX_DELETE_AFTER_SECS));
+
+    let form = format!("name=new&enabled=on&description=desc&arguments=who+where&csrf={}", CSRF);
+    assert_redirect(&app.post("/guilds/1/commands/slash", &form).await, back);
+    let command = app.store.get_command(1, "new").await.unwrap().unwrap();
+    assert!(command.slash);
+    assert_eq!(command.arguments, ["who", "where"]);
+    assert_eq!(*app.mock.registered.lock().unwrap(), ["1"]);
+    let (status, _, _) = app.post("/guilds/1/commands/slash", &format!("name=add&enabled=on&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // 登録に失敗した場合は公開設定を元に戻す
+    let (status, _, body) = app.post("/guilds/3/commands/slash", &format!("name=other&enabled=on&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert!(body.contains("公開設定は変更していません"), "{body}");
+    assert!(!app.store.get_command(3, "other").await.unwrap().unwrap().slash);
+
+    assert_redirect(&app.post("/guilds/1/commands/scopes/add", &format!("name=new&target=role:7&mode=deny&csrf={}", CSRF)).await, back);
+    let scopes = app.store.get_scopes(1, "new").await.unwrap();
+    assert_eq!((scopes[0].kind(), scopes[0].target_id, scopes[0].allow), (Some(ScopeKind::Role), 7, false));
+    let (status, _, _) = app.post("/guilds/1/commands/scopes/add", &format!("name=new&target=bogus&mode=allow&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_redirect(&app.post("/guilds/1/commands/scopes/remove", &format!("name=new&kind=role&target_id=7&csrf={}", CSRF)).await, back);
+    assert!(app.store.get_scopes(1, "new").await.unwrap().is_empty());
+
+    // 公開中のスラッシュコマンドを削除するとギルドのコマンドを登録し直す
+    assert_redirect(&app.post("/guilds/1/commands/bulk-delete", &format!("names=new&names=hello&csrf={}", CSRF)).await, back);
+    assert!(app.store.get_command(1, "new").await.unwrap().is_none());
+    assert!(app.store.get_command(1, "hello").await.unwrap().is_none());
+    assert_eq!(*app.mock.registered.lock().unwrap(), ["1", "1"]);
+
+    // POST 用のパスに GET でアクセスした場合は一覧に戻す
+    assert_redirect(&app.get("/guilds/1/commands/add", &logged_in()).await, back);
+}
+
+#[tokio::test]
+async fn mutations_publish_commands_changed() {
+    let app = setup().await;
+    let mut events = app.events.subscribe();
+    let back = "/guilds/1/commands";
+    assert_redirect(&app.post("/guilds/1/commands/add", &format!("name=new&response=created&csrf={}", CSRF)).await, back);
+    assert_eq!(events.try_recv().unwrap(), Event::CommandsChanged { guild_id: 1 });
+    // 失敗した変更は通知しない
+    let (status, _, _) = app.post("/guilds/1/commands/add", &format!("name=new&response=again&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(events.try_recv().is_err());
+}
+
+// web-only ではチャンネル・ロールを REST API で取得し、Bot からの通知で取り直す
+#[tokio::test]
+async fn rest_directory_refetches_after_guild_changed() {
+    let app = setup().await;
+    let directory = Arc::new(GuildDirectory::rest(app.http.clone()));
+    tokio::spawn(guilds::follow_events(directory.clone(), app.events.subscribe()));
+
+    let data = directory.get(1).await;
+    assert_eq!(data.channels.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["general"]);
+    assert_eq!(data.roles.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), ["@everyone", "mods", "integration"]);
+    directory.get(1).await;
+    assert_eq!(*app.mock.fetched_channels.lock().unwrap(), ["1"]);
+
+    app.events.publish(Event::GuildChanged { guild_id: 1 }).await;
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    directory.get(1).await;
+    assert_eq!(*app.mock.fetched_channels.lock().unwrap(), ["1", "1"]);
+}
+
+// 接続を閉じた SQLite のストア (すべての操作が DB エラーになる)
+async fn unavailable_store() -> Arc<dyn CommandStore> {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new().connect("sqlite::memory:").await.unwrap();
+    pool.close().await;
+    Arc::new(crate::store::SqliteCommandStore::new(pool))
+}
+
+#[tokio::test]
+async fn registration_is_skipped_when_store_fails() {
+    let mut app = setup().await;
+    let mut state = app.state.clone();
+    state.store = unavailable_store().await;
+    app.router = super::build_router(state);
+    // 公開中のコマンドを読めない場合は、管理用コマンドだけで置き換えないよう登録しない
+    let (status, _, body) = app.post("/admin/guilds/1/register", &format!("csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert!(body.contains("登録に失敗しました"), "{body}");
+    assert!(app.mock.registered.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn admin_console_is_limited_to_owners() {
+    let mut app = setup().await;
+    assert_redirect(&app.get("/admin", "").await, "/");
+    // ユーザ ID を含まない古い形式のセッションはログインし直してもらう
+    assert_redirect(&app.get("/admin", &format!("session={}", session::seal_token(&SESSION_KEY, ACCESS_TOKEN))).await, "/");
+    let (status, _, body) = app.get("/admin", &logged_in()).await;
+    assert_eq!(status, StatusCode::OK);
+    // 所属していないギルドも含めて、データのある全ギルドを表示する
+    assert!(body.contains("/admin/guilds/1/purge") && body.contains("/admin/guilds/99/purge"));
+    assert!(body.contains("未参加"));
+    assert!(!body.contains("日後に削除"));
+    // 退出したギルドには削除予定を表示する
+    let now = crate::retention::now();
+    app.store.record_guild_departure(99, now, now + 3 * 24 * 60 * 60 + 60).await.unwrap();
+    let (_, _, body) = app.get("/admin", &logged_in()).await;
+    assert!(body.contains("3 日後に削除"));
+
+    // オーナーでなければ存在を隠す
+    let mut state = app.state.clone();
+    state.owner_ids = vec![11];
+    app.router = super::build_router(state);
+    assert_eq!(app.get("/admin", &logged_in()).await.0, StatusCode::NOT_FOUND);
+    let (status, _, _) = app.post("/admin/guilds/99/purge", &format!("csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(app.store.get_command(99, "secret").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn admin_actions() {
+    let app = setup().await;
+    let mut events = app.events.subscribe();
+
+    let (status, _, body) = app.post("/admin/guilds/99/purge", "csrf=wrong").await;
+    assert_eq!((status, body.as_str()), (StatusCode::BAD_REQUEST, "invalid csrf"));
+
+    assert_redirect(&app.post("/admin/guilds/99/register", &format!("csrf={}", CSRF)).await, "/admin");
+    assert_eq!(*app.mock.registered.lock().unwrap(), ["99"]);
+    assert_eq!(app.store.get_registration(99).await.unwrap().unwrap().status, "ok");
+
+    assert_redirect(&app.post("/admin/guilds/99/purge", &format!("csrf={}", CSRF)).await, "/admin");
+    assert!(app.store.get_command(99, "secret").await.unwrap().is_none());
+    assert!(app.store.get_registration(99).await.unwrap().is_none());
+    assert_eq!(events.try_recv().unwrap(), Event::CommandsChanged { guild_id: 99 });
+
+    assert_redirect(&app.post("/admin/guilds/3/leave", &format!("csrf={}", CSRF)).await, "/admin");
+    assert_eq!(*app.mock.left.lock().unwrap(), ["3"]);
+    // 退出してもデータは残す
+    assert!(app.store.get_command(3, "other").await.unwrap().is_some());
+
+    assert_redirect(&app.get("/admin/guilds/3/purge", &logged_in()).await, "/admin");
+}
+
+#[tokio::test]
+async fn schedule_routes() {
+    let mut app = setup().await;
+    // 送信先のチャンネルはモックの REST API から取得する
+    let mut state = app.state.clone();
+    state.guilds = Arc::new(GuildDirectory::rest(app.http.clone()));
+    app.router = super::build_router(state);
+    let back = "/guilds/1/schedules";
+
+    let form = format!("channel_id=20&when=0+9+*+*+1-5&timezone=%2B09%3A00&content=&command_name=hello&csrf={}", CSRF);
+    assert_redirect(&app.post("/guilds/1/schedules/add", &form).await, back);
+    let schedule = &app.store.list_schedules(1).await.unwrap()[0];
+    assert_eq!((schedule.cron.as_deref(), schedule.timezone.as_str(), schedule.command_name.as_deref()), (Some("0 9 * * 1-5"), "+09:00", Some("hello")));
+    let (status, _, body) = app.post("/guilds/1/schedules/add", &format!("channel_id=20&when=bogus&content=x&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("cron"), "{body}");
+    // このギルドにないチャンネルには送らない
+    let (status, _, body) = app.post("/guilds/1/schedules/add", &form.replace("channel_id=20", "channel_id=21")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("チャンネル"), "{body}");
+    assert_eq!(app.store.list_schedules(1).await.unwrap().len(), 1);
+
+    let (status, _, body) = app.get(back, &logged_in()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("0 9 * * 1-5 (+09:00)") && body.contains("!hello"), "{body}");
+
+    // 所属していないギルドや CSRF の不一致は受け付けない
+    assert_redirect(&app.get("/guilds/99/schedules", &logged_in()).await, "/");
+    let (status, _, _) = app.post("/guilds/1/schedules/remove", &format!("id={}&csrf=wrong", schedule.id)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_redirect(&app.post("/guilds/99/schedules/remove", &format!("id={}&csrf={}", schedule.id, CSRF)).await, "/");
+    assert_eq!(app.store.list_schedules(1).await.unwrap().len(), 1);
+    // サーバー管理の権限がないギルドでは一覧も変更もできない
+    let (status, _, _) = app.get("/guilds/2/schedules", &logged_in()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) = app.post("/guilds/2/schedules/add", &form).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(app.store.list_schedules(2).await.unwrap().is_empty());
+
+    assert_redirect(&app.post("/guilds/1/schedules/remove", &format!("id={}&csrf={}", schedule.id, CSRF)).await, back);
+    assert!(app.store.list_schedules(1).await.unwrap().is_empty());
+    assert_redirect(&app.get("/guilds/1/schedules/add", &logged_in()).await, back);
+}
+
+#[tokio::test]
+async fn reminder_routes() {
+    let app = setup().await;
+    let reminder = |user_id: i64, channel_id: Option<i64>| Reminder {
+        id: 0,
+        user_id,
+        guild_id: Some(1),
+        channel_id,
+        content: format!("tea for {}", user_id),
+        remind_at: 1_736_164_800,
+    };
+    // モックのログインユーザ (id 10) と別のユーザ
+    let mine = app.store.add_reminder(&reminder(10, Some(20))).await.unwrap();
+    app.store.add_reminder(&reminder(10, None)).await.unwrap();
+    let theirs = app.store.add_reminder(&reminder(11, None)).await.unwrap();
+
+    assert_redirect(&app.get("/reminders", "").await, "/");
+    let (status, _, body) = app.get("/reminders", &logged_in()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("tea for 10") && !body.contains("tea for 11"), "{body}");
+    assert!(body.contains("2025-01-06 12:00") && body.contains("チャンネル 20") && body.contains("DM"), "{body}");
+
+    // タイムゾーンを変えると表示も変わる
+    let (status, _, _) = app.post("/reminders/timezone", &format!("timezone=Europe%2FParis&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_redirect(&app.post("/reminders/timezone", &format!("timezone=Asia%2FTokyo&csrf={}", CSRF)).await, "/reminders");
+    let (_, _, body) = app.get("/reminders", &logged_in()).await;
+    assert!(body.contains("2025-01-06 21:00") && body.contains("Asia/Tokyo"), "{body}");
+
+    // 他のユーザのものや CSRF の不一致では取り消せない
+    let (status, _, _) = app.post("/reminders/cancel", &format!("id={}&csrf=wrong", mine)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_redirect(&app.post("/reminders/cancel", &format!("id={}&csrf={}", theirs, CSRF)).await, "/reminders");
+    assert_eq!(app.store.list_reminders(11).await.unwrap().len(), 1);
+    assert_redirect(&app.post("/reminders/cancel", &format!("id={}&csrf={}", mine, CSRF)).await, "/reminders");
+    assert_eq!(app.store.list_reminders(10).await.unwrap().len(), 1);
+    assert_redirect(&app.get("/reminders/cancel", &logged_in()).await, "/reminders");
+}
+
+#[tokio::test]
+async fn greeting_routes() {
+    let mut app = setup().await;
+    // チャンネルとロールの選択肢はモックの REST API から取得する
+    let mut state = app.state.clone();
+    state.guilds = Arc::new(GuildDirectory::rest(app.http.clone()));
+    app.router = super::build_router(state);
+    let back = "/guilds/1/greetings";
+
+    // 未設定なら既定のメッセージでプレビューする (ログイン中のユーザとギルド名を使う)
+    let (status, _, body) = app.get(back, &logged_in()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("@Tester さん、Alpha へようこそ！"), "{body}");
+    assert!(body.contains("#general") && body.contains("@mods"), "{body}");
+    // 自動で付けられないロールは選択肢に出さない
+    assert!(!body.contains("@integration") && !body.contains("@@everyone"), "{body}");
+
+    // 有効にするには送り先が必要
+    let form = |extra: &str| format!("welcome_enabled=on&welcome_message=%7Buser%7D+hi+%7Bserver%7D&auto_role_id=30&farewell_channel_id=&farewell_message=bye&{}&csrf={}", extra, CSRF);
+    let (status, _, body) = app.post("/guilds/1/greetings/save", &form("welcome_channel_id=")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("DM"), "{body}");
+    // このギルドにないチャンネルや、付けられないロールは保存しない
+    for (extra, expected) in [
+        ("welcome_channel_id=21", "チャンネル"),
+        ("welcome_channel_id=20&farewell_channel_id=99", "チャンネル"),
+        ("welcome_channel_id=20&auto_role_id=31", "ロール"),
+        ("welcome_channel_id=20&auto_role_id=1", "ロール"),
+    ] {
+        let (status, _, body) = app.post("/guilds/1/greetings/save", &form(extra).replace("auto_role_id=30&", "").replace("farewell_channel_id=&", "")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{extra}");
+        assert!(body.contains(expected), "{extra}: {body}");
+    }
+    assert!(app.store.get_greeting(1).await.unwrap().is_none());
+    assert_redirect(&app.post("/guilds/1/greetings/save", &form("welcome_channel_id=20")).await, back);
+    let saved = app.store.get_greeting(1).await.unwrap().unwrap();
+    assert_eq!(
+        saved,
+        Greeting {
+            guild_id: 1,
+            welcome_enabled: true,
+            welcome_channel_id: Some(20),
+            welcome_message: "{user} hi {server}".to_string(),
+            welcome_dm: false,
+            auto_role_id: Some(30),
+            farewell_enabled: false,
+            farewell_channel_id: None,
+            farewell_message: "bye".to_string(),
+        }
+    );
+    let (_, _, body) = app.get(back, &logged_in()).await;
+    assert!(body.contains("@Tester hi Alpha") && body.contains("value='30' selected"), "{body}");
+
+    // テスト送信はログイン中のユーザ宛てのメッセージとして送る
+    assert_redirect(&app.post("/guilds/1/greetings/test", &format!("kind=welcome&csrf={}", CSRF)).await, back);
+    assert_eq!(*app.mock.posted.lock().unwrap(), [("20".to_string(), "<@10> hi Alpha".to_string())]);
+    let (status, _, body) = app.post("/guilds/1/greetings/test", &format!("kind=farewell&csrf={}", CSRF)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("チャンネル"), "{body}");
+
+    // 所属していないギルドや CSRF の不一致、サーバー管理の権限がない場合は受け付けない
+    assert_redirect(&app.get("/guilds/99/greetings", &logged_in()).await, "/");
+    let (status, _, _) = app.get("/guilds/2/greetings", &logged_in()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _, _) = app.post("/guilds/2/greetings/save", &form("welcome_channel_id=20")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(app.store.get_greeting(2).await.unwrap().is_none());
+    let (status, _, _) = app.post("/guilds/1/greetings/save", "welcome_message=x&csrf=wrong").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_redirect(&app.post("/guilds/99/greetings/test", &format!("kind=welcome&csrf={}", CSRF)).await, "/");
+    assert_eq!(app.mock.posted.lock().unwrap().len(), 1);
+    assert_redirect(&app.get("/guilds/1/greetings/test", &logged_in()).await, back);
+}